@@ -0,0 +1,3 @@
+-- TOTP secret waiting for its first code during 2FA enrollment; moved to user_otp_url once confirmed
+ALTER TABLE users
+    ADD COLUMN user_otp_pending_url VARCHAR(255) NULL AFTER user_otp_url;
@@ -0,0 +1,12 @@
+-- Issued refresh tokens; a token used twice revokes its whole family
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    jti CHAR(36) NOT NULL PRIMARY KEY,
+    family_id CHAR(36) NOT NULL,
+    user_id INT NOT NULL,
+    expires_at DATETIME NOT NULL,
+    used_at DATETIME NULL,
+    revoked_at DATETIME NULL,
+    created_at DATETIME NULL,
+    KEY idx_refresh_tokens_family (family_id),
+    KEY idx_refresh_tokens_user (user_id)
+);
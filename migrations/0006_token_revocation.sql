@@ -0,0 +1,12 @@
+-- Bumped by "log out everywhere"; tokens carrying an older epoch are rejected
+ALTER TABLE users
+    ADD COLUMN session_epoch INT NOT NULL DEFAULT 0 AFTER password_version;
+
+-- Access tokens revoked by logout, kept until they would have expired
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti CHAR(36) NOT NULL PRIMARY KEY,
+    user_id INT NOT NULL,
+    expires_at DATETIME NOT NULL,
+    created_at DATETIME NULL,
+    KEY idx_revoked_tokens_expires (expires_at)
+);
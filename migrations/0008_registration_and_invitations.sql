@@ -0,0 +1,24 @@
+-- Email verification tokens for registration; only the SHA-256 of the emailed token is stored
+CREATE TABLE IF NOT EXISTS email_verifications (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    expires_at DATETIME NOT NULL,
+    used_at DATETIME NULL,
+    created_at DATETIME NULL,
+    UNIQUE KEY uq_email_verifications_token (token_hash),
+    KEY idx_email_verifications_user (user_id)
+);
+
+-- Shop invitations are user_shops rows; us_invite: 1 pending, 2 accepted, 3 declined, 4 cancelled
+-- user_id stays NULL until the invitee registers; phone invitations are claimed with the SMS code
+ALTER TABLE user_shops
+    MODIFY COLUMN user_id INT NULL,
+    ADD COLUMN us_invite_email VARCHAR(255) NULL,
+    ADD COLUMN us_invite_tel VARCHAR(20) NULL,
+    ADD COLUMN us_invite_code_hash CHAR(64) NULL,
+    ADD COLUMN us_invited_by INT NULL,
+    ADD COLUMN us_invited_at DATETIME NULL,
+    ADD COLUMN us_responded_at DATETIME NULL,
+    ADD KEY idx_user_shops_invite_email (us_invite_email),
+    ADD KEY idx_user_shops_invite_code (us_invite_code_hash);
@@ -0,0 +1,18 @@
+-- Permission codes ("order.delete") granted per shop role; shop owners have every permission
+CREATE TABLE IF NOT EXISTS permissions (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    permission_code VARCHAR(50) NOT NULL,
+    permission_name VARCHAR(255) NOT NULL,
+    UNIQUE KEY uq_permissions_code (permission_code)
+);
+
+CREATE TABLE IF NOT EXISTS shop_role_permissions (
+    shop_role_id INT NOT NULL,
+    permission_id INT NOT NULL,
+    PRIMARY KEY (shop_role_id, permission_id)
+);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('order.create', 'Create orders'),
+    ('order.delete', 'Delete orders'),
+    ('user.invite', 'Invite users to the shop');
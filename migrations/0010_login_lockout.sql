@@ -0,0 +1,20 @@
+-- Failed password count and lockout per user
+ALTER TABLE users
+    ADD COLUMN user_failed_login_count INT NOT NULL DEFAULT 0,
+    ADD COLUMN user_last_failed_login_at DATETIME NULL,
+    ADD COLUMN user_locked_until DATETIME NULL;
+
+-- Every login attempt (UTC), also for unknown usernames; failures are counted per IP
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NULL,
+    username VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45) NOT NULL,
+    la_success TINYINT(1) NOT NULL,
+    la_failure_reason VARCHAR(50) NULL,
+    created_at DATETIME NOT NULL,
+    KEY idx_login_attempts_ip (ip_address, created_at)
+);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('user.unlock', 'Unlock locked user accounts');
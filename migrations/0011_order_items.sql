@@ -0,0 +1,10 @@
+-- Order lines, written in the same transaction as the order
+CREATE TABLE IF NOT EXISTS order_items (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    order_id INT NOT NULL,
+    product_id INT NOT NULL,
+    oi_quantity INT NOT NULL,
+    oi_price DOUBLE NOT NULL,
+    oi_total DOUBLE NOT NULL,
+    KEY idx_order_items_order (order_id)
+);
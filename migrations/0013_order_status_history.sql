@@ -0,0 +1,17 @@
+-- Every status change of an order; order_status: 1 draft, 2 confirmed, 3 paid, 4 partially paid,
+-- 5 cancelled, 6 refunded
+CREATE TABLE IF NOT EXISTS order_status_history (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    order_id INT NOT NULL,
+    osh_from_status TINYINT NULL,
+    osh_to_status TINYINT NOT NULL,
+    osh_reason VARCHAR(255) NULL,
+    changed_by INT NOT NULL,
+    created_at DATETIME NOT NULL,
+    KEY idx_order_status_history_order (order_id)
+);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('order.confirm', 'Confirm orders'),
+    ('order.cancel', 'Cancel unpaid orders'),
+    ('order.void', 'Void paid orders');
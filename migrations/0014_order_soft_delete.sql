@@ -0,0 +1,23 @@
+-- Deleted orders are kept and hidden; restore clears these columns
+ALTER TABLE orders
+    ADD COLUMN deleted_at DATETIME NULL,
+    ADD COLUMN deleted_by INT NULL,
+    ADD COLUMN delete_reason VARCHAR(255) NULL,
+    ADD KEY idx_orders_deleted_at (shop_id, deleted_at);
+
+-- Who did what to which record, e.g. al_entity "order", al_action "delete"
+CREATE TABLE IF NOT EXISTS audit_logs (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    shop_id INT NOT NULL,
+    user_id INT NOT NULL,
+    al_entity VARCHAR(50) NOT NULL,
+    al_entity_id INT NOT NULL,
+    al_action VARCHAR(50) NOT NULL,
+    al_detail TEXT NULL,
+    created_at DATETIME NOT NULL,
+    KEY idx_audit_logs_entity (al_entity, al_entity_id)
+);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('order.restore', 'Restore deleted orders'),
+    ('order.view_deleted', 'List deleted orders');
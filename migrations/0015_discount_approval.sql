@@ -0,0 +1,10 @@
+-- Item discounts and the manager who approved a discount above the staff role's cap
+ALTER TABLE order_items
+    ADD COLUMN oi_discount DOUBLE NOT NULL DEFAULT 0 AFTER oi_price;
+
+ALTER TABLE orders
+    ADD COLUMN discount_approved_by INT NULL;
+
+-- bcrypt hash of the manager override PIN
+ALTER TABLE users
+    ADD COLUMN user_pin VARCHAR(255) NULL;
@@ -0,0 +1,13 @@
+-- Money is stored as exact baht and satang
+ALTER TABLE orders
+    MODIFY COLUMN order_total DECIMAL(12,2) NOT NULL,
+    MODIFY COLUMN order_discount DECIMAL(12,2) NOT NULL,
+    MODIFY COLUMN order_net DECIMAL(12,2) NOT NULL;
+
+ALTER TABLE order_items
+    MODIFY COLUMN oi_price DECIMAL(12,2) NOT NULL,
+    MODIFY COLUMN oi_discount DECIMAL(12,2) NOT NULL DEFAULT 0,
+    MODIFY COLUMN oi_total DECIMAL(12,2) NOT NULL;
+
+ALTER TABLE products
+    MODIFY COLUMN product_price DECIMAL(12,2) NOT NULL;
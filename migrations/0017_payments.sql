@@ -0,0 +1,21 @@
+-- One row per tender; the tenders of one payment call share a receipt number
+-- payment_method: 1 cash, 2 card, 3 bank transfer, 4 PromptPay
+CREATE TABLE IF NOT EXISTS payments (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    order_id INT NOT NULL,
+    shop_id INT NOT NULL,
+    payment_receipt_number VARCHAR(50) NOT NULL,
+    payment_method TINYINT NOT NULL,
+    payment_amount DECIMAL(12,2) NOT NULL,
+    payment_tendered DECIMAL(12,2) NOT NULL,
+    payment_change DECIMAL(12,2) NOT NULL DEFAULT 0,
+    payment_reference VARCHAR(100) NULL,
+    payment_is_deposit TINYINT(1) NOT NULL DEFAULT 0,
+    created_by INT NOT NULL,
+    created_at DATETIME NOT NULL,
+    KEY idx_payments_order (order_id),
+    KEY idx_payments_receipt (shop_id, payment_receipt_number)
+);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('order.payment', 'Take payments');
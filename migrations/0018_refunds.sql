@@ -0,0 +1,29 @@
+-- Refunds, one credit note each; refund_method uses the payment method codes
+CREATE TABLE IF NOT EXISTS refunds (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    order_id INT NOT NULL,
+    shop_id INT NOT NULL,
+    refund_number VARCHAR(50) NOT NULL,
+    refund_method TINYINT NOT NULL,
+    refund_amount DECIMAL(12,2) NOT NULL,
+    refund_reason VARCHAR(255) NOT NULL,
+    created_by INT NOT NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE KEY uq_refunds_number (shop_id, refund_number),
+    KEY idx_refunds_order (order_id)
+);
+
+-- Refunded units per order line
+CREATE TABLE IF NOT EXISTS refund_items (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    refund_id INT NOT NULL,
+    order_item_id INT NOT NULL,
+    ri_quantity INT NOT NULL,
+    ri_amount DECIMAL(12,2) NOT NULL,
+    ri_restock TINYINT(1) NOT NULL DEFAULT 0,
+    KEY idx_refund_items_refund (refund_id),
+    KEY idx_refund_items_order_item (order_item_id)
+);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('order.refund', 'Refund orders and issue credit notes');
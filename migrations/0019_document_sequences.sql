@@ -0,0 +1,37 @@
+-- Running document numbers per shop and document type (DocumentSequenceModel)
+-- ds_type: 1 order, 2 receipt, 3 tax invoice, 4 credit note, 5 customer code
+-- ds_reset: 0 never, 1 yearly, 2 monthly
+CREATE TABLE IF NOT EXISTS document_sequences (
+    shop_id INT NOT NULL,
+    ds_type TINYINT NOT NULL,
+    ds_prefix VARCHAR(10) NOT NULL,
+    ds_reset TINYINT NOT NULL DEFAULT 1,
+    ds_padding INT NOT NULL DEFAULT 5,
+    ds_period VARCHAR(6) NOT NULL DEFAULT '',
+    ds_last_number INT NOT NULL DEFAULT 0,
+    updated_at DATETIME NULL,
+    PRIMARY KEY (shop_id, ds_type)
+);
+
+-- Every number handed out; the unique key makes a transaction that would reissue one fail
+CREATE TABLE IF NOT EXISTS issued_document_numbers (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    shop_id INT NOT NULL,
+    ds_type TINYINT NOT NULL,
+    idn_number VARCHAR(50) NOT NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE KEY uq_issued_document_numbers (shop_id, ds_type, idn_number)
+);
+
+-- Numbers issued before sequences existed
+INSERT IGNORE INTO issued_document_numbers (shop_id, ds_type, idn_number, created_at)
+SELECT shop_id, 1, order_code, NOW() FROM orders WHERE order_code <> '';
+
+INSERT IGNORE INTO issued_document_numbers (shop_id, ds_type, idn_number, created_at)
+SELECT shop_id, 2, payment_receipt_number, NOW() FROM payments WHERE payment_receipt_number <> '';
+
+INSERT IGNORE INTO issued_document_numbers (shop_id, ds_type, idn_number, created_at)
+SELECT shop_id, 4, refund_number, NOW() FROM refunds WHERE refund_number <> '';
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('shop.settings', 'Change shop settings and document numbering');
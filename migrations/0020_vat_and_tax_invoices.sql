@@ -0,0 +1,40 @@
+-- product_vat_type / oi_vat_type: 1 VAT included, 2 VAT added on top, 3 exempt
+ALTER TABLE products
+    ADD COLUMN product_vat_type TINYINT NOT NULL DEFAULT 1;
+
+ALTER TABLE orders
+    ADD COLUMN order_vatable DECIMAL(12,2) NOT NULL DEFAULT 0 AFTER order_net,
+    ADD COLUMN order_vat DECIMAL(12,2) NOT NULL DEFAULT 0 AFTER order_vatable,
+    ADD COLUMN order_exempt DECIMAL(12,2) NOT NULL DEFAULT 0 AFTER order_vat;
+
+-- oi_net is what the customer pays for the line: after its share of the bill discount, VAT included
+ALTER TABLE order_items
+    ADD COLUMN oi_vat_type TINYINT NOT NULL DEFAULT 1,
+    ADD COLUMN oi_vat DECIMAL(12,2) NOT NULL DEFAULT 0,
+    ADD COLUMN oi_net DECIMAL(12,2) NOT NULL DEFAULT 0;
+
+-- Lines of orders taken before VAT was tracked are refunded at their total
+UPDATE order_items SET oi_net = oi_total;
+
+ALTER TABLE shops
+    ADD COLUMN shop_tax_id VARCHAR(13) NULL,
+    ADD COLUMN shop_branch_code VARCHAR(5) NULL;
+
+-- Full tax invoice of an order, at most one
+CREATE TABLE IF NOT EXISTS tax_invoices (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    order_id INT NOT NULL,
+    shop_id INT NOT NULL,
+    ti_number VARCHAR(50) NOT NULL,
+    ti_customer_name VARCHAR(255) NOT NULL,
+    ti_customer_tax_id VARCHAR(13) NOT NULL,
+    ti_customer_branch_code VARCHAR(5) NOT NULL,
+    ti_customer_address VARCHAR(500) NOT NULL,
+    issued_by INT NOT NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE KEY uq_tax_invoices_order (order_id),
+    UNIQUE KEY uq_tax_invoices_number (shop_id, ti_number)
+);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('order.tax_invoice', 'Issue full tax invoices');
@@ -0,0 +1,3 @@
+-- Rendered documents are kept in S3; only the permission to store them is new
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('order.document', 'Store printed documents');
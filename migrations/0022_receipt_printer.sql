@@ -0,0 +1,7 @@
+-- Thermal receipt logo (https URL) and the shop's network printer ("ip" or "ip:port")
+ALTER TABLE shops
+    ADD COLUMN shop_logo_url VARCHAR(500) NULL,
+    ADD COLUMN shop_printer_address VARCHAR(100) NULL;
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('order.print', 'Send documents to the network printer');
@@ -0,0 +1,3 @@
+-- PromptPay ID: mobile number, tax ID or e-wallet ID
+ALTER TABLE shops
+    ADD COLUMN shop_promptpay_id VARCHAR(15) NULL;
@@ -0,0 +1,17 @@
+-- Customer code (HN) from the shop's customer sequence, Thai national ID and soft delete
+-- Customers created before codes existed keep an empty code
+ALTER TABLE customers
+    ADD COLUMN customer_code VARCHAR(50) NOT NULL DEFAULT '' AFTER shop_id,
+    ADD COLUMN customer_national_id VARCHAR(13) NULL,
+    ADD COLUMN customer_birthdate DATE NULL,
+    ADD COLUMN customer_address VARCHAR(500) NULL,
+    ADD COLUMN deleted_at DATETIME NULL,
+    ADD COLUMN deleted_by INT NULL,
+    ADD KEY idx_customers_code (shop_id, customer_code),
+    ADD KEY idx_customers_tel (shop_id, customer_tel),
+    ADD KEY idx_customers_national_id (shop_id, customer_national_id);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('customer.create', 'Create customers'),
+    ('customer.update', 'Edit customers'),
+    ('customer.delete', 'Delete customers');
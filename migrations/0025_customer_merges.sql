@@ -0,0 +1,22 @@
+-- Customer merged away into another one (soft deleted at the same time)
+ALTER TABLE customers
+    ADD COLUMN merged_into INT NULL;
+
+-- Merges with the row IDs re-pointed per table (cm_moved, JSON) so they can be undone
+-- until cm_undo_until (UTC)
+CREATE TABLE IF NOT EXISTS customer_merges (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    shop_id INT NOT NULL,
+    cm_target_id INT NOT NULL,
+    cm_source_id INT NOT NULL,
+    cm_moved TEXT NOT NULL,
+    merged_by INT NOT NULL,
+    merged_at DATETIME NOT NULL,
+    cm_undo_until DATETIME NOT NULL,
+    undone_at DATETIME NULL,
+    undone_by INT NULL,
+    KEY idx_customer_merges_shop (shop_id, undone_at)
+);
+
+INSERT IGNORE INTO permissions (permission_code, permission_name) VALUES
+    ('customer.merge', 'Merge duplicate customers');
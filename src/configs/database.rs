@@ -1,6 +1,8 @@
 use sqlx::{MySql, Pool, MySqlPool};
 use std::env;
+use std::sync::Arc;
 use anyhow::Result;
+use crate::libs::{MailSender, SmtpMailSender};
 
 /// Application state containing all database connections
 /// Equivalent to Go's configs/database.go with DB1, DB2, DBL1, DBL2
@@ -10,6 +12,7 @@ pub struct AppState {
     pub db2: Pool<MySql>,   // Main read replica
     pub dbl1: Pool<MySql>,  // Logging write database
     pub dbl2: Pool<MySql>,  // Logging read replica
+    pub mailer: Arc<dyn MailSender>, // Outgoing email (SMTP)
 }
 
 /// Database configuration structure
@@ -66,6 +69,7 @@ pub async fn init_databases() -> Result<AppState> {
         db2,
         dbl1,
         dbl2,
+        mailer: Arc::new(SmtpMailSender),
     })
 }
 
@@ -1,18 +1,60 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{State, Query, ConnectInfo},
+    http::{StatusCode, HeaderMap},
+    Extension,
     Json,
 };
 use crate::configs::AppState;
-use crate::structs::{LoginRequest, LoginResponse, ApiResponse, ShopAccount};
-use crate::models::UserModel;
-use crate::middlewares::{hash_password, verify_password, create_access_token, create_refresh_token};
+use crate::structs::{
+    LoginRequest, LoginResponse, LogoutRequest, ApiResponse, ShopAccount, SwitchShopRequest, TokenResponse,
+    OtpSetupRequest, OtpSetupResponse, OtpVerifyRequest,
+    ChangePasswordRequest, ForgotPasswordRequest, ResetPasswordRequest,
+    RegisterRequest, RegisterResponse, VerifyEmailQuery, ResendVerificationRequest,
+};
+use crate::models::{
+    User, UserShop, UserModel, RefreshTokenModel, NewPasswordReset, PasswordResetModel,
+    EmailVerificationModel, NewEmailVerification, UserShopModel, LoginAttemptModel,
+};
+use crate::middlewares::{
+    hash_password, check_password, dummy_password_check, PasswordCheck,
+    create_access_token, create_refresh_token, refresh_token_expire_hours,
+    decode_refresh_token, revoke_token, invalidate_user_auth_status,
+    hash_token,
+    AuthUser, RefreshTokenClaims, TokenSubject,
+    login_guard::{
+        check_login_lock, client_ip, LoginBlock, IP_WINDOW_MINUTES, LOCKOUT_MINUTES,
+        MAX_FAILED_LOGINS, MAX_IP_FAILURES,
+    },
+};
+use crate::libs::{
+    generate_otp_url, verify_otp, otp_secret_from_url, render_qr_png_base64,
+    send_password_reset_email, send_verification_email, send_welcome_email,
+    send_account_locked_email, send_account_exists_email,
+};
+use std::net::SocketAddr;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
 use validator::Validate;
 
+/// Length of the random password reset token sent by email
+const PASSWORD_RESET_TOKEN_LENGTH: usize = 48;
+
+/// Password reset link lifetime
+const PASSWORD_RESET_EXPIRE_HOURS: i64 = 1;
+
+/// Length of the random email verification token
+const EMAIL_VERIFICATION_TOKEN_LENGTH: usize = 48;
+
+/// Email verification link lifetime
+const EMAIL_VERIFICATION_EXPIRE_HOURS: i64 = 24;
+
 /// Login handler
 /// Equivalent to Go's Login function in controllers/auth.go
+/// Failed attempts are throttled per IP and per account; see middlewares::login_guard
 pub async fn login(
     State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Json<ApiResponse<LoginResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
     // Validate request
@@ -23,27 +65,94 @@ pub async fn login(
         ));
     }
 
-    // Hash password for comparison
-    let password_hash = hash_password(&payload.password)
+    let ip_address = client_ip(&headers, addr);
+
+    // Per-IP throttle, applied before touching the account
+    let ip_failures = LoginAttemptModel::count_recent_ip_failures(&state.dbl1, &ip_address, IP_WINDOW_MINUTES)
+        .await
         .map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(format!("Password hashing failed: {}", e))),
+                Json(ApiResponse::error(format!("Failed to check login attempts: {}", e))),
             )
         })?;
 
+    if ip_failures >= MAX_IP_FAILURES {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ApiResponse::error("Too many failed login attempts, please try again later".to_string())),
+        ));
+    }
+
+    // Same error for unknown email and wrong password to avoid user enumeration
+    let invalid_credentials = || {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::error("Invalid username or password".to_string())),
+        )
+    };
+
     // Get user from database
-    let user = UserModel::get_user_for_login(&state.db1, &payload.username, &password_hash)
+    let user = UserModel::get_user_by_email(&state.db1, &payload.username)
         .await
-        .map_err(|_| {
+        .map_err(|e| {
             (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error("Invalid username or password".to_string())),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch user: {}", e))),
             )
         })?;
 
+    let user = match user {
+        Some(user) => user,
+        None => {
+            dummy_password_check(&payload.password);
+            record_login_attempt(&state, None, &payload.username, &ip_address, Some("unknown_user")).await;
+            return Err(invalid_credentials());
+        }
+    };
+
+    // Per-account lockout and backoff
+    let login_lock = UserModel::get_login_lock(&state.db1, user.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to check login attempts: {}", e))),
+            )
+        })?;
+
+    if let Some(block) = login_lock.as_ref().and_then(|lock| check_login_lock(lock, Utc::now().naive_utc())) {
+        record_login_attempt(&state, Some(user.id), &payload.username, &ip_address, Some("throttled")).await;
+        return Err(login_block_error(block));
+    }
+
+    // Verify password (bcrypt, or legacy hash from the Go system)
+    let password_check = check_password(&payload.password, &user.user_password)
+        .unwrap_or_else(|e| {
+            tracing::warn!("Password check failed for user {}: {}", user.id, e);
+            PasswordCheck::Invalid
+        });
+
+    if password_check == PasswordCheck::Invalid {
+        register_login_failure(&state, &user, &payload.username, &ip_address, "invalid_password").await;
+        return Err(invalid_credentials());
+    }
+
+    // Transparently upgrade legacy hashes to bcrypt
+    if password_check == PasswordCheck::ValidLegacy {
+        match hash_password(&payload.password) {
+            Ok(new_hash) => {
+                if let Err(e) = UserModel::upgrade_password_hash(&state.db1, user.id, &new_hash).await {
+                    tracing::warn!("Failed to upgrade password hash for user {}: {}", user.id, e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to hash password for user {}: {}", user.id, e),
+        }
+    }
+
     // Check if user is active
     if user.user_is_active == 0 {
+        record_login_attempt(&state, Some(user.id), &payload.username, &ip_address, Some("inactive")).await;
         return Err((
             StatusCode::FORBIDDEN,
             Json(ApiResponse::error("User account is deactivated".to_string())),
@@ -54,10 +163,23 @@ pub async fn login(
     if let Some(otp_url) = &user.user_otp_url {
         if !otp_url.is_empty() {
             if let Some(otp_code) = &payload.otp_code {
-                // TODO: Verify OTP code using totp-rs
-                // For now, just placeholder
-                tracing::info!("OTP verification needed for user {}", user.id);
+                let is_valid = verify_otp(otp_url, otp_code).map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ApiResponse::error(format!("OTP verification failed: {}", e))),
+                    )
+                })?;
+
+                if !is_valid {
+                    register_login_failure(&state, &user, &payload.username, &ip_address, "invalid_otp").await;
+                    return Err((
+                        StatusCode::UNAUTHORIZED,
+                        Json(ApiResponse::error("Invalid OTP code".to_string())),
+                    ));
+                }
             } else {
+                // Counted like a wrong code, so the OTP prompt cannot be used to test passwords
+                register_login_failure(&state, &user, &payload.username, &ip_address, "missing_otp").await;
                 return Err((
                     StatusCode::UNAUTHORIZED,
                     Json(ApiResponse::error("OTP code required".to_string())),
@@ -66,70 +188,744 @@ pub async fn login(
         }
     }
 
+    // Registered users must verify their email before logging in
+    let is_unverified = EmailVerificationModel::is_pending(&state.db1, user.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to check email verification: {}", e))),
+            )
+        })?;
+
+    if is_unverified {
+        record_login_attempt(&state, Some(user.id), &payload.username, &ip_address, Some("unverified_email")).await;
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error("Email address is not verified".to_string())),
+        ));
+    }
+
+    let shop_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to fetch shops: {}", e))),
+        )
+    };
+
+    // Attach invitations sent to this (verified) email before the account existed
+    UserShopModel::claim_invites(&state.db1, user.id, &user.user_email)
+        .await
+        .map_err(shop_error)?;
+
     // Get shop accounts for user
-    // For now, return empty vec - in real implementation, query user_shops table
-    let shops: Vec<ShopAccount> = vec![];
+    let mut user_shops = UserModel::get_user_shops(&state.db1, user.id)
+        .await
+        .map_err(shop_error)?;
+
+    // First login of an invited user: accept pending invitations
+    if user_shops.is_empty() {
+        let accepted = UserShopModel::accept_all_invites(&state.db1, user.id, &user.user_email)
+            .await
+            .map_err(shop_error)?;
+
+        if accepted > 0 {
+            user_shops = UserModel::get_user_shops(&state.db1, user.id)
+                .await
+                .map_err(shop_error)?;
+
+            let name = format!("{} {}", user.user_fname, user.user_lname);
+            if let Err(e) = send_welcome_email(state.mailer.as_ref(), &user.user_email, &name).await {
+                tracing::error!("Failed to send welcome email to user {}: {}", user.id, e);
+            }
+        }
+    }
+
+    // Use the requested shop, or fall back to the first shop
+    let selected_shop = match payload.shop_id {
+        Some(shop_id) => user_shops.iter().find(|shop| shop.shop_id == shop_id),
+        None => user_shops.first(),
+    };
+    let Some(selected_shop) = selected_shop else {
+        record_login_attempt(&state, Some(user.id), &payload.username, &ip_address, Some("no_shop")).await;
+        let message = if payload.shop_id.is_some() {
+            "User does not belong to this shop"
+        } else {
+            "User has no active shop"
+        };
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error(message.to_string())),
+        ));
+    };
+
+    // Every check passed: clear the failed login counter
+    if login_lock.is_some_and(|lock| lock.user_failed_login_count > 0) {
+        if let Err(e) = UserModel::reset_failed_logins(&state.db1, user.id).await {
+            tracing::warn!("Failed to reset failed logins for user {}: {}", user.id, e);
+        }
+    }
+    record_login_attempt(&state, Some(user.id), &payload.username, &ip_address, None).await;
 
     // Generate tokens
-    let access_token = create_access_token(
-        user.id,
-        1, // Default shop_id, should come from first shop or selected shop
-        1, // shop_mother_id
-        1, // role_id
-        1, // shop_role_id
-        user.user_email.clone(),
-        0, // sr_discount_type_id
-        0.0, // sr_discount
-        user.password_version,
+    let tokens = issue_tokens(&state, &user, selected_shop, None).await?;
+
+    let shop_id = selected_shop.shop_id;
+    let shops: Vec<ShopAccount> = user_shops
+        .into_iter()
+        .map(|shop| ShopAccount {
+            shop_id: shop.shop_id,
+            shop_name: shop.shop_name,
+            shop_role_id: shop.shop_role_id,
+            role_id: shop.role_id,
+        })
+        .collect();
+
+    // Prepare response
+    let response = LoginResponse {
+        user_id: user.id,
+        email: user.user_email.clone(),
+        fname: user.user_fname,
+        lname: user.user_lname,
+        shop_id,
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        shops,
+    };
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Record a login attempt in the logging database; failures are logged, not returned
+/// failure_reason is None for a successful attempt
+async fn record_login_attempt(
+    state: &AppState,
+    user_id: Option<i32>,
+    username: &str,
+    ip_address: &str,
+    failure_reason: Option<&str>,
+) {
+    if let Err(e) = LoginAttemptModel::record_attempt(
+        &state.dbl1,
+        user_id,
+        username,
+        ip_address,
+        failure_reason.is_none(),
+        failure_reason,
+    )
+    .await
+    {
+        tracing::error!("Failed to record login attempt for {}: {}", username, e);
+    }
+}
+
+/// Count a failed login against the account and notify the user when it gets locked
+async fn register_login_failure(
+    state: &AppState,
+    user: &User,
+    username: &str,
+    ip_address: &str,
+    failure_reason: &str,
+) {
+    record_login_attempt(state, Some(user.id), username, ip_address, Some(failure_reason)).await;
+
+    let lock = match UserModel::register_failed_login(&state.db1, user.id, MAX_FAILED_LOGINS, LOCKOUT_MINUTES).await {
+        Ok(lock) => lock,
+        Err(e) => {
+            tracing::error!("Failed to count failed login for user {}: {}", user.id, e);
+            return;
+        }
+    };
+
+    // Only the failure that locks the account sends the email
+    if lock.user_failed_login_count == MAX_FAILED_LOGINS {
+        tracing::warn!("User {} locked after {} failed logins from {}", user.id, lock.user_failed_login_count, ip_address);
+
+        if let Err(e) = send_account_locked_email(state.mailer.as_ref(), &user.user_email, LOCKOUT_MINUTES, ip_address).await {
+            tracing::error!("Failed to send lockout email to user {}: {}", user.id, e);
+        }
+    }
+}
+
+fn login_block_error(block: LoginBlock) -> (StatusCode, Json<ApiResponse<()>>) {
+    match block {
+        LoginBlock::Locked { retry_after_secs } => (
+            StatusCode::LOCKED,
+            Json(ApiResponse::error(format!(
+                "Account is temporarily locked, try again in {} minutes",
+                (retry_after_secs + 59) / 60
+            ))),
+        ),
+        LoginBlock::Backoff { retry_after_secs } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ApiResponse::error(format!(
+                "Too many failed login attempts, try again in {} seconds",
+                retry_after_secs
+            ))),
+        ),
+    }
+}
+
+/// Register handler
+/// Creates the account and emails a verification link; login is blocked until verified
+pub async fn register(
+    State(state): State<AppState>,
+    Json(payload): Json<RegisterRequest>,
+) -> Result<Json<ApiResponse<RegisterResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Registration failed: {}", e))),
+        )
+    };
+
+    // Hashed up front so a taken email answers as slowly as a new one
+    let password_hash = hash_password(&payload.password).map_err(db_error)?;
+
+    let existing = UserModel::get_user_by_email(&state.db1, &payload.email)
+        .await
+        .map_err(db_error)?;
+
+    // A taken email gets the same response; only its owner learns of the attempt
+    if let Some(existing) = existing {
+        if let Err(e) = send_account_exists_email(state.mailer.as_ref(), &existing.user_email).await {
+            tracing::error!("Failed to send account exists email to user {}: {}", existing.id, e);
+        }
+    } else {
+        let user_id = UserModel::create_user(
+            &state.db1,
+            &payload.email,
+            &password_hash,
+            &payload.fname,
+            &payload.lname,
+            &payload.tel,
+        )
+        .await
+        .map_err(db_error)?;
+
+        send_email_verification(&state, user_id, &payload.email)
+            .await
+            .map_err(db_error)?;
+    }
+
+    let response = RegisterResponse {
+        email: payload.email,
+    };
+
+    Ok(Json(ApiResponse::success_with_message(
+        response,
+        "Please check your email to finish registering".to_string(),
+    )))
+}
+
+/// Verify email handler (link from verification email)
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Email verification failed: {}", e))),
+        )
+    };
+
+    let user_id = EmailVerificationModel::consume_verification(&state.db1, &hash_token(&query.token))
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Invalid or expired verification link".to_string())),
+            )
+        })?;
+
+    if let Some(user) = UserModel::get_user_account(&state.db1, user_id).await.map_err(db_error)? {
+        UserShopModel::claim_invites(&state.db1, user.id, &user.user_email)
+            .await
+            .map_err(db_error)?;
+
+        let name = format!("{} {}", user.user_fname, user.user_lname);
+        if let Err(e) = send_welcome_email(state.mailer.as_ref(), &user.user_email, &name).await {
+            tracing::error!("Failed to send welcome email to user {}: {}", user.id, e);
+        }
+    }
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Email verified successfully".to_string(),
+    )))
+}
+
+/// Resend verification email handler
+/// Always returns the same response so it cannot be used to discover accounts
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<ResendVerificationRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to resend verification: {}", e))),
+        )
+    };
+
+    let user = UserModel::get_user_by_email(&state.db1, &payload.email)
+        .await
+        .map_err(db_error)?;
+
+    if let Some(user) = user {
+        let is_pending = EmailVerificationModel::is_pending(&state.db1, user.id)
+            .await
+            .map_err(db_error)?;
+
+        if is_pending {
+            send_email_verification(&state, user.id, &user.user_email)
+                .await
+                .map_err(db_error)?;
+        }
+    }
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "If the email needs verification, a new link has been sent".to_string(),
+    )))
+}
+
+/// Create a verification token and email the link
+async fn send_email_verification(state: &AppState, user_id: i32, email: &str) -> anyhow::Result<()> {
+    let expires_at = (Utc::now() + Duration::hours(EMAIL_VERIFICATION_EXPIRE_HOURS)).naive_utc();
+    let verification = NewEmailVerification::generate(EMAIL_VERIFICATION_TOKEN_LENGTH, expires_at);
+
+    EmailVerificationModel::create_verification(
+        &state.db1,
+        user_id,
+        &verification.token_hash,
+        verification.expires_at,
     )
-    .map_err(|e| {
+    .await?;
+
+    if let Err(e) = send_verification_email(state.mailer.as_ref(), email, &verification.token).await {
+        tracing::error!("Failed to send verification email to user {}: {}", user_id, e);
+    }
+
+    Ok(())
+}
+
+/// Switch shop handler
+/// Re-issues tokens for another shop the user belongs to
+pub async fn switch_shop(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<SwitchShopRequest>,
+) -> Result<Json<ApiResponse<TokenResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let user = UserModel::get_user_account(&state.db1, auth.user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch user: {}", e))),
+            )
+        })?
+        .filter(|user| user.user_is_active == 1)
+        .ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::error("User account is deactivated".to_string())),
+            )
+        })?;
+
+    let shop = UserModel::get_user_shop(&state.db1, user.id, payload.shop_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch shop: {}", e))),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::FORBIDDEN,
+                Json(ApiResponse::error("User does not belong to this shop".to_string())),
+            )
+        })?;
+
+    let tokens = issue_tokens(&state, &user, &shop, None).await?;
+
+    Ok(Json(ApiResponse::success(tokens)))
+}
+
+/// Refresh token handler
+/// Rotates the refresh token; reusing an already-rotated token revokes its whole family
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<RefreshTokenClaims>,
+) -> Result<Json<ApiResponse<TokenResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let unauthorized = |message: &str| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::error(message.to_string())),
+        )
+    };
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Token refresh failed: {}", e))),
+        )
+    };
+
+    let record = RefreshTokenModel::get_token(&state.dbl1, &claims.jti)
+        .await
+        .map_err(db_error)?
+        .filter(|record| record.user_id == claims.user_id && record.family_id == claims.family_id)
+        .ok_or_else(|| unauthorized("Invalid refresh token"))?;
+
+    // A used or revoked token being presented again means it was stolen
+    let is_first_use = record.used_at.is_none()
+        && record.revoked_at.is_none()
+        && RefreshTokenModel::mark_used(&state.dbl1, &record.jti)
+            .await
+            .map_err(db_error)?;
+
+    if !is_first_use {
+        tracing::warn!(
+            "Refresh token reuse detected for user {}, revoking family {}",
+            record.user_id,
+            record.family_id
+        );
+        RefreshTokenModel::revoke_family(&state.dbl1, &record.family_id)
+            .await
+            .map_err(db_error)?;
+        return Err(unauthorized("Refresh token has been revoked"));
+    }
+
+    // Re-check the user is still active and has not changed password
+    let user = UserModel::get_user_account(&state.db1, claims.user_id)
+        .await
+        .map_err(db_error)?
+        .filter(|user| user.user_is_active == 1)
+        .ok_or_else(|| unauthorized("User account is deactivated"))?;
+
+    if user.password_version != claims.password_version
+        || user.session_epoch != claims.session_epoch
+    {
+        RefreshTokenModel::revoke_family(&state.dbl1, &record.family_id)
+            .await
+            .map_err(db_error)?;
+        return Err(unauthorized("Session is no longer valid, please log in again"));
+    }
+
+    // Reload shop membership so role or discount changes take effect
+    let shop = UserModel::get_user_shop(&state.db1, user.id, claims.shop_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| unauthorized("User no longer belongs to this shop"))?;
+
+    let tokens = issue_tokens(&state, &user, &shop, Some(record.family_id)).await?;
+
+    Ok(Json(ApiResponse::success(tokens)))
+}
+
+/// Change password handler
+/// Verifies the old password, bumps password_version and returns fresh tokens
+/// for the current shop (all other sessions are invalidated)
+pub async fn change_password(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<Json<ApiResponse<TokenResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    if payload.old_password == payload.new_password {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("New password must be different from old password".to_string())),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Password change failed: {}", e))),
+        )
+    };
+
+    let user = UserModel::get_user_account(&state.db1, auth.user_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("User not found".to_string())),
+            )
+        })?;
+
+    let password_check = check_password(&payload.old_password, &user.user_password)
+        .unwrap_or(PasswordCheck::Invalid);
+
+    if password_check == PasswordCheck::Invalid {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::error("Old password is incorrect".to_string())),
+        ));
+    }
+
+    let new_hash = hash_password(&payload.new_password).map_err(db_error)?;
+    UserModel::update_password(&state.db1, user.id, &new_hash)
+        .await
+        .map_err(db_error)?;
+    invalidate_user_auth_status(user.id);
+
+    // Re-issue tokens with the new password_version for this session
+    let user = UserModel::get_user_account(&state.db1, auth.user_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("User not found".to_string())),
+            )
+        })?;
+
+    let shop = UserModel::get_user_shop(&state.db1, user.id, auth.shop_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::FORBIDDEN,
+                Json(ApiResponse::error("User does not belong to this shop".to_string())),
+            )
+        })?;
+
+    let tokens = issue_tokens(&state, &user, &shop, None).await?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        tokens,
+        "Password changed successfully".to_string(),
+    )))
+}
+
+/// Forgot password handler
+/// Always returns the same response so it cannot be used to discover accounts
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let user = UserModel::get_user_by_email(&state.db1, &payload.email)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch user: {}", e))),
+            )
+        })?;
+
+    if let Some(user) = user.filter(|user| user.user_is_active == 1) {
+        let expires_at = (Utc::now() + Duration::hours(PASSWORD_RESET_EXPIRE_HOURS)).naive_utc();
+        let reset = NewPasswordReset::generate(PASSWORD_RESET_TOKEN_LENGTH, expires_at);
+
+        PasswordResetModel::create_reset(&state.db1, user.id, &reset.token_hash, reset.expires_at)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiResponse::error(format!("Failed to create reset token: {}", e))),
+                )
+            })?;
+
+        if let Err(e) = send_password_reset_email(state.mailer.as_ref(), &user.user_email, &reset.token).await {
+            tracing::error!("Failed to send password reset email to user {}: {}", user.id, e);
+        }
+    }
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "If the email is registered, a reset link has been sent".to_string(),
+    )))
+}
+
+/// Reset password handler
+/// Consumes a single-use reset token and sets the new password
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Password reset failed: {}", e))),
+        )
+    };
+
+    let user_id = PasswordResetModel::consume_reset(&state.db1, &hash_token(&payload.token))
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Invalid or expired reset token".to_string())),
+            )
+        })?;
+
+    let new_hash = hash_password(&payload.new_password).map_err(db_error)?;
+    UserModel::update_password(&state.db1, user_id, &new_hash)
+        .await
+        .map_err(db_error)?;
+    invalidate_user_auth_status(user_id);
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Password has been reset successfully".to_string(),
+    )))
+}
+
+/// Generate access and refresh tokens for a user in a shop
+/// A new refresh token family is started unless one is given (rotation)
+async fn issue_tokens(
+    state: &AppState,
+    user: &User,
+    shop: &UserShop,
+    family_id: Option<String>,
+) -> Result<TokenResponse, (StatusCode, Json<ApiResponse<()>>)> {
+    let subject = TokenSubject {
+        user_id: user.id,
+        shop_id: shop.shop_id,
+        shop_mother_id: shop.shop_mother_id,
+        role_id: shop.role_id,
+        shop_role_id: shop.shop_role_id,
+        user_email: user.user_email.clone(),
+        sr_discount_type_id: shop.sr_discount_type_id,
+        sr_discount: shop.sr_discount,
+        password_version: user.password_version,
+        session_epoch: user.session_epoch,
+    };
+
+    let access_token = create_access_token(&subject).map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ApiResponse::error(format!("Token generation failed: {}", e))),
         )
     })?;
 
-    let refresh_token = create_refresh_token(
-        user.id,
-        1,
-        1,
-        1,
-        1,
-        user.user_email.clone(),
-        0,
-        0.0,
-        user.password_version,
-        1, // user_type
-    )
-    .map_err(|e| {
+    let jti = Uuid::new_v4().to_string();
+    let family_id = family_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let refresh_token = create_refresh_token(&subject, 1, jti.clone(), family_id.clone()).map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ApiResponse::error(format!("Token generation failed: {}", e))),
         )
     })?;
 
-    // Prepare response
-    let response = LoginResponse {
-        user_id: user.id,
-        email: user.user_email.clone(),
-        fname: user.user_fname,
-        lname: user.user_lname,
+    let expires_at = (Utc::now() + Duration::hours(refresh_token_expire_hours())).naive_utc();
+    RefreshTokenModel::create_token(&state.dbl1, &jti, &family_id, user.id, expires_at)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to record refresh token: {}", e))),
+            )
+        })?;
+
+    Ok(TokenResponse {
         access_token,
         refresh_token,
-        shops,
-    };
-
-    Ok(Json(ApiResponse::success(response)))
+    })
 }
 
 /// Logout handler
-pub async fn logout() -> Json<ApiResponse<()>> {
-    // In JWT-based auth, logout is typically handled client-side
-    // Server can maintain a blacklist if needed
-    Json(ApiResponse::success_with_message(
+/// Revokes the current access token and, if given, the refresh token and its family
+pub async fn logout(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    payload: Option<Json<LogoutRequest>>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let logout_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Logout failed: {}", e))),
+        )
+    };
+
+    revoke_token(&state, &auth.jti, auth.user_id, auth.exp)
+        .await
+        .map_err(logout_error)?;
+
+    let refresh_claims = payload
+        .and_then(|Json(payload)| payload.refresh_token)
+        .and_then(|token| decode_refresh_token(&token).ok())
+        .filter(|claims| claims.user_id == auth.user_id);
+
+    if let Some(claims) = refresh_claims {
+        revoke_token(&state, &claims.jti, claims.user_id, claims.exp)
+            .await
+            .map_err(logout_error)?;
+        RefreshTokenModel::revoke_family(&state.dbl1, &claims.family_id)
+            .await
+            .map_err(logout_error)?;
+    }
+
+    Ok(Json(ApiResponse::success_with_message(
         (),
         "Logged out successfully".to_string(),
-    ))
+    )))
+}
+
+/// Logout from all devices
+/// Bumps the user's session epoch so every token issued so far is rejected
+pub async fn logout_all(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    UserModel::increment_session_epoch(&state.db1, auth.user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Logout failed: {}", e))),
+            )
+        })?;
+
+    invalidate_user_auth_status(auth.user_id);
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Logged out from all devices".to_string(),
+    )))
 }
 
 /// Verify token handler
@@ -141,3 +937,190 @@ pub async fn verify_token() -> Json<ApiResponse<()>> {
         "Token is valid".to_string(),
     ))
 }
+
+/// Start 2FA enrollment
+/// Generates a new secret and stores it as pending until the first code is confirmed
+pub async fn setup_otp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<OtpSetupRequest>,
+) -> Result<Json<ApiResponse<OtpSetupResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if payload.user_id != auth.user_id {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error("Cannot set up OTP for another user".to_string())),
+        ));
+    }
+
+    let user = UserModel::get_user_account(&state.db1, auth.user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch user: {}", e))),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("User not found".to_string())),
+            )
+        })?;
+
+    if user.user_otp_url.as_deref().is_some_and(|url| !url.is_empty()) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("OTP is already enabled".to_string())),
+        ));
+    }
+
+    let otp_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("OTP setup failed: {}", e))),
+        )
+    };
+
+    let otp_url = generate_otp_url(&user.user_email).map_err(otp_error)?;
+    let secret = otp_secret_from_url(&otp_url).map_err(otp_error)?;
+    let qr_code = render_qr_png_base64(&otp_url).map_err(otp_error)?;
+
+    UserModel::update_otp_pending_url(&state.db1, user.id, Some(&otp_url))
+        .await
+        .map_err(otp_error)?;
+
+    let response = OtpSetupResponse {
+        otp_url,
+        secret,
+        qr_code,
+    };
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Confirm 2FA enrollment with the first code from the authenticator app
+pub async fn verify_otp_setup(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<OtpVerifyRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if payload.user_id != auth.user_id {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error("Cannot verify OTP for another user".to_string())),
+        ));
+    }
+
+    let pending_url = UserModel::get_otp_pending_url(&state.db1, auth.user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch OTP setup: {}", e))),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("No pending OTP setup".to_string())),
+            )
+        })?;
+
+    let is_valid = verify_otp(&pending_url, &payload.otp_code).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("OTP verification failed: {}", e))),
+        )
+    })?;
+
+    if !is_valid {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::error("Invalid OTP code".to_string())),
+        ));
+    }
+
+    UserModel::update_otp_url(&state.db1, auth.user_id, &pending_url)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to enable OTP: {}", e))),
+            )
+        })?;
+
+    UserModel::update_otp_pending_url(&state.db1, auth.user_id, None)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to enable OTP: {}", e))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "OTP enabled successfully".to_string(),
+    )))
+}
+
+/// Disable 2FA
+/// Requires a valid code from the currently enrolled authenticator
+pub async fn disable_otp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<OtpVerifyRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if payload.user_id != auth.user_id {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error("Cannot disable OTP for another user".to_string())),
+        ));
+    }
+
+    let otp_url = UserModel::get_user_account(&state.db1, auth.user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch user: {}", e))),
+            )
+        })?
+        .and_then(|user| user.user_otp_url)
+        .filter(|url| !url.is_empty())
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("OTP is not enabled".to_string())),
+            )
+        })?;
+
+    let is_valid = verify_otp(&otp_url, &payload.otp_code).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("OTP verification failed: {}", e))),
+        )
+    })?;
+
+    if !is_valid {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::error("Invalid OTP code".to_string())),
+        ));
+    }
+
+    // Empty OTP URL means 2FA is disabled (same as the Go system)
+    UserModel::update_otp_url(&state.db1, auth.user_id, "")
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to disable OTP: {}", e))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "OTP disabled successfully".to_string(),
+    )))
+}
@@ -0,0 +1,566 @@
+use axum::{
+    extract::{State, Path, Query},
+    http::StatusCode,
+    Json,
+};
+use crate::configs::AppState;
+use crate::structs::{
+    ApiResponse, CustomerResponse, CustomerDetailResponse, CustomerRequest, CreateCustomerRequest,
+    CustomerSearchRequest, PaginatedResponse, PaginationRequest, SortDirection, DuplicateMatchResponse,
+    MergeCustomerRequest, CustomerMergeResponse,
+};
+use crate::models::{
+    Customer, CustomerFields, CustomerSearch, CustomerMerge, DuplicateMatch, DuplicateScan, CustomerModel,
+    OrderSearch, OrderModel, AuditLogModel, find_duplicates_of,
+};
+use crate::middlewares::AuthUser;
+use crate::middlewares::cache::TtlCache;
+use crate::libs::{normalize_thai_phone, phone_search_digits};
+use super::order::order_response;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use validator::Validate;
+
+/// Largest page size accepted by customer search
+const MAX_SEARCH_LIMIT: i64 = 100;
+
+/// Number of latest orders shown in customer detail
+const ORDER_HISTORY_LIMIT: i64 = 20;
+
+/// How long a customer merge can be undone
+const MERGE_UNDO_HOURS: i64 = 24;
+
+/// How long a shop's duplicate scan is reused before the shop is scanned again
+const DUPLICATE_SCAN_CACHE_TTL_SECS: u64 = 300;
+
+/// Duplicate scans keyed by shop_id, so paging through the list does not rescan the shop
+/// Dropped when a customer of the shop is created, changed, deleted or merged
+static DUPLICATE_SCAN_CACHE: LazyLock<TtlCache<i32, Arc<DuplicateScan>>> =
+    LazyLock::new(|| TtlCache::new(std::time::Duration::from_secs(DUPLICATE_SCAN_CACHE_TTL_SECS)));
+
+/// Search customers by name, phone, email or customer code
+pub async fn search_customers(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<CustomerSearchRequest>,
+) -> Result<Json<ApiResponse<PaginatedResponse<CustomerResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, MAX_SEARCH_LIMIT);
+
+    let search = CustomerSearch {
+        shop_id: auth.shop_id,
+        keyword: params.q,
+        name: params.name,
+        // "+66 81-234" and "081234" find the same customers
+        tel: params.tel.map(|tel| phone_search_digits(&tel).unwrap_or(tel)),
+        email: params.email,
+        customer_code: params.customer_code,
+        sort_column: params.sort_by.unwrap_or_default().column(),
+        sort_desc: params.sort_dir.unwrap_or_default() == SortDirection::Desc,
+        limit,
+        offset: (page - 1) * limit,
+    };
+
+    let (customers, total) = CustomerModel::search_customers(&state.db2, &search)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Search failed: {}", e))),
+            )
+        })?;
+
+    let data: Vec<CustomerResponse> = customers.into_iter().map(customer_response).collect();
+
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(data, page, limit, total))))
+}
+
+/// Get customer detail with their latest orders
+pub async fn get_customer_detail(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(customer_id): Path<i32>,
+) -> Result<Json<ApiResponse<CustomerDetailResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let customer = CustomerModel::get_customer_by_id(&state.db2, customer_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Customer not found".to_string())),
+            )
+        })?;
+
+    let history = OrderSearch {
+        shop_id: auth.shop_id,
+        customer_id: Some(customer.id),
+        status: None,
+        date_from: None,
+        date_to: None,
+        order_code_prefix: None,
+        min_net: None,
+        max_net: None,
+        sort_column: "order_date",
+        sort_desc: true,
+        include_deleted: false,
+        limit: ORDER_HISTORY_LIMIT,
+        offset: 0,
+    };
+
+    let (orders, order_count) = OrderModel::search_orders(&state.db2, &history)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch orders: {}", e))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success(CustomerDetailResponse {
+        customer: customer_response(customer),
+        order_count,
+        orders: orders.into_iter().map(order_response).collect(),
+    })))
+}
+
+/// Create a customer
+/// Rejected with 409 when another customer has the same phone, email or national ID, unless
+/// allow_duplicate is set
+pub async fn create_customer(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<CreateCustomerRequest>,
+) -> Result<Json<ApiResponse<CustomerResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to create customer: {}", e))),
+        )
+    };
+
+    let fields = customer_fields(payload.customer);
+
+    if !payload.allow_duplicate {
+        let duplicates = CustomerModel::find_duplicates(&state.db1, auth.shop_id, &fields, None)
+            .await
+            .map_err(db_error)?;
+
+        if !duplicates.is_empty() {
+            let matches: Vec<String> = duplicates
+                .iter()
+                .map(|c| format!("{} {} {} ({})", c.customer_code, c.customer_fname, c.customer_lname, c.customer_tel))
+                .collect();
+            return Err((
+                StatusCode::CONFLICT,
+                Json(ApiResponse::error(format!(
+                    "Customer may already exist: {}. Set allow_duplicate to create anyway",
+                    matches.join(", ")
+                ))),
+            ));
+        }
+    }
+
+    let customer_id = CustomerModel::create_customer(&state.db1, auth.shop_id, &fields)
+        .await
+        .map_err(db_error)?;
+    DUPLICATE_SCAN_CACHE.remove(&auth.shop_id);
+
+    let customer = CustomerModel::get_customer_by_id(&state.db1, customer_id, auth.shop_id)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(ApiResponse::success(customer_response(customer))))
+}
+
+/// Update a customer's details; the customer code stays the same
+pub async fn update_customer(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(customer_id): Path<i32>,
+    Json(payload): Json<CustomerRequest>,
+) -> Result<Json<ApiResponse<CustomerResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Update failed: {}", e))),
+        )
+    };
+
+    let fields = customer_fields(payload);
+
+    let updated = CustomerModel::update_customer(&state.db1, customer_id, auth.shop_id, &fields)
+        .await
+        .map_err(db_error)?;
+
+    if !updated {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::error("Customer not found".to_string())),
+        ));
+    }
+    DUPLICATE_SCAN_CACHE.remove(&auth.shop_id);
+
+    let customer = CustomerModel::get_customer_by_id(&state.db1, customer_id, auth.shop_id)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(ApiResponse::success(customer_response(customer))))
+}
+
+/// Delete customer
+/// Customers are soft-deleted so their orders and documents still show who they were for
+pub async fn delete_customer(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(customer_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let deleted = CustomerModel::delete_customer(&state.db1, customer_id, auth.shop_id, auth.user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Delete failed: {}", e))),
+            )
+        })?;
+
+    if !deleted {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::error("Customer not found".to_string())),
+        ));
+    }
+
+    DUPLICATE_SCAN_CACHE.remove(&auth.shop_id);
+    record_customer_audit(&state, &auth, customer_id, "delete", None).await;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Customer deleted successfully".to_string(),
+    )))
+}
+
+/// Find customers of the shop that may be the same person (shared phone, email or national ID,
+/// or a similar name), most likely first
+/// The scan is cached per shop, so later pages and repeat requests reuse it
+pub async fn find_duplicate_customers(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<PaginationRequest>,
+) -> Result<Json<ApiResponse<PaginatedResponse<DuplicateMatchResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, MAX_SEARCH_LIMIT);
+
+    let scan = shop_duplicate_scan(&state, auth.shop_id).await?;
+    let total = scan.matches.len() as i64;
+
+    let data: Vec<DuplicateMatchResponse> = scan
+        .matches
+        .iter()
+        .skip(((page - 1) * limit) as usize)
+        .take(limit as usize)
+        .map(|m| duplicate_match_response(&scan.customers, m.clone()))
+        .collect();
+
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(data, page, limit, total))))
+}
+
+/// Customers that may be the same person as the given customer
+pub async fn get_customer_duplicates(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(customer_id): Path<i32>,
+) -> Result<Json<ApiResponse<Vec<DuplicateMatchResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let customer = CustomerModel::get_customer_by_id(&state.db2, customer_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Customer not found".to_string())),
+            )
+        })?;
+
+    let customers = CustomerModel::get_shop_customers(&state.db2, auth.shop_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch customers: {}", e))),
+            )
+        })?;
+
+    let customer_id = customer.id;
+    let (customers, matches) = tokio::task::spawn_blocking(move || {
+        let matches = find_duplicates_of(&customer, &customers);
+        (customers, matches)
+    })
+    .await
+    .map_err(scan_error)?;
+
+    let by_id: HashMap<i32, Customer> = customers.into_iter().map(|c| (c.id, c)).collect();
+    let data: Vec<DuplicateMatchResponse> = matches
+        .into_iter()
+        .map(|m| {
+            // The requested customer first, whichever ID is lower
+            let mut response = duplicate_match_response(&by_id, m);
+            if response.customer.id != customer_id {
+                std::mem::swap(&mut response.customer, &mut response.duplicate);
+            }
+            response
+        })
+        .collect();
+
+    Ok(Json(ApiResponse::success(data)))
+}
+
+/// Merge a duplicate into the customer in the path
+/// The duplicate's orders, appointments and attachments move over in one transaction and the
+/// duplicate is soft-deleted; the merge can be undone for MERGE_UNDO_HOURS
+pub async fn merge_customer(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(customer_id): Path<i32>,
+    Json(payload): Json<MergeCustomerRequest>,
+) -> Result<Json<ApiResponse<CustomerMergeResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if payload.duplicate_id == customer_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("A customer cannot be merged into itself".to_string())),
+        ));
+    }
+
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::error("Customer not found".to_string())),
+        )
+    };
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Merge failed: {}", e))),
+        )
+    };
+
+    let target = CustomerModel::get_customer_by_id(&state.db1, customer_id, auth.shop_id)
+        .await
+        .map_err(|_| not_found())?;
+    let source = CustomerModel::get_customer_by_id(&state.db1, payload.duplicate_id, auth.shop_id)
+        .await
+        .map_err(|_| not_found())?;
+
+    let merge_id = CustomerModel::merge_customers(&state.db1, auth.shop_id, target.id, source.id, auth.user_id, MERGE_UNDO_HOURS)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+
+    let merge = CustomerModel::get_merge(&state.db1, merge_id, auth.shop_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+
+    let response = merge_response(merge);
+    let moved: Vec<String> = response
+        .moved
+        .iter()
+        .map(|(table, count)| format!("{} {}", table, count))
+        .collect();
+    let detail = format!(
+        "Merged {} (id {}) into {}: {}; merge {}",
+        source.customer_code,
+        source.id,
+        target.customer_code,
+        moved.join(", "),
+        merge_id
+    );
+    DUPLICATE_SCAN_CACHE.remove(&auth.shop_id);
+    record_customer_audit(&state, &auth, target.id, "merge", Some(&detail)).await;
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Merges of the shop that can still be undone
+pub async fn get_customer_merges(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<ApiResponse<Vec<CustomerMergeResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let merges = CustomerModel::get_undoable_merges(&state.db2, auth.shop_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch merges: {}", e))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success(merges.into_iter().map(merge_response).collect())))
+}
+
+/// Undo a merge within the grace period: the moved rows go back and the duplicate is restored
+pub async fn undo_customer_merge(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(merge_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Undo failed: {}", e))),
+        )
+    };
+    let conflict = |message: &str| {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(message.to_string())),
+        )
+    };
+
+    let merge = CustomerModel::get_merge(&state.db1, merge_id, auth.shop_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Merge not found".to_string())),
+            )
+        })?;
+
+    if merge.undone_at.is_some() {
+        return Err(conflict("Merge was already undone"));
+    }
+    if merge.cm_undo_until <= Utc::now().naive_utc() {
+        return Err(conflict("Merge can no longer be undone"));
+    }
+
+    // Rows of a target that has since been merged away would not be found on the target
+    if CustomerModel::get_customer_by_id(&state.db1, merge.cm_target_id, auth.shop_id).await.is_err() {
+        return Err(conflict("The surviving customer has since been deleted or merged; undo that first"));
+    }
+
+    // The checks above are repeated under lock; anything that changed since fails here
+    if !CustomerModel::undo_merge(&state.db1, &merge, auth.user_id).await.map_err(db_error)? {
+        return Err(conflict("Merge was changed by another request, please reload"));
+    }
+
+    let detail = format!("Undid merge {}: customer {} restored", merge.id, merge.cm_source_id);
+    DUPLICATE_SCAN_CACHE.remove(&auth.shop_id);
+    record_customer_audit(&state, &auth, merge.cm_target_id, "undo_merge", Some(&detail)).await;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Merge undone successfully".to_string(),
+    )))
+}
+
+/// The shop's duplicate scan, from the cache or scanned on a blocking thread
+async fn shop_duplicate_scan(state: &AppState, shop_id: i32) -> Result<Arc<DuplicateScan>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Some(scan) = DUPLICATE_SCAN_CACHE.get(&shop_id) {
+        return Ok(scan);
+    }
+
+    let customers = CustomerModel::get_shop_customers(&state.db2, shop_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch customers: {}", e))),
+            )
+        })?;
+
+    let scan = tokio::task::spawn_blocking(move || DuplicateScan::new(customers))
+        .await
+        .map_err(scan_error)?;
+    let scan = Arc::new(scan);
+    DUPLICATE_SCAN_CACHE.insert(shop_id, scan.clone());
+
+    Ok(scan)
+}
+
+fn scan_error(e: tokio::task::JoinError) -> (StatusCode, Json<ApiResponse<()>>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiResponse::error(format!("Duplicate scan failed: {}", e))),
+    )
+}
+
+/// Record a customer action in the log database; failures are logged, not returned
+async fn record_customer_audit(state: &AppState, auth: &AuthUser, customer_id: i32, action: &str, detail: Option<&str>) {
+    if let Err(e) = AuditLogModel::record(&state.dbl1, auth.shop_id, auth.user_id, "customer", customer_id, action, detail).await {
+        tracing::error!("Failed to record customer {} {} in audit log: {}", action, customer_id, e);
+    }
+}
+
+/// Trim the request and normalize the phone number; blank optional fields are stored as NULL
+fn customer_fields(request: CustomerRequest) -> CustomerFields {
+    let optional = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    CustomerFields {
+        fname: request.fname.trim().to_string(),
+        lname: request.lname.trim().to_string(),
+        tel: normalize_thai_phone(&request.tel).unwrap_or(request.tel),
+        email: optional(request.email),
+        national_id: optional(request.national_id),
+        birthdate: request.birthdate,
+        address: optional(request.address),
+    }
+}
+
+fn customer_response(customer: Customer) -> CustomerResponse {
+    CustomerResponse {
+        id: customer.id,
+        shop_id: customer.shop_id,
+        customer_code: customer.customer_code,
+        fname: customer.customer_fname,
+        lname: customer.customer_lname,
+        tel: customer.customer_tel,
+        email: customer.customer_email,
+        national_id: customer.customer_national_id,
+        birthdate: customer.customer_birthdate,
+        address: customer.customer_address,
+        created_at: customer.created_at,
+    }
+}
+
+/// Both customers of a match; they come from the same list the match was found in
+fn duplicate_match_response(by_id: &HashMap<i32, Customer>, found: DuplicateMatch) -> DuplicateMatchResponse {
+    DuplicateMatchResponse {
+        customer: customer_response(by_id[&found.customer_id].clone()),
+        duplicate: customer_response(by_id[&found.duplicate_id].clone()),
+        reasons: found.reasons,
+        name_similarity: found.name_similarity,
+    }
+}
+
+fn merge_response(merge: CustomerMerge) -> CustomerMergeResponse {
+    CustomerMergeResponse {
+        id: merge.id,
+        target_id: merge.cm_target_id,
+        source_id: merge.cm_source_id,
+        moved: merge
+            .moved_rows()
+            .into_iter()
+            .map(|(table, ids)| (table, ids.len()))
+            .collect(),
+        merged_by: merge.merged_by,
+        merged_at: merge.merged_at,
+        undo_until: merge.cm_undo_until,
+        undone_at: merge.undone_at,
+    }
+}
@@ -0,0 +1,502 @@
+use axum::{
+    body::Bytes,
+    extract::{State, Path, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use crate::configs::AppState;
+use crate::structs::{ApiResponse, PrintDocumentQuery, StoredDocumentResponse, ThermalPrintRequest};
+use crate::models::{
+    Order, OrderLine, OrderModel, OrderStatus, Payment, PaymentModel, RefundModel, Shop, ShopModel,
+    TaxInvoiceModel,
+};
+use crate::libs::{
+    is_public_ip, layout_document, promptpay_payload, render_document_pdf, render_escpos, send_to_printer,
+    DocumentData, DocumentKind, DocumentLine, DocumentParty, DocumentPayment, EscPosOptions, LogoBitmap,
+    Money, PromptPayId, VatBreakdown, VatType, DEFAULT_THAI_CODE_PAGE,
+};
+use crate::middlewares::{upload_s3, AuthUser};
+use crate::middlewares::cache::TtlCache;
+use anyhow::{bail, Context};
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+/// Largest logo download accepted
+const LOGO_MAX_BYTES: usize = 1024 * 1024;
+
+const LOGO_CACHE_TTL_SECS: u64 = 600;
+
+/// Decoded receipt logos keyed by URL, so printing does not download the logo every time
+/// A logo that failed to load is cached as None, so a broken URL is not retried on every receipt
+static LOGO_CACHE: LazyLock<TtlCache<String, Option<Arc<LogoBitmap>>>> =
+    LazyLock::new(|| TtlCache::new(Duration::from_secs(LOGO_CACHE_TTL_SECS)));
+
+/// Print an order's receipt, abbreviated or full tax invoice, or credit note as PDF
+/// The file is built from the order, its items and the shop at request time
+pub async fn get_order_document_pdf(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Query(query): Query<PrintDocumentQuery>,
+) -> Result<Response, (StatusCode, Json<ApiResponse<()>>)> {
+    let (number, pdf) = order_document_pdf(&state, auth.shop_id, order_id, &query).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("inline; filename=\"{}.pdf\"", number)),
+        ],
+        pdf,
+    )
+        .into_response())
+}
+
+/// Render an order document as PDF and keep it in S3 under documents/{shop_id}
+pub async fn store_order_document(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<PrintDocumentQuery>,
+) -> Result<Json<ApiResponse<StoredDocumentResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let (number, pdf) = order_document_pdf(&state, auth.shop_id, order_id, &payload).await?;
+
+    let filename = format!("{}.pdf", number);
+    let folder = format!("documents/{}", auth.shop_id);
+    let uploaded = upload_s3(Bytes::from(pdf), "application/pdf", &filename, Some(&folder))
+        .await
+        .map_err(|(status, message)| {
+            (
+                status,
+                Json(ApiResponse::error(format!("Failed to store document: {}", message))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success(StoredDocumentResponse {
+        number,
+        url: uploaded.url,
+    })))
+}
+
+/// Number and PDF of an order document
+async fn order_document_pdf(
+    state: &AppState,
+    shop_id: i32,
+    order_id: i32,
+    options: &PrintDocumentQuery,
+) -> Result<(String, Vec<u8>), (StatusCode, Json<ApiResponse<()>>)> {
+    let shop = ShopModel::get_shop_by_id(&state.db2, shop_id)
+        .await
+        .map_err(shop_error)?;
+    let kind = options.kind.unwrap_or_default();
+    let data = order_document_data(state, &shop, order_id, kind, options.refund_id).await?;
+
+    let font = load_document_font().await?;
+    let pdf = render_document_pdf(&data, options.paper.unwrap_or_default(), &font).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to render document: {}", e))),
+        )
+    })?;
+
+    Ok((data.number, pdf))
+}
+
+/// Read the Thai TrueType font embedded in printed documents (PDF_FONT_PATH)
+async fn load_document_font() -> Result<Vec<u8>, (StatusCode, Json<ApiResponse<()>>)> {
+    let font_error = |message: String| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(message)),
+        )
+    };
+
+    let path = std::env::var("PDF_FONT_PATH")
+        .map_err(|_| font_error("PDF_FONT_PATH not set".to_string()))?;
+    tokio::fs::read(&path)
+        .await
+        .map_err(|e| font_error(format!("Failed to read document font {}: {}", path, e)))
+}
+
+/// ESC/POS job of an order document, fetched by the local print agent (PRINT_URL) and passed on
+/// to a USB or network thermal printer
+pub async fn get_order_receipt_escpos(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Query(query): Query<ThermalPrintRequest>,
+) -> Result<Response, (StatusCode, Json<ApiResponse<()>>)> {
+    let shop = ShopModel::get_shop_by_id(&state.db2, auth.shop_id)
+        .await
+        .map_err(shop_error)?;
+    let (number, job) = order_escpos_job(&state, &shop, order_id, &query).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.bin\"", number)),
+        ],
+        job,
+    )
+        .into_response())
+}
+
+/// Send an order document straight to the shop's network printer (raw TCP, port 9100)
+pub async fn print_order_receipt(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<ThermalPrintRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let shop = ShopModel::get_shop_by_id(&state.db2, auth.shop_id)
+        .await
+        .map_err(shop_error)?;
+    let printer = shop.shop_printer_address.clone().ok_or_else(|| {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Set the shop's receipt printer first".to_string())),
+        )
+    })?;
+
+    let (number, job) = order_escpos_job(&state, &shop, order_id, &payload).await?;
+    send_to_printer(&printer, &job).await.map_err(|e| {
+        (
+            StatusCode::BAD_GATEWAY,
+            Json(ApiResponse::error(format!("{:#}", e))),
+        )
+    })?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        format!("{} sent to the printer", number),
+    )))
+}
+
+fn shop_error(e: anyhow::Error) -> (StatusCode, Json<ApiResponse<()>>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiResponse::error(format!("Failed to fetch shop: {}", e))),
+    )
+}
+
+/// Lay out an order document for a thermal printer with the shop's logo and a QR code: PromptPay
+/// for a receipt with a balance left to pay, otherwise the e-receipt link when ERECEIPT_URL is set
+async fn order_escpos_job(
+    state: &AppState,
+    shop: &Shop,
+    order_id: i32,
+    request: &ThermalPrintRequest,
+) -> Result<(String, Vec<u8>), (StatusCode, Json<ApiResponse<()>>)> {
+    let data = order_document_data(state, shop, order_id, request.kind.unwrap_or_default(), request.refund_id).await?;
+    let rows = layout_document(&data);
+
+    let logo = match &shop.shop_logo_url {
+        Some(url) => shop_logo(url).await,
+        None => None,
+    };
+
+    let paid: Money = data.payments.iter().map(|payment| payment.amount).sum();
+    let qr = match shop.shop_promptpay_id.as_deref().and_then(PromptPayId::parse) {
+        Some(promptpay_id) if data.kind == DocumentKind::Receipt && data.total > paid => {
+            Some(promptpay_payload(&promptpay_id, Some(data.total - paid)))
+        }
+        _ => std::env::var("ERECEIPT_URL")
+            .ok()
+            .map(|url| format!("{}/{}/{}", url.trim_end_matches('/'), shop.id, data.number)),
+    };
+    let options = EscPosOptions {
+        code_page: std::env::var("ESCPOS_CODE_PAGE")
+            .ok()
+            .and_then(|code_page| code_page.parse().ok())
+            .unwrap_or(DEFAULT_THAI_CODE_PAGE),
+        logo,
+        qr,
+        ..Default::default()
+    };
+
+    let job = render_escpos(&rows, &options).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to render receipt: {}", e))),
+        )
+    })?;
+
+    Ok((data.number, job))
+}
+
+/// The shop's receipt logo, from the cache or downloaded; receipts print without it if that fails
+async fn shop_logo(url: &str) -> Option<Arc<LogoBitmap>> {
+    let key = url.to_string();
+    if let Some(logo) = LOGO_CACHE.get(&key) {
+        return logo;
+    }
+
+    let logo = match fetch_logo(url).await {
+        Ok(logo) => Some(Arc::new(logo)),
+        Err(e) => {
+            tracing::warn!("Failed to fetch receipt logo {}: {:#}", url, e);
+            None
+        }
+    };
+    LOGO_CACHE.insert(key, logo.clone());
+    logo
+}
+
+/// Download and decode a logo
+/// Only https to a public address; the address checked is the one connected to, so DNS cannot
+/// swap in an internal one, redirects are not followed and the body is capped at LOGO_MAX_BYTES
+async fn fetch_logo(url: &str) -> anyhow::Result<LogoBitmap> {
+    let url = reqwest::Url::parse(url).context("Invalid logo URL")?;
+    if url.scheme() != "https" {
+        bail!("Logo URL must use https");
+    }
+    let host = url.host_str().context("Logo URL has no host")?.to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    // IPv6 literals come bracketed
+    let addresses: Vec<SocketAddr> =
+        tokio::net::lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port))
+            .await
+            .with_context(|| format!("Failed to resolve {}", host))?
+            .collect();
+    if let Some(address) = addresses.iter().find(|address| !is_public_ip(address.ip())) {
+        bail!("Logo host {} resolves to non-public address {}", host, address.ip());
+    }
+    let address = *addresses.first().with_context(|| format!("{} has no address", host))?;
+
+    let client = reqwest::Client::builder()
+        .resolve(&host, address)
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let mut response = client.get(url).send().await?.error_for_status()?;
+
+    if response.content_length().is_some_and(|length| length > LOGO_MAX_BYTES as u64) {
+        bail!("Logo is larger than {} bytes", LOGO_MAX_BYTES);
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if bytes.len() + chunk.len() > LOGO_MAX_BYTES {
+            bail!("Logo is larger than {} bytes", LOGO_MAX_BYTES);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    tokio::task::spawn_blocking(move || LogoBitmap::decode(&bytes)).await?
+}
+
+/// Gather what a printed document shows from the order, its items, payments and the shop
+pub(crate) async fn order_document_data(
+    state: &AppState,
+    shop: &Shop,
+    order_id: i32,
+    kind: DocumentKind,
+    refund_id: Option<i32>,
+) -> Result<DocumentData, (StatusCode, Json<ApiResponse<()>>)> {
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to fetch document data: {}", e))),
+        )
+    };
+    let conflict = |message: &str| {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(message.to_string())),
+        )
+    };
+
+    let order = OrderModel::get_order_by_id(&state.db2, order_id, shop.id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+    let items = OrderModel::get_order_items(&state.db2, order.id)
+        .await
+        .map_err(db_error)?;
+    let tax_invoice = TaxInvoiceModel::get_by_order(&state.db2, order.id)
+        .await
+        .map_err(db_error)?;
+
+    if kind.shows_vat() && shop.shop_tax_id.is_none() {
+        return Err(conflict("Set the shop's tax ID before printing tax documents"));
+    }
+
+    match kind {
+        DocumentKind::Receipt | DocumentKind::AbbreviatedTaxInvoice => {
+            if kind == DocumentKind::AbbreviatedTaxInvoice && order.order_status != OrderStatus::Paid {
+                return Err(conflict("Tax invoices are printed for paid orders"));
+            }
+
+            let payments = PaymentModel::get_order_payments(&state.db2, order.id)
+                .await
+                .map_err(db_error)?;
+            // The latest payment's receipt number identifies the document
+            let latest = payments
+                .last()
+                .ok_or_else(|| conflict("The order has no payments to print a receipt for"))?;
+
+            Ok(DocumentData {
+                number: latest.payment_receipt_number.clone(),
+                issued_at: latest.created_at,
+                ..sale_document(kind, &order, shop, &items, &payments)
+            })
+        }
+        DocumentKind::FullTaxInvoice => {
+            let invoice = tax_invoice
+                .ok_or_else(|| conflict("Issue a tax invoice for this order first"))?;
+            let payments = PaymentModel::get_order_payments(&state.db2, order.id)
+                .await
+                .map_err(db_error)?;
+
+            Ok(DocumentData {
+                number: invoice.ti_number,
+                issued_at: invoice.created_at,
+                buyer: Some(DocumentParty {
+                    name: invoice.ti_customer_name,
+                    address: Some(invoice.ti_customer_address),
+                    tel: None,
+                    tax_id: Some(invoice.ti_customer_tax_id),
+                    branch_code: Some(invoice.ti_customer_branch_code),
+                }),
+                ..sale_document(kind, &order, shop, &items, &payments)
+            })
+        }
+        DocumentKind::CreditNote => {
+            let refunds = RefundModel::get_order_refunds(&state.db2, order.id)
+                .await
+                .map_err(db_error)?;
+            let refund = match refund_id {
+                Some(refund_id) => refunds.iter().find(|refund| refund.id == refund_id),
+                None => refunds.last(),
+            }
+            .ok_or_else(|| {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(ApiResponse::error("Credit note not found".to_string())),
+                )
+            })?;
+            let refund_lines = RefundModel::get_order_refund_lines(&state.db2, order.id)
+                .await
+                .map_err(db_error)?;
+
+            let mut lines = Vec::new();
+            let mut vat = VatBreakdown::default();
+            for refund_line in refund_lines.iter().filter(|line| line.refund_id == refund.id) {
+                let Some(item) = items.iter().find(|item| item.id == refund_line.order_item_id) else {
+                    continue;
+                };
+
+                // The refunded amount carries the line's VAT in proportion
+                if item.oi_vat_type == VatType::Exempt {
+                    vat.exempt += refund_line.ri_amount;
+                } else {
+                    let line_vat = item.oi_vat.prorate(refund_line.ri_amount.amount(), item.oi_net.amount());
+                    vat.vat += line_vat;
+                    vat.vatable += refund_line.ri_amount - line_vat;
+                }
+
+                lines.push(DocumentLine::refunded(
+                    item.product_name.clone(),
+                    refund_line.ri_quantity,
+                    item.oi_price,
+                    item.oi_vat_type,
+                    refund_line.ri_amount,
+                ));
+            }
+
+            Ok(DocumentData {
+                kind,
+                number: refund.refund_number.clone(),
+                reference: Some(
+                    tax_invoice
+                        .map(|invoice| invoice.ti_number)
+                        .unwrap_or_else(|| order.order_code.clone()),
+                ),
+                issued_at: refund.created_at,
+                seller: seller_party(shop),
+                buyer: None,
+                lines,
+                subtotal: refund.refund_amount,
+                discount: Money::ZERO,
+                total: refund.refund_amount,
+                vat,
+                payments: vec![DocumentPayment {
+                    label: format!("คืนเงิน / Refund ({})", refund.refund_method.label()),
+                    amount: refund.refund_amount,
+                    tendered: refund.refund_amount,
+                    change: Money::ZERO,
+                }],
+                note: Some(format!("เหตุผล / Reason: {}", refund.refund_reason)),
+            })
+        }
+    }
+}
+
+fn seller_party(shop: &Shop) -> DocumentParty {
+    DocumentParty {
+        name: shop.shop_name.clone(),
+        address: shop.shop_address.clone(),
+        tel: shop.shop_tel.clone(),
+        tax_id: shop.shop_tax_id.clone(),
+        branch_code: shop.shop_branch_code.clone(),
+    }
+}
+
+/// Receipt or tax invoice for the whole order, numbered with the order code until the caller
+/// sets the document's own number
+fn sale_document(
+    kind: DocumentKind,
+    order: &Order,
+    shop: &Shop,
+    items: &[OrderLine],
+    payments: &[Payment],
+) -> DocumentData {
+    let subtotal: Money = items.iter().map(|item| item.oi_total).sum();
+    let item_discount: Money = items.iter().map(|item| item.oi_discount).sum();
+
+    DocumentData {
+        kind,
+        number: order.order_code.clone(),
+        reference: Some(order.order_code.clone()),
+        issued_at: order.order_date,
+        seller: seller_party(shop),
+        buyer: None,
+        lines: items
+            .iter()
+            .map(|item| DocumentLine {
+                name: item.product_name.clone(),
+                quantity: item.oi_quantity,
+                unit_price: item.oi_price,
+                discount: item.oi_discount,
+                amount: item.oi_total,
+            })
+            .collect(),
+        subtotal,
+        discount: order.order_discount - item_discount,
+        total: order.order_net,
+        vat: VatBreakdown {
+            vatable: order.order_vatable,
+            vat: order.order_vat,
+            exempt: order.order_exempt,
+        },
+        payments: payments
+            .iter()
+            .map(|payment| DocumentPayment {
+                label: if payment.payment_is_deposit {
+                    format!("มัดจำ / Deposit ({})", payment.payment_method.label())
+                } else {
+                    payment.payment_method.label().to_string()
+                },
+                amount: payment.payment_amount,
+                tendered: payment.payment_tendered,
+                change: payment.payment_change,
+            })
+            .collect(),
+        note: None,
+    }
+}
@@ -4,8 +4,20 @@
 pub mod auth;
 pub mod user;
 pub mod order;
+pub mod customer;
+pub mod payment;
+pub mod refund;
+pub mod shop;
+pub mod tax_invoice;
+pub mod document;
 
 // Re-export handler functions
 pub use auth::*;
 pub use user::*;
 pub use order::*;
+pub use customer::*;
+pub use payment::*;
+pub use refund::*;
+pub use shop::*;
+pub use tax_invoice::*;
+pub use document::*;
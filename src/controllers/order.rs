@@ -4,9 +4,30 @@ use axum::{
     Json,
 };
 use crate::configs::AppState;
-use crate::structs::{OrderResponse, ApiResponse, CreateOrderRequest, OrderSearchRequest};
-use crate::models::OrderModel;
-use crate::middlewares::AuthUser;
+use crate::structs::{
+    OrderResponse, OrderDetailResponse, OrderItemResponse, ApiResponse, CreateOrderRequest,
+    OrderSearchRequest, PaginatedResponse, SortDirection, OrderReasonRequest, OrderStatusHistoryResponse,
+    DiscountApproval, CreditNoteResponse, VoidOrderRequest, CreateRefundResponse,
+};
+use crate::models::{
+    Order, OrderLine, OrderSearch, NewOrder, NewOrderLine, OrderModel, ProductModel,
+    OrderStatus, OrderStatusModel, AuditLogModel, UserModel, Payment, PaymentModel, PaymentSummary,
+    RefundModel, NewRefund, TaxInvoice, TaxInvoiceModel, CustomerModel, void_refund_items,
+};
+use crate::middlewares::{
+    AuthUser, ensure_permission, check_password, verify_password, dummy_password_check, PasswordCheck,
+    login_guard::{check_login_lock, LOCKOUT_MINUTES, MAX_FAILED_LOGINS},
+    permission::SHOP_OWNER_ROLE_ID,
+};
+use crate::libs::{price_lines, DiscountCap, Money, VatType};
+use super::payment::summary_response;
+use super::refund::{credit_note_responses, issued_credit_note};
+use super::tax_invoice::tax_invoice_response;
+use chrono::Utc;
+use validator::Validate;
+
+/// Largest page size accepted by order search
+const MAX_SEARCH_LIMIT: i64 = 100;
 
 /// Search orders
 /// Equivalent to Go's OrdersSearch function
@@ -14,42 +35,43 @@ pub async fn search_orders(
     State(state): State<AppState>,
     auth: AuthUser,
     Query(params): Query<OrderSearchRequest>,
-) -> Result<Json<ApiResponse<Vec<OrderResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let page = params.page.unwrap_or(1);
-    let limit = params.limit.unwrap_or(20);
-    let offset = (page - 1) * limit;
-
-    let orders = OrderModel::search_orders(
-        &state.db2,
-        auth.shop_id,
-        params.customer_id,
-        params.status,
+) -> Result<Json<ApiResponse<PaginatedResponse<OrderResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let include_deleted = params.include_deleted.unwrap_or(false);
+    if include_deleted {
+        ensure_permission(&state, &auth, "order.view_deleted").await?;
+    }
+
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, MAX_SEARCH_LIMIT);
+
+    let search = OrderSearch {
+        shop_id: auth.shop_id,
+        customer_id: params.customer_id,
+        status: params.status,
+        date_from: params.date_from,
+        date_to: params.date_to,
+        order_code_prefix: params.order_code,
+        min_net: params.min_net,
+        max_net: params.max_net,
+        sort_column: params.sort_by.unwrap_or_default().column(),
+        sort_desc: params.sort_dir.unwrap_or_default() == SortDirection::Desc,
+        include_deleted,
         limit,
-        offset,
-    )
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(format!("Search failed: {}", e))),
-        )
-    })?;
+        offset: (page - 1) * limit,
+    };
 
-    let response: Vec<OrderResponse> = orders
-        .into_iter()
-        .map(|o| OrderResponse {
-            id: o.id,
-            shop_id: o.shop_id,
-            customer_id: o.customer_id,
-            order_code: o.order_code,
-            order_total: o.order_total,
-            order_discount: o.order_discount,
-            order_net: o.order_net,
-            order_status: o.order_status,
-        })
-        .collect();
+    let (orders, total) = OrderModel::search_orders(&state.db2, &search)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Search failed: {}", e))),
+            )
+        })?;
 
-    Ok(Json(ApiResponse::success(response)))
+    let data: Vec<OrderResponse> = orders.into_iter().map(order_response).collect();
+
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(data, page, limit, total))))
 }
 
 /// Get order detail
@@ -58,7 +80,7 @@ pub async fn get_order_detail(
     State(state): State<AppState>,
     auth: AuthUser,
     Path(order_id): Path<i32>,
-) -> Result<Json<ApiResponse<OrderResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<Json<ApiResponse<OrderDetailResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
     let order = OrderModel::get_order_by_id(&state.db2, order_id, auth.shop_id)
         .await
         .map_err(|_| {
@@ -68,18 +90,48 @@ pub async fn get_order_detail(
             )
         })?;
 
-    let response = OrderResponse {
-        id: order.id,
-        shop_id: order.shop_id,
-        customer_id: order.customer_id,
-        order_code: order.order_code,
-        order_total: order.order_total,
-        order_discount: order.order_discount,
-        order_net: order.order_net,
-        order_status: order.order_status,
+    let items = OrderModel::get_order_items(&state.db2, order.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch order items: {}", e))),
+            )
+        })?;
+
+    let payments = PaymentModel::get_order_payments(&state.db2, order.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch payments: {}", e))),
+            )
+        })?;
+
+    let refund_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to fetch refunds: {}", e))),
+        )
     };
+    let refunds = RefundModel::get_order_refunds(&state.db2, order.id)
+        .await
+        .map_err(refund_error)?;
+    let refund_lines = RefundModel::get_order_refund_lines(&state.db2, order.id)
+        .await
+        .map_err(refund_error)?;
+    let refunds = credit_note_responses(refunds, &refund_lines);
 
-    Ok(Json(ApiResponse::success(response)))
+    let tax_invoice = TaxInvoiceModel::get_by_order(&state.db2, order.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch tax invoice: {}", e))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success(order_detail_response(order, items, &payments, refunds, tax_invoice))))
 }
 
 /// Create new order
@@ -88,57 +140,159 @@ pub async fn create_order(
     State(state): State<AppState>,
     auth: AuthUser,
     Json(payload): Json<CreateOrderRequest>,
-) -> Result<Json<ApiResponse<OrderResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
-    // Generate order code
-    let order_code = format!("ORD-{}-{}", auth.shop_id, chrono::Utc::now().timestamp());
+) -> Result<Json<ApiResponse<OrderDetailResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
 
-    // Calculate totals
-    let total: f64 = payload.items.iter().map(|item| item.price * item.quantity as f64).sum();
-    let discount = 0.0; // Apply discount logic here
-    let net = total - discount;
+    // The customer must be an active customer of this shop
+    CustomerModel::get_customer_by_id(&state.db1, payload.customer_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error(format!("Customer {} not found in this shop", payload.customer_id))),
+            )
+        })?;
 
-    // Create order
-    let order_id = OrderModel::create_order(
-        &state.db1,
-        auth.shop_id,
-        payload.customer_id,
-        &order_code,
+    // Price each item from the shop's product, never from the client
+    let mut lines = Vec::with_capacity(payload.items.len());
+    for item in &payload.items {
+        let product = ProductModel::get_product_by_id(&state.db1, item.product_id, auth.shop_id)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::error(format!("Product {} not found in this shop", item.product_id))),
+                )
+            })?;
+
+        let gross = product.product_price * item.quantity;
+        let discount = match &item.discount {
+            Some(discount) => discount.amount(gross).map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiResponse::error(format!("Invalid discount for product {}: {}", product.id, e))),
+                )
+            })?,
+            None => Money::ZERO,
+        };
+
+        lines.push(NewOrderLine {
+            product_id: product.id,
+            quantity: item.quantity,
+            price: product.product_price,
+            discount,
+            vat_type: product.product_vat_type,
+            vat: Money::ZERO,
+            net: Money::ZERO,
+        });
+    }
+
+    // Calculate totals: bill discount applies to the subtotal after item discounts
+    let total: Money = lines.iter().map(NewOrderLine::gross).sum();
+    let item_discount: Money = lines.iter().map(|line| line.discount).sum();
+    let bill_discount = match &payload.discount {
+        Some(discount) => discount.amount(total - item_discount).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error(format!("Invalid bill discount: {}", e))),
+            )
+        })?,
+        None => Money::ZERO,
+    };
+    let discount = item_discount + bill_discount;
+
+    // Share the bill discount over the lines and work out VAT; net includes VAT added on
+    // VAT-exclusive items
+    let vat_lines: Vec<(VatType, Money)> = lines.iter().map(|line| (line.vat_type, line.total())).collect();
+    let (priced, vat) = price_lines(&vat_lines, bill_discount);
+    for (line, priced) in lines.iter_mut().zip(priced) {
+        line.vat = priced.vat;
+        line.net = priced.net;
+    }
+    let net = vat.payable();
+
+    // Discounts above the staff member's cap need a manager's approval
+    let mut discount_approved_by = None;
+    if !discount.is_zero() && !discount_cap(auth.role_id, auth.sr_discount_type_id, auth.sr_discount).allows(discount, total) {
+        let approval = payload.discount_approval.as_ref().ok_or_else(|| {
+            (
+                StatusCode::FORBIDDEN,
+                Json(ApiResponse::error("Discount exceeds your limit, manager approval required".to_string())),
+            )
+        })?;
+
+        let (approver_id, approver_cap) = verify_discount_approver(&state, &auth, approval).await?;
+        if !approver_cap.allows(discount, total) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ApiResponse::error("Discount exceeds the approver's limit".to_string())),
+            ));
+        }
+
+        discount_approved_by = Some(approver_id);
+    }
+
+    let new_order = NewOrder {
+        shop_id: auth.shop_id,
+        customer_id: payload.customer_id,
+        created_by: auth.user_id,
         total,
         discount,
         net,
-    )
-    .await
-    .map_err(|e| {
+        vat,
+        discount_approved_by,
+    };
+
+    // Create order with its items
+    let order_id = OrderModel::create_order(&state.db1, &new_order, &lines)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Order creation failed: {}", e))),
+            )
+        })?;
+
+    let fetch_error = |e: anyhow::Error| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(format!("Order creation failed: {}", e))),
+            Json(ApiResponse::error(format!("Failed to fetch created order: {}", e))),
         )
-    })?;
-
-    // TODO: Create order items in order_items table
-
-    let response = OrderResponse {
-        id: order_id,
-        shop_id: auth.shop_id,
-        customer_id: payload.customer_id,
-        order_code,
-        order_total: total,
-        order_discount: discount,
-        order_net: net,
-        order_status: 1,
     };
 
-    Ok(Json(ApiResponse::success(response)))
+    // Read back from the primary; the replica may not have the order yet
+    let order = OrderModel::get_order_by_id(&state.db1, order_id, auth.shop_id)
+        .await
+        .map_err(fetch_error)?;
+    let items = OrderModel::get_order_items(&state.db1, order_id)
+        .await
+        .map_err(fetch_error)?;
+
+    Ok(Json(ApiResponse::success(order_detail_response(order, items, &[], Vec::new(), None))))
 }
 
 /// Delete order
 /// Equivalent to Go's DelOrder function
+/// Orders are soft-deleted with a reason and the deletion is recorded in the log database
 pub async fn delete_order(
     State(state): State<AppState>,
     auth: AuthUser,
     Path(order_id): Path<i32>,
+    Json(payload): Json<OrderReasonRequest>,
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
-    OrderModel::delete_order(&state.db1, order_id, auth.shop_id)
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let deleted = OrderModel::delete_order(&state.db1, order_id, auth.shop_id, auth.user_id, &payload.reason)
         .await
         .map_err(|e| {
             (
@@ -147,8 +301,442 @@ pub async fn delete_order(
             )
         })?;
 
+    if !deleted {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::error("Order not found".to_string())),
+        ));
+    }
+
+    record_order_audit(&state, &auth, order_id, "delete", Some(&payload.reason)).await;
+
     Ok(Json(ApiResponse::success_with_message(
         (),
         "Order deleted successfully".to_string(),
     )))
 }
+
+/// Restore a soft-deleted order
+pub async fn restore_order(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let restored = OrderModel::restore_order(&state.db1, order_id, auth.shop_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Restore failed: {}", e))),
+            )
+        })?;
+
+    if !restored {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::error("Deleted order not found".to_string())),
+        ));
+    }
+
+    record_order_audit(&state, &auth, order_id, "restore", None).await;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Order restored successfully".to_string(),
+    )))
+}
+
+/// Record an order action in the log database; failures are logged, not returned
+pub(crate) async fn record_order_audit(state: &AppState, auth: &AuthUser, order_id: i32, action: &str, detail: Option<&str>) {
+    if let Err(e) = AuditLogModel::record(&state.dbl1, auth.shop_id, auth.user_id, "order", order_id, action, detail).await {
+        tracing::error!("Failed to record order {} {} in audit log: {}", action, order_id, e);
+    }
+}
+
+/// Confirm a draft order
+pub async fn confirm_order(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    transition_order(&state, &auth, order_id, OrderStatus::Confirmed, "confirm", None, |_| true).await?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Order confirmed".to_string(),
+    )))
+}
+
+/// Cancel an order that has not been paid
+pub async fn cancel_order(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<OrderReasonRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    transition_order(
+        &state,
+        &auth,
+        order_id,
+        OrderStatus::Cancelled,
+        "cancel",
+        Some(&payload.reason),
+        |status| !status.has_payment(),
+    )
+    .await?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Order cancelled".to_string(),
+    )))
+}
+
+/// Void an order that has already taken payment
+/// Everything received and not yet refunded goes back on one credit note, issued in the same
+/// transaction that cancels the order
+pub async fn void_order(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<VoidOrderRequest>,
+) -> Result<Json<ApiResponse<CreateRefundResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Void failed: {}", e))),
+        )
+    };
+
+    let order = OrderModel::get_order_by_id(&state.db1, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+
+    if !order.order_status.has_payment() || !order.order_status.can_transition_to(OrderStatus::Cancelled) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(format!(
+                "Cannot void an order that is {}",
+                order.order_status.as_str()
+            ))),
+        ));
+    }
+
+    let lines = OrderModel::get_order_items(&state.db1, order.id)
+        .await
+        .map_err(db_error)?;
+    let payments = PaymentModel::get_order_payments(&state.db1, order.id)
+        .await
+        .map_err(db_error)?;
+    let refunds = RefundModel::get_order_refunds(&state.db1, order.id)
+        .await
+        .map_err(db_error)?;
+    let paid = PaymentSummary::new(order.order_net, &payments).paid;
+    let refunded: Money = refunds.iter().map(|r| r.refund_amount).sum();
+
+    let items = void_refund_items(&lines, paid - refunded, payload.restock).map_err(|e| {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(e.to_string())),
+        )
+    })?;
+
+    let new_refund = NewRefund {
+        method: payload.method,
+        reason: payload.reason.clone(),
+        created_by: auth.user_id,
+    };
+
+    let refund_id = RefundModel::create_refund(
+        &state.db1,
+        &order,
+        paid,
+        refunded,
+        &new_refund,
+        &items,
+        Some(OrderStatus::Cancelled),
+    )
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Order was changed by another request, please reload".to_string())),
+        )
+    })?;
+
+    let credit_note = issued_credit_note(&state, order.id, refund_id)
+        .await
+        .map_err(db_error)?;
+
+    record_order_audit(&state, &auth, order.id, "void", Some(&credit_note.credit_note_number)).await;
+
+    Ok(Json(ApiResponse::success_with_message(
+        CreateRefundResponse {
+            order_status: OrderStatus::Cancelled,
+            credit_note,
+        },
+        "Order voided".to_string(),
+    )))
+}
+
+/// Get the status history of an order
+pub async fn get_order_status_history(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+) -> Result<Json<ApiResponse<Vec<OrderStatusHistoryResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let order = OrderModel::get_order_by_id(&state.db2, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+
+    let history = OrderStatusModel::get_history(&state.db2, order.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch status history: {}", e))),
+            )
+        })?;
+
+    let response: Vec<OrderStatusHistoryResponse> = history
+        .into_iter()
+        .map(|h| OrderStatusHistoryResponse {
+            from_status: h.osh_from_status,
+            to_status: h.osh_to_status,
+            reason: h.osh_reason,
+            changed_by: h.changed_by,
+            changed_at: h.created_at,
+        })
+        .collect();
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Move an order to a new status if the transition is allowed
+/// `allowed_from` narrows the statuses an action applies to (e.g. cancel only before payment)
+async fn transition_order(
+    state: &AppState,
+    auth: &AuthUser,
+    order_id: i32,
+    to: OrderStatus,
+    action: &str,
+    reason: Option<&str>,
+    allowed_from: impl Fn(OrderStatus) -> bool,
+) -> Result<(), (StatusCode, Json<ApiResponse<()>>)> {
+    let order = OrderModel::get_order_by_id(&state.db1, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+
+    let from = order.order_status;
+    if !allowed_from(from) || !from.can_transition_to(to) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(format!(
+                "Cannot {} an order that is {}",
+                action,
+                from.as_str()
+            ))),
+        ));
+    }
+
+    let changed = OrderStatusModel::change_status(&state.db1, order.id, auth.shop_id, from, to, auth.user_id, reason)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to update order status: {}", e))),
+            )
+        })?;
+
+    if !changed {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Order status was changed by another request, please reload".to_string())),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Discount cap of a shop role; shop owners are not capped
+fn discount_cap(role_id: i32, discount_type_id: i32, discount: f32) -> DiscountCap {
+    if role_id == SHOP_OWNER_ROLE_ID {
+        DiscountCap::Unlimited
+    } else {
+        DiscountCap::from_shop_role(discount_type_id, discount)
+    }
+}
+
+/// Verify a manager override and return the approver's user ID and discount cap
+/// Wrong passwords/PINs count toward the approver's login lockout
+async fn verify_discount_approver(
+    state: &AppState,
+    auth: &AuthUser,
+    approval: &DiscountApproval,
+) -> Result<(i32, DiscountCap), (StatusCode, Json<ApiResponse<()>>)> {
+    let rejected = || {
+        (
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error("Invalid approver credentials".to_string())),
+        )
+    };
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to verify approver: {}", e))),
+        )
+    };
+
+    let approver = match UserModel::get_user_by_email(&state.db1, &approval.username)
+        .await
+        .map_err(db_error)?
+    {
+        Some(approver) if approver.user_is_active == 1 => approver,
+        _ => {
+            dummy_password_check(approval.password.as_deref().or(approval.pin.as_deref()).unwrap_or_default());
+            return Err(rejected());
+        }
+    };
+
+    if approver.id == auth.user_id {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error("Discount must be approved by another user".to_string())),
+        ));
+    }
+
+    let login_lock = UserModel::get_login_lock(&state.db1, approver.id)
+        .await
+        .map_err(db_error)?;
+    if login_lock.is_some_and(|lock| check_login_lock(&lock, Utc::now().naive_utc()).is_some()) {
+        return Err((
+            StatusCode::LOCKED,
+            Json(ApiResponse::error("Approver account is temporarily locked".to_string())),
+        ));
+    }
+
+    let is_valid = match (&approval.password, &approval.pin) {
+        (Some(password), _) => check_password(password, &approver.user_password)
+            .map(|check| check != PasswordCheck::Invalid)
+            .unwrap_or(false),
+        (None, Some(pin)) => match UserModel::get_user_pin_hash(&state.db1, approver.id)
+            .await
+            .map_err(db_error)?
+        {
+            Some(pin_hash) => verify_password(pin, &pin_hash).unwrap_or(false),
+            None => false,
+        },
+        (None, None) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Approver password or PIN is required".to_string())),
+            ));
+        }
+    };
+
+    if !is_valid {
+        if let Err(e) = UserModel::register_failed_login(&state.db1, approver.id, MAX_FAILED_LOGINS, LOCKOUT_MINUTES).await {
+            tracing::error!("Failed to count failed approval for user {}: {}", approver.id, e);
+        }
+        return Err(rejected());
+    }
+
+    let approver_shop = UserModel::get_user_shop(&state.db1, approver.id, auth.shop_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(rejected)?;
+
+    let cap = discount_cap(approver_shop.role_id, approver_shop.sr_discount_type_id, approver_shop.sr_discount);
+
+    Ok((approver.id, cap))
+}
+
+/// Order as listed in search results and customer history
+pub(crate) fn order_response(order: Order) -> OrderResponse {
+    OrderResponse {
+        id: order.id,
+        shop_id: order.shop_id,
+        customer_id: order.customer_id,
+        order_code: order.order_code,
+        order_total: order.order_total,
+        order_discount: order.order_discount,
+        order_net: order.order_net,
+        order_vat: order.order_vat,
+        order_status: order.order_status,
+        deleted_at: order.deleted_at,
+    }
+}
+
+fn order_detail_response(
+    order: Order,
+    items: Vec<OrderLine>,
+    payments: &[Payment],
+    refunds: Vec<CreditNoteResponse>,
+    tax_invoice: Option<TaxInvoice>,
+) -> OrderDetailResponse {
+    let payment = summary_response(PaymentSummary::new(order.order_net, payments));
+
+    OrderDetailResponse {
+        id: order.id,
+        shop_id: order.shop_id,
+        customer_id: order.customer_id,
+        order_code: order.order_code,
+        order_total: order.order_total,
+        order_discount: order.order_discount,
+        order_net: order.order_net,
+        order_vatable: order.order_vatable,
+        order_vat: order.order_vat,
+        order_exempt: order.order_exempt,
+        order_status: order.order_status,
+        discount_approved_by: order.discount_approved_by,
+        items: items
+            .into_iter()
+            .map(|item| OrderItemResponse {
+                id: item.id,
+                product_id: item.product_id,
+                product_name: item.product_name,
+                quantity: item.oi_quantity,
+                price: item.oi_price,
+                discount: item.oi_discount,
+                total: item.oi_total,
+                vat_type: item.oi_vat_type,
+                vat: item.oi_vat,
+                net: item.oi_net,
+                refunded_quantity: item.oi_refunded_quantity,
+                refunded_amount: item.oi_refunded_amount,
+            })
+            .collect(),
+        payment,
+        refunds,
+        tax_invoice: tax_invoice.map(tax_invoice_response),
+    }
+}
@@ -0,0 +1,239 @@
+use axum::{
+    extract::{State, Path},
+    http::StatusCode,
+    Json,
+};
+use crate::configs::AppState;
+use crate::structs::{
+    ApiResponse, RecordPaymentRequest, PaymentResponse, PaymentSummaryResponse, OrderPaymentsResponse,
+    RecordPaymentResponse, PromptPayQrResponse,
+};
+use crate::models::{
+    Payment, PaymentSummary, NewTender, PaymentModel, OrderModel, OrderStatus, ShopModel, allocate_tenders,
+};
+use crate::middlewares::AuthUser;
+use crate::libs::{promptpay_payload, render_qr_png_base64, Money, PromptPayId};
+use validator::Validate;
+
+/// Get the payments of an order with paid/outstanding totals
+pub async fn get_order_payments(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+) -> Result<Json<ApiResponse<OrderPaymentsResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let order = OrderModel::get_order_by_id(&state.db2, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+
+    let payments = PaymentModel::get_order_payments(&state.db2, order.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch payments: {}", e))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success(OrderPaymentsResponse {
+        order_id: order.id,
+        order_net: order.order_net,
+        order_status: order.order_status,
+        summary: summary_response(PaymentSummary::new(order.order_net, &payments)),
+        payments: payments.into_iter().map(payment_response).collect(),
+    })))
+}
+
+/// Take payment on an order
+/// Tenders may be split across methods; cash may exceed the balance and the change is
+/// returned. The order becomes partially paid or paid automatically.
+pub async fn record_payment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<RecordPaymentRequest>,
+) -> Result<Json<ApiResponse<RecordPaymentResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Payment failed: {}", e))),
+        )
+    };
+
+    let order = OrderModel::get_order_by_id(&state.db1, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+
+    // Deposits secure a draft or confirmed order; full payments need a confirmed order
+    let payable = if payload.is_deposit {
+        matches!(order.order_status, OrderStatus::Draft | OrderStatus::Confirmed)
+    } else {
+        matches!(order.order_status, OrderStatus::Confirmed | OrderStatus::PartiallyPaid)
+    };
+    if !payable {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(format!(
+                "Cannot take {} on an order that is {}",
+                if payload.is_deposit { "a deposit" } else { "payment" },
+                order.order_status.as_str()
+            ))),
+        ));
+    }
+
+    let existing = PaymentModel::get_order_payments(&state.db1, order.id)
+        .await
+        .map_err(db_error)?;
+    let before = PaymentSummary::new(order.order_net, &existing);
+
+    let tenders: Vec<NewTender> = payload
+        .tenders
+        .iter()
+        .map(|t| NewTender {
+            method: t.method,
+            amount: t.amount,
+            reference: t.reference.clone(),
+        })
+        .collect();
+
+    let allocated = allocate_tenders(&tenders, before.outstanding).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(e.to_string())),
+        )
+    })?;
+
+    let applied: Money = allocated.iter().map(|t| t.amount).sum();
+    if payload.is_deposit && applied >= before.outstanding {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("A deposit must be less than the order balance".to_string())),
+        ));
+    }
+
+    let (status, receipt_number) = PaymentModel::record_payments(&state.db1, &order, before.paid, &allocated, payload.is_deposit, auth.user_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::CONFLICT,
+                Json(ApiResponse::error("Order was changed by another request, please reload".to_string())),
+            )
+        })?;
+
+    let change: Money = allocated.iter().map(|t| t.change).sum();
+
+    // Read back from the primary; the replica may not have the payments yet
+    let payments = PaymentModel::get_order_payments(&state.db1, order.id)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(ApiResponse::success(RecordPaymentResponse {
+        order_status: status,
+        receipt_number,
+        change,
+        summary: summary_response(PaymentSummary::new(order.order_net, &payments)),
+        payments: payments.into_iter().map(payment_response).collect(),
+    })))
+}
+
+/// PromptPay QR for the order's outstanding balance, paid to the shop's PromptPay ID
+pub async fn get_order_promptpay_qr(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+) -> Result<Json<ApiResponse<PromptPayQrResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to create PromptPay QR: {}", e))),
+        )
+    };
+    let conflict = |message: String| {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(message)),
+        )
+    };
+
+    let order = OrderModel::get_order_by_id(&state.db2, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+    if !matches!(order.order_status, OrderStatus::Confirmed | OrderStatus::PartiallyPaid) {
+        return Err(conflict(format!(
+            "Cannot take payment on an order that is {}",
+            order.order_status.as_str()
+        )));
+    }
+
+    let shop = ShopModel::get_shop_by_id(&state.db2, auth.shop_id)
+        .await
+        .map_err(db_error)?;
+    let promptpay_id = shop
+        .shop_promptpay_id
+        .as_deref()
+        .and_then(PromptPayId::parse)
+        .ok_or_else(|| conflict("Set the shop's PromptPay ID first".to_string()))?;
+
+    let payments = PaymentModel::get_order_payments(&state.db2, order.id)
+        .await
+        .map_err(db_error)?;
+    let outstanding = PaymentSummary::new(order.order_net, &payments).outstanding;
+    if outstanding <= Money::ZERO {
+        return Err(conflict("Nothing is left to pay on this order".to_string()));
+    }
+
+    let payload = promptpay_payload(&promptpay_id, Some(outstanding));
+    let qr_code = render_qr_png_base64(&payload).map_err(db_error)?;
+
+    Ok(Json(ApiResponse::success(PromptPayQrResponse {
+        order_id: order.id,
+        amount: outstanding,
+        payload,
+        qr_code,
+    })))
+}
+
+pub(crate) fn summary_response(summary: PaymentSummary) -> PaymentSummaryResponse {
+    PaymentSummaryResponse {
+        paid: summary.paid,
+        deposit: summary.deposit,
+        outstanding: summary.outstanding,
+    }
+}
+
+fn payment_response(payment: Payment) -> PaymentResponse {
+    PaymentResponse {
+        id: payment.id,
+        receipt_number: payment.payment_receipt_number,
+        method: payment.payment_method,
+        amount: payment.payment_amount,
+        tendered: payment.payment_tendered,
+        change: payment.payment_change,
+        reference: payment.payment_reference,
+        is_deposit: payment.payment_is_deposit,
+        created_by: payment.created_by,
+        created_at: payment.created_at,
+    }
+}
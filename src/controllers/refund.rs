@@ -0,0 +1,218 @@
+use axum::{
+    extract::{State, Path},
+    http::StatusCode,
+    Json,
+};
+use crate::configs::AppState;
+use crate::structs::{
+    ApiResponse, CreateRefundRequest, CreateRefundResponse, CreditNoteResponse, CreditNoteItemResponse,
+};
+use crate::models::{
+    Refund, RefundLine, NewRefund, RefundRequestLine, RefundModel, OrderModel, OrderStatus, PaymentModel,
+    PaymentSummary, allocate_refund, is_full_refund,
+};
+use crate::middlewares::AuthUser;
+use crate::libs::Money;
+use super::order::record_order_audit;
+use validator::Validate;
+
+/// Get the refunds (credit notes) of an order
+pub async fn get_order_refunds(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+) -> Result<Json<ApiResponse<Vec<CreditNoteResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let order = OrderModel::get_order_by_id(&state.db2, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to fetch refunds: {}", e))),
+        )
+    };
+
+    let refunds = RefundModel::get_order_refunds(&state.db2, order.id)
+        .await
+        .map_err(db_error)?;
+    let lines = RefundModel::get_order_refund_lines(&state.db2, order.id)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(ApiResponse::success(credit_note_responses(refunds, &lines))))
+}
+
+/// Refund a paid order, fully or per line item, and issue a credit note
+/// Refunded units can be returned to stock; the order becomes refunded once nothing is left
+pub async fn create_refund(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<CreateRefundRequest>,
+) -> Result<Json<ApiResponse<CreateRefundResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Refund failed: {}", e))),
+        )
+    };
+
+    let order = OrderModel::get_order_by_id(&state.db1, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+
+    if !order.order_status.has_payment() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(format!(
+                "Cannot refund an order that is {}",
+                order.order_status.as_str()
+            ))),
+        ));
+    }
+
+    let lines = OrderModel::get_order_items(&state.db1, order.id)
+        .await
+        .map_err(db_error)?;
+
+    let requests: Vec<RefundRequestLine> = match &payload.items {
+        Some(items) => items
+            .iter()
+            .map(|item| RefundRequestLine {
+                order_item_id: item.order_item_id,
+                quantity: item.quantity,
+                restock: item.restock,
+            })
+            .collect(),
+        // Full refund of whatever is left
+        None => lines
+            .iter()
+            .filter(|line| line.oi_refunded_quantity < i64::from(line.oi_quantity))
+            .map(|line| RefundRequestLine {
+                order_item_id: line.id,
+                quantity: line.oi_quantity - line.oi_refunded_quantity as i32,
+                restock: payload.restock,
+            })
+            .collect(),
+    };
+
+    let items = allocate_refund(&lines, &requests).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(e.to_string())),
+        )
+    })?;
+
+    // Never refund more than was actually received
+    let payments = PaymentModel::get_order_payments(&state.db1, order.id)
+        .await
+        .map_err(db_error)?;
+    let refunds = RefundModel::get_order_refunds(&state.db1, order.id)
+        .await
+        .map_err(db_error)?;
+    let paid = PaymentSummary::new(order.order_net, &payments).paid;
+    let refunded: Money = refunds.iter().map(|r| r.refund_amount).sum();
+    let amount: Money = items.iter().map(|i| i.amount).sum();
+
+    if amount > paid - refunded {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!(
+                "Refund of {} exceeds the {} paid and not yet refunded",
+                amount,
+                paid - refunded
+            ))),
+        ));
+    }
+
+    let full_refund = is_full_refund(&lines, &items);
+    let new_refund = NewRefund {
+        method: payload.method,
+        reason: payload.reason.clone(),
+        created_by: auth.user_id,
+    };
+
+    let refund_id = RefundModel::create_refund(
+        &state.db1,
+        &order,
+        paid,
+        refunded,
+        &new_refund,
+        &items,
+        full_refund.then_some(OrderStatus::Refunded),
+    )
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Order was changed by another request, please reload".to_string())),
+        )
+    })?;
+
+    let credit_note = issued_credit_note(&state, order.id, refund_id)
+        .await
+        .map_err(db_error)?;
+
+    record_order_audit(&state, &auth, order.id, "refund", Some(&credit_note.credit_note_number)).await;
+
+    Ok(Json(ApiResponse::success(CreateRefundResponse {
+        order_status: if full_refund { OrderStatus::Refunded } else { order.order_status },
+        credit_note,
+    })))
+}
+
+/// Read a credit note back right after issuing it
+/// Reads from the primary; the replica may not have the refund yet
+pub(crate) async fn issued_credit_note(state: &AppState, order_id: i32, refund_id: i32) -> anyhow::Result<CreditNoteResponse> {
+    let refunds = RefundModel::get_order_refunds(&state.db1, order_id).await?;
+    let refund_lines = RefundModel::get_order_refund_lines(&state.db1, order_id).await?;
+
+    credit_note_responses(refunds, &refund_lines)
+        .into_iter()
+        .find(|note| note.id == refund_id)
+        .ok_or_else(|| anyhow::anyhow!("refund {} not found after insert", refund_id))
+}
+
+pub(crate) fn credit_note_responses(refunds: Vec<Refund>, lines: &[RefundLine]) -> Vec<CreditNoteResponse> {
+    refunds
+        .into_iter()
+        .map(|refund| CreditNoteResponse {
+            id: refund.id,
+            credit_note_number: refund.refund_number,
+            method: refund.refund_method,
+            amount: refund.refund_amount,
+            reason: refund.refund_reason,
+            items: lines
+                .iter()
+                .filter(|line| line.refund_id == refund.id)
+                .map(|line| CreditNoteItemResponse {
+                    order_item_id: line.order_item_id,
+                    quantity: line.ri_quantity,
+                    amount: line.ri_amount,
+                    restock: line.ri_restock,
+                })
+                .collect(),
+            created_by: refund.created_by,
+            created_at: refund.created_at,
+        })
+        .collect()
+}
@@ -0,0 +1,192 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use crate::configs::AppState;
+use crate::structs::{
+    ApiResponse, DocumentSequenceResponse, UpdateDocumentSequenceRequest, UpdatePromptPayRequest,
+    UpdateReceiptPrinterRequest, UpdateShopTaxInfoRequest,
+};
+use crate::models::{DocumentSequence, DocumentSequenceModel, DocumentType, ShopModel};
+use crate::middlewares::AuthUser;
+use crate::libs::{thailand_today, PromptPayId};
+use validator::Validate;
+
+/// Get the numbering format of every document type of the shop
+/// Types the shop has not configured yet show the defaults
+pub async fn get_document_sequences(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<ApiResponse<Vec<DocumentSequenceResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let sequences = DocumentSequenceModel::get_shop_sequences(&state.db2, auth.shop_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch document sequences: {}", e))),
+            )
+        })?;
+
+    let today = thailand_today();
+    let response: Vec<DocumentSequenceResponse> = DocumentType::ALL
+        .iter()
+        .map(|&document_type| {
+            let sequence = sequences
+                .iter()
+                .find(|s| s.ds_type == document_type)
+                .cloned()
+                .unwrap_or_else(|| DocumentSequence::default_for(auth.shop_id, document_type));
+
+            DocumentSequenceResponse {
+                document_type,
+                next_number: sequence.preview(&today),
+                prefix: sequence.ds_prefix,
+                reset: sequence.ds_reset,
+                padding: sequence.ds_padding,
+            }
+        })
+        .collect();
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Change the prefix, reset period or padding of a document type
+/// The running number continues; it restarts from 1 when the period changes
+/// Prefix and reset period are fixed once the current period has numbers (padding can change)
+pub async fn update_document_sequence(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<UpdateDocumentSequenceRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let saved = DocumentSequenceModel::save_settings(
+        &state.db1,
+        auth.shop_id,
+        payload.document_type,
+        &payload.prefix,
+        payload.reset,
+        payload.padding,
+        thailand_today(),
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to save document sequence: {}", e))),
+        )
+    })?;
+
+    if !saved {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(
+                "Numbers of the current period were already issued; the prefix and reset period can change once the next period starts".to_string(),
+            )),
+        ));
+    }
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Document numbering updated".to_string(),
+    )))
+}
+
+/// Set the shop's tax ID and branch code
+pub async fn update_shop_tax_info(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<UpdateShopTaxInfoRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    ShopModel::update_tax_info(&state.db1, auth.shop_id, &payload.tax_id, &payload.branch_code)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to save tax information: {}", e))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Tax information updated".to_string(),
+    )))
+}
+
+/// Set the shop's PromptPay ID, stored as digits only
+pub async fn update_shop_promptpay(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<UpdatePromptPayRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let promptpay_id = PromptPayId::parse(&payload.promptpay_id)
+        .map(|id| id.as_str().to_string())
+        .unwrap_or(payload.promptpay_id);
+
+    ShopModel::update_promptpay_id(&state.db1, auth.shop_id, &promptpay_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to save PromptPay ID: {}", e))),
+            )
+        })?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "PromptPay ID updated".to_string(),
+    )))
+}
+
+/// Set the logo and network printer used for thermal receipts
+pub async fn update_receipt_printer(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<UpdateReceiptPrinterRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    ShopModel::update_receipt_printer(
+        &state.db1,
+        auth.shop_id,
+        payload.logo_url.as_deref(),
+        payload.printer_address.as_deref(),
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to save receipt printer settings: {}", e))),
+        )
+    })?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Receipt printer settings updated".to_string(),
+    )))
+}
@@ -0,0 +1,104 @@
+use axum::{
+    extract::{State, Path},
+    http::StatusCode,
+    Json,
+};
+use crate::configs::AppState;
+use crate::structs::{ApiResponse, IssueTaxInvoiceRequest, TaxInvoiceResponse};
+use crate::models::{NewTaxInvoice, OrderModel, OrderStatus, ShopModel, TaxInvoice, TaxInvoiceModel};
+use crate::middlewares::AuthUser;
+use super::order::record_order_audit;
+use validator::Validate;
+
+/// Issue a full tax invoice (ใบกำกับภาษีเต็มรูป) for a paid order
+/// Needs the shop's tax ID; the buyer's tax ID is checked against its check digit
+pub async fn issue_tax_invoice(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<IssueTaxInvoiceRequest>,
+) -> Result<Json<ApiResponse<TaxInvoiceResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to issue tax invoice: {}", e))),
+        )
+    };
+
+    let order = OrderModel::get_order_by_id(&state.db1, order_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Order not found".to_string())),
+            )
+        })?;
+
+    if order.order_status != OrderStatus::Paid {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(format!(
+                "Tax invoices are issued for paid orders, this order is {}",
+                order.order_status.as_str()
+            ))),
+        ));
+    }
+
+    let shop = ShopModel::get_shop_by_id(&state.db1, auth.shop_id)
+        .await
+        .map_err(db_error)?;
+    if shop.shop_tax_id.is_none() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Set the shop's tax ID before issuing tax invoices".to_string())),
+        ));
+    }
+
+    let invoice = NewTaxInvoice {
+        customer_name: payload.customer_name,
+        customer_tax_id: payload.tax_id,
+        customer_branch_code: payload.branch_code,
+        customer_address: payload.address,
+        issued_by: auth.user_id,
+    };
+
+    TaxInvoiceModel::create(&state.db1, &order, &invoice)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::CONFLICT,
+                Json(ApiResponse::error("A tax invoice was already issued for this order, or it is no longer paid".to_string())),
+            )
+        })?;
+
+    // Read back from the primary; the replica may not have the invoice yet
+    let issued = TaxInvoiceModel::get_by_order(&state.db1, order.id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| db_error(anyhow::anyhow!("tax invoice of order {} not found after insert", order.id)))?;
+
+    record_order_audit(&state, &auth, order.id, "tax_invoice", Some(&issued.ti_number)).await;
+
+    Ok(Json(ApiResponse::success(tax_invoice_response(issued))))
+}
+
+pub(crate) fn tax_invoice_response(invoice: TaxInvoice) -> TaxInvoiceResponse {
+    TaxInvoiceResponse {
+        id: invoice.id,
+        tax_invoice_number: invoice.ti_number,
+        customer_name: invoice.ti_customer_name,
+        tax_id: invoice.ti_customer_tax_id,
+        branch_code: invoice.ti_customer_branch_code,
+        address: invoice.ti_customer_address,
+        issued_by: invoice.issued_by,
+        issued_at: invoice.created_at,
+    }
+}
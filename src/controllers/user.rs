@@ -4,9 +4,20 @@ use axum::{
     Json,
 };
 use crate::configs::AppState;
-use crate::structs::{UserResponse, ApiResponse, UpdateUserRequest};
-use crate::models::UserModel;
-use crate::middlewares::AuthUser;
+use validator::Validate;
+use crate::structs::{
+    UserResponse, ApiResponse, UpdateUserRequest, InviteUserRequest, ShopInviteResponse,
+    SetPinRequest, ClaimInviteRequest,
+};
+use crate::models::{
+    ShopInvite, UserModel, UserShopModel,
+    user_shop::{invite_code_hash, INVITE_ACCEPTED, INVITE_CANCELLED, INVITE_DECLINED, INVITE_PENDING},
+};
+use crate::middlewares::{AuthUser, check_password, hash_password, generate_random_string, PasswordCheck};
+use crate::libs::{send_shop_invite_email, send_sms};
+
+/// Length of the code sent with an SMS invitation
+const INVITE_CODE_LENGTH: usize = 8;
 
 /// Get user by ID
 /// Equivalent to Go's UserDetail function
@@ -121,3 +132,439 @@ pub async fn get_shop_users(
 
     Ok(Json(ApiResponse::success(response)))
 }
+
+/// Set the current user's discount approval PIN (requires current password)
+pub async fn set_pin(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<SetPinRequest>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    if !payload.pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("PIN must be 4-8 digits".to_string())),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to set PIN: {}", e))),
+        )
+    };
+
+    let user = UserModel::get_user_account(&state.db1, auth.user_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("User not found".to_string())),
+            )
+        })?;
+
+    let password_check = check_password(&payload.password, &user.user_password).unwrap_or(PasswordCheck::Invalid);
+    if password_check == PasswordCheck::Invalid {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::error("Current password is incorrect".to_string())),
+        ));
+    }
+
+    let pin_hash = hash_password(&payload.pin).map_err(db_error)?;
+    UserModel::update_user_pin(&state.db1, user.id, &pin_hash)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "PIN updated successfully".to_string(),
+    )))
+}
+
+/// Clear a user's failed login counter and lockout (requires user.unlock)
+pub async fn unlock_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    // Only users of the admin's current shop can be unlocked
+    UserModel::get_user_by_id(&state.db1, user_id, auth.shop_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("User not found".to_string())),
+            )
+        })?;
+
+    UserModel::reset_failed_logins(&state.db1, user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Unlock failed: {}", e))),
+            )
+        })?;
+
+    tracing::info!("User {} unlocked by user {} in shop {}", user_id, auth.user_id, auth.shop_id);
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "User unlocked successfully".to_string(),
+    )))
+}
+
+/// Invite a user to the current shop by email or phone (requires user.invite)
+pub async fn invite_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<InviteUserRequest>,
+) -> Result<Json<ApiResponse<ShopInviteResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    if payload.email.is_none() && payload.tel.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Email or phone number is required".to_string())),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to invite user: {}", e))),
+        )
+    };
+
+    let role_exists = UserShopModel::shop_role_exists(&state.db1, auth.shop_id, payload.shop_role_id)
+        .await
+        .map_err(db_error)?;
+
+    if !role_exists {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Shop role not found".to_string())),
+        ));
+    }
+
+    // Link the invitation straight away if the email already has an account
+    let existing_user = match payload.email.as_deref() {
+        Some(email) => UserModel::get_user_by_email(&state.db1, email)
+            .await
+            .map_err(db_error)?,
+        None => None,
+    };
+    let user_id = existing_user.map(|u| u.id);
+
+    let existing = UserShopModel::find_existing(
+        &state.db1,
+        auth.shop_id,
+        user_id,
+        payload.email.as_deref(),
+        payload.tel.as_deref(),
+    )
+    .await
+    .map_err(db_error)?;
+
+    if let Some(existing) = existing {
+        let message = if existing.us_invite == INVITE_ACCEPTED {
+            "User is already a member of this shop"
+        } else {
+            "User has already been invited"
+        };
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(message.to_string())),
+        ));
+    }
+
+    let invite_id = UserShopModel::create_invite(
+        &state.db1,
+        auth.shop_id,
+        user_id,
+        payload.shop_role_id,
+        payload.email.as_deref(),
+        payload.tel.as_deref(),
+        auth.user_id,
+    )
+    .await
+    .map_err(db_error)?;
+
+    let invite = UserShopModel::get_invite(&state.db1, invite_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error("Invitation was not saved".to_string())),
+            )
+        })?;
+
+    notify_invitee(&state, &invite).await;
+
+    Ok(Json(ApiResponse::success_with_message(
+        invite_response(invite),
+        "Invitation sent".to_string(),
+    )))
+}
+
+/// List pending invitations of the current shop (requires user.invite)
+pub async fn get_shop_invites(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<ApiResponse<Vec<ShopInviteResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let invites = UserShopModel::get_shop_invites(&state.db1, auth.shop_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch invitations: {}", e))),
+            )
+        })?;
+
+    let response: Vec<ShopInviteResponse> = invites.into_iter().map(invite_response).collect();
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Resend a pending invitation (requires user.invite)
+pub async fn resend_invite(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(invite_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let invite = get_shop_pending_invite(&state, auth.shop_id, invite_id).await?;
+
+    UserShopModel::touch_invite(&state.db1, invite.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to resend invitation: {}", e))),
+            )
+        })?;
+
+    notify_invitee(&state, &invite).await;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Invitation resent".to_string(),
+    )))
+}
+
+/// Cancel a pending invitation (requires user.invite)
+pub async fn cancel_invite(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(invite_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let invite = get_shop_pending_invite(&state, auth.shop_id, invite_id).await?;
+    respond_to_invite(&state, invite.id, INVITE_CANCELLED).await?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Invitation cancelled".to_string(),
+    )))
+}
+
+/// List pending invitations for the current user
+pub async fn get_my_invites(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<ApiResponse<Vec<ShopInviteResponse>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let invites = UserShopModel::get_user_invites(&state.db1, auth.user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch invitations: {}", e))),
+            )
+        })?;
+
+    let response: Vec<ShopInviteResponse> = invites.into_iter().map(invite_response).collect();
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Claim an invitation sent by SMS with its code
+/// The code proves the user has the invited phone; the invitation then shows in their list
+/// and still has to be accepted
+pub async fn claim_invite(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<ClaimInviteRequest>,
+) -> Result<Json<ApiResponse<ShopInviteResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    if let Err(errors) = payload.validate() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Validation error: {}", errors))),
+        ));
+    }
+
+    let db_error = |e: anyhow::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to claim invitation: {}", e))),
+        )
+    };
+
+    let invite_id = UserShopModel::claim_invite_by_code(&state.db1, auth.user_id, &invite_code_hash(&payload.code))
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Invalid or already used invitation code".to_string())),
+            )
+        })?;
+
+    let invite = get_own_pending_invite(&state, auth.user_id, invite_id).await?;
+
+    Ok(Json(ApiResponse::success(invite_response(invite))))
+}
+
+/// Accept an invitation addressed to the current user
+pub async fn accept_invite(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(invite_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let invite = get_own_pending_invite(&state, auth.user_id, invite_id).await?;
+    respond_to_invite(&state, invite.id, INVITE_ACCEPTED).await?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        format!("You have joined {}", invite.shop_name),
+    )))
+}
+
+/// Decline an invitation addressed to the current user
+pub async fn decline_invite(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(invite_id): Path<i32>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    let invite = get_own_pending_invite(&state, auth.user_id, invite_id).await?;
+    respond_to_invite(&state, invite.id, INVITE_DECLINED).await?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "Invitation declined".to_string(),
+    )))
+}
+
+/// Load a pending invitation belonging to the shop
+async fn get_shop_pending_invite(
+    state: &AppState,
+    shop_id: i32,
+    invite_id: i32,
+) -> Result<ShopInvite, (StatusCode, Json<ApiResponse<()>>)> {
+    find_pending_invite(state, invite_id, |invite| invite.shop_id == shop_id).await
+}
+
+/// Load a pending invitation addressed to the user
+async fn get_own_pending_invite(
+    state: &AppState,
+    user_id: i32,
+    invite_id: i32,
+) -> Result<ShopInvite, (StatusCode, Json<ApiResponse<()>>)> {
+    find_pending_invite(state, invite_id, |invite| invite.user_id == Some(user_id)).await
+}
+
+async fn find_pending_invite(
+    state: &AppState,
+    invite_id: i32,
+    belongs: impl Fn(&ShopInvite) -> bool,
+) -> Result<ShopInvite, (StatusCode, Json<ApiResponse<()>>)> {
+    let invite = UserShopModel::get_invite(&state.db1, invite_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to fetch invitation: {}", e))),
+            )
+        })?
+        .filter(|invite| belongs(invite) && invite.us_invite == INVITE_PENDING)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Invitation not found".to_string())),
+            )
+        })?;
+
+    Ok(invite)
+}
+
+async fn respond_to_invite(
+    state: &AppState,
+    invite_id: i32,
+    status: i8,
+) -> Result<(), (StatusCode, Json<ApiResponse<()>>)> {
+    let updated = UserShopModel::update_invite_status(&state.db1, invite_id, status)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(format!("Failed to update invitation: {}", e))),
+            )
+        })?;
+
+    if !updated {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error("Invitation is no longer pending".to_string())),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Send the invitation by email or SMS; failures are logged, not returned
+/// An SMS carries a new single-use code, which the invitee enters after logging in
+async fn notify_invitee(state: &AppState, invite: &ShopInvite) {
+    if let Some(email) = invite.us_invite_email.as_deref() {
+        if let Err(e) = send_shop_invite_email(state.mailer.as_ref(), email, &invite.shop_name).await {
+            tracing::error!("Failed to send invitation email for invite {}: {}", invite.id, e);
+        }
+    } else if let Some(tel) = invite.us_invite_tel.as_deref() {
+        let code = generate_random_string(INVITE_CODE_LENGTH).to_uppercase();
+        if let Err(e) = UserShopModel::set_invite_code(&state.db1, invite.id, &invite_code_hash(&code)).await {
+            tracing::error!("Failed to save invitation code for invite {}: {}", invite.id, e);
+            return;
+        }
+
+        let message = format!(
+            "You have been invited to join {}. Sign up or log in and enter code {} to accept.",
+            invite.shop_name, code
+        );
+        if let Err(e) = send_sms(tel, &message).await {
+            tracing::error!("Failed to send invitation SMS for invite {}: {}", invite.id, e);
+        }
+    }
+}
+
+fn invite_response(invite: ShopInvite) -> ShopInviteResponse {
+    ShopInviteResponse {
+        id: invite.id,
+        shop_id: invite.shop_id,
+        shop_name: invite.shop_name,
+        shop_role_id: invite.shop_role_id,
+        email: invite.us_invite_email,
+        tel: invite.us_invite_tel,
+        status: invite.us_invite,
+        invited_at: invite.us_invited_at,
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::{NaiveDate, Datelike, Duration, Weekday};
+use chrono::{NaiveDate, Datelike, Duration, Utc, Weekday};
 use anyhow::Result;
 
 /// Calendar utilities
@@ -99,6 +99,22 @@ pub fn count_business_days(start: NaiveDate, end: NaiveDate) -> i32 {
     count
 }
 
+/// Thailand is UTC+7 all year (no daylight saving)
+const THAILAND_UTC_OFFSET_HOURS: i64 = 7;
+
+/// Buddhist Era years are 543 ahead of the Gregorian calendar
+const BUDDHIST_ERA_OFFSET: i32 = 543;
+
+/// Today's date in Thailand, independent of the server timezone
+pub fn thailand_today() -> NaiveDate {
+    (Utc::now() + Duration::hours(THAILAND_UTC_OFFSET_HOURS)).date_naive()
+}
+
+/// Buddhist Era year of a date (2024 -> 2567)
+pub fn buddhist_year(date: &NaiveDate) -> i32 {
+    date.year() + BUDDHIST_ERA_OFFSET
+}
+
 /// Get date range
 pub fn date_range(start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
     let mut dates = Vec::new();
@@ -150,4 +166,10 @@ mod tests {
 
         assert_eq!(count_business_days(start, end), 5);
     }
+
+    #[test]
+    fn test_buddhist_year() {
+        assert_eq!(buddhist_year(&NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()), 2567);
+        assert_eq!(buddhist_year(&NaiveDate::from_ymd_opt(2026, 12, 31).unwrap()), 2569);
+    }
 }
@@ -0,0 +1,119 @@
+// Discount calculation and staff discount caps
+
+use serde::{Deserialize, Serialize};
+use anyhow::{bail, Result};
+use rust_decimal::Decimal;
+use super::money::Money;
+
+/// shop_roles.sr_discount_type_id: cap is a percentage of the order total
+pub const DISCOUNT_TYPE_PERCENT: i32 = 1;
+
+/// shop_roles.sr_discount_type_id: cap is a fixed amount in baht per order
+pub const DISCOUNT_TYPE_FIXED: i32 = 2;
+
+/// Discount requested on an item or on the whole bill
+/// JSON: {"type": "percent", "value": "10"} or {"type": "fixed", "value": "50.00"}
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Discount {
+    Percent(Decimal),
+    Fixed(Money),
+}
+
+impl Discount {
+    /// Discount amount on `base`, rounded to satang
+    /// Fails for negative values, percentages over 100 or amounts larger than base
+    pub fn amount(&self, base: Money) -> Result<Money> {
+        match *self {
+            Discount::Percent(percent) => {
+                if percent.is_sign_negative() || percent > Decimal::ONE_HUNDRED {
+                    bail!("Discount percent must be between 0 and 100");
+                }
+                Ok(base.percent(percent))
+            }
+            Discount::Fixed(value) => {
+                if value.is_negative() {
+                    bail!("Discount amount cannot be negative");
+                }
+                if value > base {
+                    bail!("Discount amount exceeds the price");
+                }
+                Ok(value.round_satang())
+            }
+        }
+    }
+}
+
+/// Largest discount a staff member may give on one order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiscountCap {
+    Unlimited,
+    Percent(Decimal),
+    Fixed(Money),
+}
+
+impl DiscountCap {
+    /// Cap from a shop role's sr_discount_type_id/sr_discount; unknown types allow no discount
+    pub fn from_shop_role(discount_type_id: i32, discount: f32) -> Self {
+        let value = Money::from_f64_lossy(discount as f64);
+        match discount_type_id {
+            DISCOUNT_TYPE_PERCENT => DiscountCap::Percent(value.amount()),
+            DISCOUNT_TYPE_FIXED => DiscountCap::Fixed(value),
+            _ => DiscountCap::Fixed(Money::ZERO),
+        }
+    }
+
+    /// Whether a total discount on an order with the given gross total is within the cap
+    pub fn allows(&self, discount: Money, gross: Money) -> bool {
+        match *self {
+            DiscountCap::Unlimited => true,
+            DiscountCap::Percent(percent) => discount <= gross.percent(percent),
+            DiscountCap::Fixed(value) => discount <= value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn baht(amount: Decimal) -> Money {
+        Money::new(amount)
+    }
+
+    #[test]
+    fn test_discount_amount() {
+        assert_eq!(Discount::Percent(dec!(10)).amount(baht(dec!(250))).unwrap(), baht(dec!(25)));
+        assert_eq!(Discount::Percent(dec!(33)).amount(baht(dec!(10))).unwrap(), baht(dec!(3.3)));
+        assert_eq!(Discount::Percent(dec!(12.5)).amount(baht(dec!(0.20))).unwrap(), baht(dec!(0.03)));
+        assert_eq!(Discount::Fixed(baht(dec!(50))).amount(baht(dec!(250))).unwrap(), baht(dec!(50)));
+
+        assert!(Discount::Percent(dec!(120)).amount(baht(dec!(100))).is_err());
+        assert!(Discount::Fixed(baht(dec!(-1))).amount(baht(dec!(100))).is_err());
+        assert!(Discount::Fixed(baht(dec!(150))).amount(baht(dec!(100))).is_err());
+    }
+
+    #[test]
+    fn test_discount_json() {
+        let discount: Discount = serde_json::from_str(r#"{"type":"percent","value":10}"#).unwrap();
+        assert_eq!(discount, Discount::Percent(dec!(10)));
+
+        let discount: Discount = serde_json::from_str(r#"{"type":"fixed","value":"49.50"}"#).unwrap();
+        assert_eq!(discount, Discount::Fixed(baht(dec!(49.50))));
+    }
+
+    #[test]
+    fn test_discount_cap() {
+        let percent = DiscountCap::from_shop_role(DISCOUNT_TYPE_PERCENT, 10.0);
+        assert!(percent.allows(baht(dec!(100)), baht(dec!(1000))));
+        assert!(!percent.allows(baht(dec!(100.01)), baht(dec!(1000))));
+
+        let fixed = DiscountCap::from_shop_role(DISCOUNT_TYPE_FIXED, 50.0);
+        assert!(fixed.allows(baht(dec!(50)), baht(dec!(10000))));
+        assert!(!fixed.allows(baht(dec!(50.01)), baht(dec!(10000))));
+
+        assert!(!DiscountCap::from_shop_role(0, 99.0).allows(baht(dec!(1)), baht(dec!(100))));
+        assert!(DiscountCap::Unlimited.allows(baht(dec!(1000000)), baht(dec!(1))));
+    }
+}
@@ -0,0 +1,696 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use printpdf::{IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point};
+use serde::Deserialize;
+use super::calendar::buddhist_year;
+use super::money::Money;
+use super::vat::{VatBreakdown, VatType};
+
+// Printable sales documents (receipts, tax invoices, credit notes)
+// A document is first laid out as rows of text, then drawn onto 80mm thermal paper or A4 pages
+
+/// Printable document types
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    #[default]
+    Receipt,
+    AbbreviatedTaxInvoice,
+    FullTaxInvoice,
+    CreditNote,
+}
+
+impl DocumentKind {
+    pub fn title_th(self) -> &'static str {
+        match self {
+            DocumentKind::Receipt => "ใบเสร็จรับเงิน",
+            DocumentKind::AbbreviatedTaxInvoice => "ใบกำกับภาษีอย่างย่อ",
+            DocumentKind::FullTaxInvoice => "ใบกำกับภาษี",
+            DocumentKind::CreditNote => "ใบลดหนี้",
+        }
+    }
+
+    pub fn title_en(self) -> &'static str {
+        match self {
+            DocumentKind::Receipt => "Receipt",
+            DocumentKind::AbbreviatedTaxInvoice => "Abbreviated Tax Invoice",
+            DocumentKind::FullTaxInvoice => "Tax Invoice",
+            DocumentKind::CreditNote => "Credit Note",
+        }
+    }
+
+    /// Documents that must print the seller's tax ID and the VAT breakdown
+    pub fn shows_vat(self) -> bool {
+        !matches!(self, DocumentKind::Receipt)
+    }
+}
+
+/// Paper the document is laid out for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaperSize {
+    /// 80mm roll, one page as long as the document
+    #[default]
+    Thermal80,
+    A4,
+}
+
+/// Page geometry and font sizes (points) per paper size
+struct PaperLayout {
+    width: f32,
+    /// Fixed page height, None for a roll
+    height: Option<f32>,
+    margin: f32,
+    font_size: f32,
+    title_size: f32,
+}
+
+impl PaperSize {
+    fn layout(self) -> PaperLayout {
+        match self {
+            PaperSize::Thermal80 => PaperLayout {
+                width: 80.0,
+                height: None,
+                margin: 4.0,
+                font_size: 9.0,
+                title_size: 12.0,
+            },
+            PaperSize::A4 => PaperLayout {
+                width: 210.0,
+                height: Some(297.0),
+                margin: 20.0,
+                font_size: 11.0,
+                title_size: 16.0,
+            },
+        }
+    }
+}
+
+/// Seller or buyer printed on the document
+#[derive(Debug, Clone, Default)]
+pub struct DocumentParty {
+    pub name: String,
+    pub address: Option<String>,
+    pub tel: Option<String>,
+    pub tax_id: Option<String>,
+    pub branch_code: Option<String>,
+}
+
+/// Item line; amount is the line total after its own discount
+#[derive(Debug, Clone)]
+pub struct DocumentLine {
+    pub name: String,
+    pub quantity: i32,
+    pub unit_price: Money,
+    pub discount: Money,
+    pub amount: Money,
+}
+
+impl DocumentLine {
+    /// Credit note line for `quantity` units refunded for `amount` (VAT included)
+    /// The unit price is shown VAT included as well, so the discount is what the customer
+    /// got off the line (its own and its share of the bill discount); rounding of the
+    /// partial refund never prints it below zero
+    pub fn refunded(name: String, quantity: i32, unit_price: Money, vat_type: VatType, amount: Money) -> Self {
+        let unit_price = vat_type.gross(unit_price);
+        DocumentLine {
+            name,
+            quantity,
+            unit_price,
+            discount: (unit_price * quantity - amount).max(Money::ZERO),
+            amount,
+        }
+    }
+}
+
+/// Payment (or refund) printed under the totals
+#[derive(Debug, Clone)]
+pub struct DocumentPayment {
+    pub label: String,
+    pub amount: Money,
+    /// Cash handed over, printed with the change when change was given
+    pub tendered: Money,
+    pub change: Money,
+}
+
+/// Everything needed to print one document
+/// subtotal is the sum of the lines before the bill discount; total is what the customer pays
+/// (equal to vat.payable() on documents showing VAT)
+#[derive(Debug, Clone)]
+pub struct DocumentData {
+    pub kind: DocumentKind,
+    pub number: String,
+    /// Order code, or the original invoice for a credit note
+    pub reference: Option<String>,
+    pub issued_at: NaiveDateTime,
+    pub seller: DocumentParty,
+    pub buyer: Option<DocumentParty>,
+    pub lines: Vec<DocumentLine>,
+    pub subtotal: Money,
+    pub discount: Money,
+    pub total: Money,
+    pub vat: VatBreakdown,
+    pub payments: Vec<DocumentPayment>,
+    /// Free text under the totals (e.g. the credit note reason)
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+}
+
+/// Laid out row of a document
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocumentRow {
+    Text { text: String, align: TextAlign },
+    Title(String),
+    /// Label on the left, value right-aligned
+    Columns { left: String, right: String },
+    ItemHeader,
+    Item { name: String, quantity: i32, unit_price: String, amount: String },
+    Rule,
+}
+
+impl DocumentRow {
+    fn text(text: impl Into<String>) -> Self {
+        DocumentRow::Text { text: text.into(), align: TextAlign::Left }
+    }
+
+    fn centered(text: impl Into<String>) -> Self {
+        DocumentRow::Text { text: text.into(), align: TextAlign::Center }
+    }
+
+    fn columns(left: impl Into<String>, right: impl Into<String>) -> Self {
+        DocumentRow::Columns { left: left.into(), right: right.into() }
+    }
+}
+
+/// Format an amount with thousands separators (1234567.5 -> "1,234,567.50")
+pub fn format_amount(amount: Money) -> String {
+    let plain = amount.to_string();
+    let (sign, digits) = match plain.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", plain.as_str()),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "00"));
+
+    let mut grouped = String::with_capacity(whole.len() + whole.len() / 3);
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    format!("{}{}.{}", sign, grouped, fraction)
+}
+
+/// Date and time as printed in Thailand, Buddhist Era year (17/10/2569 14:05)
+pub fn format_document_date(at: &NaiveDateTime) -> String {
+    let date = at.date();
+    format!(
+        "{:02}/{:02}/{} {:02}:{:02}",
+        date.day(),
+        date.month(),
+        buddhist_year(&date),
+        at.hour(),
+        at.minute()
+    )
+}
+
+/// "สำนักงานใหญ่" for branch 00000, otherwise "สาขาที่ 00001"
+pub fn branch_label(branch_code: &str) -> String {
+    if branch_code == "00000" {
+        "สำนักงานใหญ่".to_string()
+    } else {
+        format!("สาขาที่ {}", branch_code)
+    }
+}
+
+fn tax_id_text(tax_id: &str, branch_code: Option<&str>) -> String {
+    match branch_code {
+        Some(branch) => format!("เลขประจำตัวผู้เสียภาษี {} ({})", tax_id, branch_label(branch)),
+        None => format!("เลขประจำตัวผู้เสียภาษี {}", tax_id),
+    }
+}
+
+/// Lay out a document as rows, independent of the paper it is printed on
+pub fn layout_document(data: &DocumentData) -> Vec<DocumentRow> {
+    let mut rows = Vec::new();
+
+    // Seller
+    rows.push(DocumentRow::Title(data.seller.name.clone()));
+    if let Some(address) = &data.seller.address {
+        rows.push(DocumentRow::centered(address.clone()));
+    }
+    if let Some(tel) = &data.seller.tel {
+        rows.push(DocumentRow::centered(format!("โทร {}", tel)));
+    }
+    if let Some(tax_id) = &data.seller.tax_id {
+        rows.push(DocumentRow::centered(tax_id_text(tax_id, data.seller.branch_code.as_deref())));
+    }
+
+    rows.push(DocumentRow::Title(format!("{} / {}", data.kind.title_th(), data.kind.title_en())));
+    rows.push(DocumentRow::columns("เลขที่ / No.", data.number.clone()));
+    rows.push(DocumentRow::columns("วันที่ / Date", format_document_date(&data.issued_at)));
+    if let Some(reference) = &data.reference {
+        rows.push(DocumentRow::columns("อ้างอิง / Ref.", reference.clone()));
+    }
+
+    // Buyer
+    if let Some(buyer) = &data.buyer {
+        rows.push(DocumentRow::Rule);
+        rows.push(DocumentRow::text(format!("ลูกค้า / Customer: {}", buyer.name)));
+        if let Some(address) = &buyer.address {
+            rows.push(DocumentRow::text(address.clone()));
+        }
+        if let Some(tax_id) = &buyer.tax_id {
+            rows.push(DocumentRow::text(tax_id_text(tax_id, buyer.branch_code.as_deref())));
+        }
+    }
+
+    // Items
+    rows.push(DocumentRow::Rule);
+    rows.push(DocumentRow::ItemHeader);
+    for line in &data.lines {
+        rows.push(DocumentRow::Item {
+            name: line.name.clone(),
+            quantity: line.quantity,
+            unit_price: format_amount(line.unit_price),
+            amount: format_amount(line.amount + line.discount),
+        });
+        if !line.discount.is_zero() {
+            rows.push(DocumentRow::columns("  ส่วนลด / Discount", format_amount(-line.discount)));
+        }
+    }
+    rows.push(DocumentRow::Rule);
+
+    // Totals
+    if !data.discount.is_zero() {
+        rows.push(DocumentRow::columns("รวม / Subtotal", format_amount(data.subtotal)));
+        rows.push(DocumentRow::columns("ส่วนลดท้ายบิล / Bill discount", format_amount(-data.discount)));
+    }
+    if data.kind.shows_vat() {
+        rows.push(DocumentRow::columns("มูลค่าสินค้า / Vatable", format_amount(data.vat.vatable)));
+        rows.push(DocumentRow::columns("ภาษีมูลค่าเพิ่ม 7% / VAT", format_amount(data.vat.vat)));
+        if !data.vat.exempt.is_zero() {
+            rows.push(DocumentRow::columns("ยกเว้นภาษี / VAT exempt", format_amount(data.vat.exempt)));
+        }
+    }
+    rows.push(DocumentRow::columns("ยอดสุทธิ / Total", format_amount(data.total)));
+
+    // Payments
+    if !data.payments.is_empty() {
+        rows.push(DocumentRow::Rule);
+        for payment in &data.payments {
+            if payment.change.is_zero() {
+                rows.push(DocumentRow::columns(payment.label.clone(), format_amount(payment.amount)));
+            } else {
+                rows.push(DocumentRow::columns(payment.label.clone(), format_amount(payment.tendered)));
+                rows.push(DocumentRow::columns("เงินทอน / Change", format_amount(payment.change)));
+            }
+        }
+    }
+
+    if let Some(note) = &data.note {
+        rows.push(DocumentRow::Rule);
+        rows.push(DocumentRow::text(note.clone()));
+    }
+
+    rows.push(DocumentRow::Rule);
+    rows.push(DocumentRow::centered("ขอบคุณที่ใช้บริการ / Thank you"));
+
+    rows
+}
+
+/// Split text into lines no wider than `max_width`, breaking at spaces where possible
+/// Thai has no spaces between words, so long Thai text breaks between characters
+pub fn wrap_text(text: &str, max_width: f32, width_of: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for c in text.chars() {
+        current.push(c);
+        if current.chars().count() > 1 && width_of(&current) > max_width {
+            current.pop();
+            let carry = match current.rfind(' ') {
+                Some(space) if space > 0 => {
+                    let carry = current[space + 1..].to_string();
+                    current.truncate(space);
+                    carry
+                }
+                _ => String::new(),
+            };
+            lines.push(std::mem::take(&mut current));
+            current = carry;
+            current.push(c);
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
+
+/// Millimetres per PDF point
+const MM_PER_PT: f32 = 0.3528;
+
+/// Line height as a multiple of the font size
+const LINE_SPACING: f32 = 1.5;
+
+/// TrueType font used both for measuring and, embedded, for drawing the text
+struct DocumentFont<'a> {
+    face: ttf_parser::Face<'a>,
+}
+
+impl DocumentFont<'_> {
+    /// Width of `text` in mm at `size` points
+    /// Thai vowels and tone marks have no advance, so they don't widen the text
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let units: u32 = text
+            .chars()
+            .filter_map(|c| self.face.glyph_index(c))
+            .filter_map(|glyph| self.face.glyph_hor_advance(glyph))
+            .map(u32::from)
+            .sum();
+        units as f32 / self.face.units_per_em() as f32 * size * MM_PER_PT
+    }
+}
+
+/// A laid out line of text at a position on the page
+#[derive(Debug, Clone)]
+struct PlacedText {
+    text: String,
+    x: f32,
+    size: f32,
+}
+
+/// What one printed line holds: text runs or a horizontal rule
+enum PrintedLine {
+    Texts(Vec<PlacedText>, f32),
+    Rule,
+}
+
+impl PrintedLine {
+    fn height(&self, paper: &PaperLayout) -> f32 {
+        match self {
+            PrintedLine::Texts(_, size) => size * LINE_SPACING * MM_PER_PT,
+            PrintedLine::Rule => paper.font_size * 0.75 * MM_PER_PT,
+        }
+    }
+}
+
+/// Turn rows into printed lines for a paper size, wrapping and aligning the text
+fn place_rows(rows: &[DocumentRow], paper: &PaperLayout, font: &DocumentFont) -> Vec<PrintedLine> {
+    let left = paper.margin;
+    let right = paper.width - paper.margin;
+    let usable = right - left;
+    let size = paper.font_size;
+    let is_wide = paper.height.is_some();
+
+    let at_right = |text: &str, size: f32, edge: f32| PlacedText {
+        x: edge - font.width(text, size),
+        text: text.to_string(),
+        size,
+    };
+
+    let mut lines = Vec::new();
+    let push_wrapped = |lines: &mut Vec<PrintedLine>, text: &str, size: f32, centered: bool| {
+        for part in wrap_text(text, usable, |s| font.width(s, size)) {
+            let x = if centered {
+                left + (usable - font.width(&part, size)) / 2.0
+            } else {
+                left
+            };
+            lines.push(PrintedLine::Texts(vec![PlacedText { text: part, x, size }], size));
+        }
+    };
+
+    // A4 prints items as a table; the roll puts quantity and price under the name
+    let quantity_edge = right - usable * 0.40;
+    let price_edge = right - usable * 0.20;
+
+    for row in rows {
+        match row {
+            DocumentRow::Title(text) => push_wrapped(&mut lines, text, paper.title_size, true),
+            DocumentRow::Text { text, align } => push_wrapped(&mut lines, text, size, *align == TextAlign::Center),
+            DocumentRow::Columns { left: label, right: value } => {
+                let value = at_right(value, size, right);
+                let label_width = value.x - left - 2.0;
+                let mut parts = wrap_text(label, label_width, |s| font.width(s, size)).into_iter();
+                let first = parts.next().unwrap_or_default();
+                lines.push(PrintedLine::Texts(vec![PlacedText { text: first, x: left, size }, value], size));
+                for part in parts {
+                    lines.push(PrintedLine::Texts(vec![PlacedText { text: part, x: left, size }], size));
+                }
+            }
+            DocumentRow::ItemHeader => {
+                let mut texts = vec![PlacedText { text: "รายการ / Item".to_string(), x: left, size }];
+                if is_wide {
+                    texts.push(at_right("จำนวน / Qty", size, quantity_edge));
+                    texts.push(at_right("ราคา / Price", size, price_edge));
+                }
+                texts.push(at_right("จำนวนเงิน / Amount", size, right));
+                lines.push(PrintedLine::Texts(texts, size));
+            }
+            DocumentRow::Item { name, quantity, unit_price, amount } => {
+                if is_wide {
+                    let name_width = quantity_edge - left - usable * 0.12;
+                    let mut parts = wrap_text(name, name_width, |s| font.width(s, size)).into_iter();
+                    let first = parts.next().unwrap_or_default();
+                    lines.push(PrintedLine::Texts(
+                        vec![
+                            PlacedText { text: first, x: left, size },
+                            at_right(&quantity.to_string(), size, quantity_edge),
+                            at_right(unit_price, size, price_edge),
+                            at_right(amount, size, right),
+                        ],
+                        size,
+                    ));
+                    for part in parts {
+                        lines.push(PrintedLine::Texts(vec![PlacedText { text: part, x: left, size }], size));
+                    }
+                } else {
+                    push_wrapped(&mut lines, name, size, false);
+                    lines.push(PrintedLine::Texts(
+                        vec![
+                            PlacedText { text: format!("  {} x {}", quantity, unit_price), x: left, size },
+                            at_right(amount, size, right),
+                        ],
+                        size,
+                    ));
+                }
+            }
+            DocumentRow::Rule => lines.push(PrintedLine::Rule),
+        }
+    }
+
+    lines
+}
+
+fn draw_line(layer: &PdfLayerReference, line: &PrintedLine, paper: &PaperLayout, top: f32, font: &IndirectFontRef) {
+    match line {
+        PrintedLine::Texts(texts, size) => {
+            // Baseline sits about a font size below the top of the line
+            let baseline = top - size * MM_PER_PT * 1.1;
+            for text in texts {
+                layer.use_text(text.text.clone(), text.size, Mm(text.x), Mm(baseline), font);
+            }
+        }
+        PrintedLine::Rule => {
+            let y = top - line.height(paper) / 2.0;
+            layer.set_outline_thickness(0.5);
+            layer.add_line(Line {
+                points: vec![
+                    (Point::new(Mm(paper.margin), Mm(y)), false),
+                    (Point::new(Mm(paper.width - paper.margin), Mm(y)), false),
+                ],
+                is_closed: false,
+            });
+        }
+    }
+}
+
+/// Render a document as PDF with the given TrueType font embedded
+/// The font must cover Thai (e.g. Sarabun or TH Sarabun New)
+pub fn render_document_pdf(data: &DocumentData, paper: PaperSize, font_data: &[u8]) -> Result<Vec<u8>> {
+    let face = ttf_parser::Face::parse(font_data, 0).context("Failed to read the document font")?;
+    let font = DocumentFont { face };
+    let paper = paper.layout();
+
+    let rows = layout_document(data);
+    let lines = place_rows(&rows, &paper, &font);
+
+    let content_height: f32 = lines.iter().map(|line| line.height(&paper)).sum();
+    let page_height = paper.height.unwrap_or(content_height + paper.margin * 2.0);
+
+    let title = format!("{} {}", data.kind.title_en(), data.number);
+    let (doc, page, layer) = PdfDocument::new(title, Mm(paper.width), Mm(page_height), "content");
+    let pdf_font = doc
+        .add_external_font(font_data)
+        .map_err(|e| anyhow!("Failed to embed the document font: {}", e))?;
+
+    let mut layer = doc.get_page(page).get_layer(layer);
+    let mut top = page_height - paper.margin;
+    for line in &lines {
+        let height = line.height(&paper);
+        if top - height < paper.margin {
+            let (page, new_layer) = doc.add_page(Mm(paper.width), Mm(page_height), "content");
+            layer = doc.get_page(page).get_layer(new_layer);
+            top = page_height - paper.margin;
+        }
+        draw_line(&layer, line, &paper, top, &pdf_font);
+        top -= height;
+    }
+
+    doc.save_to_bytes().map_err(|e| anyhow!("Failed to write PDF: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn sample(kind: DocumentKind) -> DocumentData {
+        DocumentData {
+            kind,
+            number: "RC2569-00001".to_string(),
+            reference: Some("ORD2569-00007".to_string()),
+            issued_at: chrono::NaiveDate::from_ymd_opt(2026, 10, 17)
+                .unwrap()
+                .and_hms_opt(14, 5, 0)
+                .unwrap(),
+            seller: DocumentParty {
+                name: "คลินิกตัวอย่าง".to_string(),
+                tax_id: Some("0105536000313".to_string()),
+                branch_code: Some("00000".to_string()),
+                ..Default::default()
+            },
+            buyer: None,
+            lines: vec![DocumentLine {
+                name: "ทรีตเมนต์หน้าใส".to_string(),
+                quantity: 2,
+                unit_price: Money::from(dec!(1000)),
+                discount: Money::from(dec!(100)),
+                amount: Money::from(dec!(1900)),
+            }],
+            subtotal: Money::from(dec!(1900)),
+            discount: Money::ZERO,
+            total: Money::from(dec!(1900)),
+            vat: VatBreakdown {
+                vatable: Money::from(dec!(1775.70)),
+                vat: Money::from(dec!(124.30)),
+                exempt: Money::ZERO,
+            },
+            payments: vec![DocumentPayment {
+                label: "เงินสด / Cash".to_string(),
+                amount: Money::from(dec!(1900)),
+                tendered: Money::from(dec!(2000)),
+                change: Money::from(dec!(100)),
+            }],
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(Money::from(dec!(0))), "0.00");
+        assert_eq!(format_amount(Money::from(dec!(999.5))), "999.50");
+        assert_eq!(format_amount(Money::from(dec!(1000))), "1,000.00");
+        assert_eq!(format_amount(Money::from(dec!(1234567.89))), "1,234,567.89");
+        assert_eq!(format_amount(Money::from(dec!(-12345))), "-12,345.00");
+    }
+
+    #[test]
+    fn test_refunded_line_compares_vat_included_amounts() {
+        // 100 + 7% VAT refunded at the 107 paid: no discount
+        let line = DocumentLine::refunded("Product".to_string(), 1, Money::from(dec!(100)), VatType::Exclusive, Money::from(dec!(107)));
+        assert_eq!(line.unit_price, Money::from(dec!(107)));
+        assert_eq!(line.discount, Money::ZERO);
+
+        // Two units with 10% off the bill: 180 + 12.60 VAT
+        let line = DocumentLine::refunded("Product".to_string(), 2, Money::from(dec!(100)), VatType::Exclusive, Money::from(dec!(192.60)));
+        assert_eq!(line.discount, Money::from(dec!(21.40)));
+
+        let line = DocumentLine::refunded("Product".to_string(), 1, Money::from(dec!(107)), VatType::Inclusive, Money::from(dec!(96.30)));
+        assert_eq!(line.unit_price, Money::from(dec!(107)));
+        assert_eq!(line.discount, Money::from(dec!(10.70)));
+    }
+
+    #[test]
+    fn test_format_document_date() {
+        let at = chrono::NaiveDate::from_ymd_opt(2026, 1, 5).unwrap().and_hms_opt(9, 3, 0).unwrap();
+        assert_eq!(format_document_date(&at), "05/01/2569 09:03");
+    }
+
+    #[test]
+    fn test_layout_receipt() {
+        let rows = layout_document(&sample(DocumentKind::Receipt));
+
+        assert!(rows.contains(&DocumentRow::Title("ใบเสร็จรับเงิน / Receipt".to_string())));
+        assert!(rows.contains(&DocumentRow::columns("เลขที่ / No.", "RC2569-00001")));
+        assert!(rows.contains(&DocumentRow::Item {
+            name: "ทรีตเมนต์หน้าใส".to_string(),
+            quantity: 2,
+            unit_price: "1,000.00".to_string(),
+            amount: "2,000.00".to_string(),
+        }));
+        assert!(rows.contains(&DocumentRow::columns("  ส่วนลด / Discount", "-100.00")));
+        assert!(rows.contains(&DocumentRow::columns("ยอดสุทธิ / Total", "1,900.00")));
+        assert!(rows.contains(&DocumentRow::columns("เงินทอน / Change", "100.00")));
+        // A plain receipt has no VAT breakdown
+        assert!(!rows.contains(&DocumentRow::columns("ภาษีมูลค่าเพิ่ม 7% / VAT", "124.30")));
+    }
+
+    #[test]
+    fn test_layout_tax_invoice() {
+        let mut data = sample(DocumentKind::FullTaxInvoice);
+        data.buyer = Some(DocumentParty {
+            name: "บริษัท ตัวอย่าง จำกัด".to_string(),
+            address: Some("1 ถนนสุขุมวิท กรุงเทพฯ".to_string()),
+            tax_id: Some("1101700203450".to_string()),
+            branch_code: Some("00001".to_string()),
+            ..Default::default()
+        });
+        let rows = layout_document(&data);
+
+        assert!(rows.contains(&DocumentRow::centered("เลขประจำตัวผู้เสียภาษี 0105536000313 (สำนักงานใหญ่)")));
+        assert!(rows.contains(&DocumentRow::text("เลขประจำตัวผู้เสียภาษี 1101700203450 (สาขาที่ 00001)")));
+        assert!(rows.contains(&DocumentRow::columns("มูลค่าสินค้า / Vatable", "1,775.70")));
+        assert!(rows.contains(&DocumentRow::columns("ภาษีมูลค่าเพิ่ม 7% / VAT", "124.30")));
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        let width = |s: &str| s.chars().count() as f32;
+
+        assert_eq!(wrap_text("short", 10.0, width), vec!["short"]);
+        assert_eq!(wrap_text("hello big world", 10.0, width), vec!["hello big", "world"]);
+        assert_eq!(wrap_text("abcdefghij", 4.0, width), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap_text("", 4.0, width), vec![""]);
+    }
+
+    /// Box glyphs for ASCII and Thai, made by tests/fixtures/make_test_font.py
+    const TEST_FONT: &[u8] = include_bytes!("../../tests/fixtures/ThaiTestBoxes.ttf");
+
+    #[test]
+    fn test_render_document_pdf() {
+        for paper in [PaperSize::Thermal80, PaperSize::A4] {
+            let pdf = render_document_pdf(&sample(DocumentKind::AbbreviatedTaxInvoice), paper, TEST_FONT).unwrap();
+            assert!(pdf.starts_with(b"%PDF"));
+        }
+    }
+
+    #[test]
+    fn test_thai_combining_marks_take_no_width() {
+        let font = DocumentFont { face: ttf_parser::Face::parse(TEST_FONT, 0).unwrap() };
+        let size = 10.0;
+
+        // น้ำ: the tone mark has no width, sara am does
+        assert_eq!(font.width("น้ำ", size), font.width("นา", size));
+        assert!(font.width("ก", size) > 0.0);
+    }
+}
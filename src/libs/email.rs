@@ -25,7 +25,7 @@ pub async fn send_email(
     // Build email
     let email = Message::builder()
         .from(from)
-        .to(to)
+        .to(to.clone())
         .subject(subject)
         .header(ContentType::TEXT_PLAIN)
         .body(body.to_string())?;
@@ -59,7 +59,7 @@ pub async fn send_html_email(
 
     let email = Message::builder()
         .from(from)
-        .to(to)
+        .to(to.clone())
         .subject(subject)
         .header(ContentType::TEXT_HTML)
         .body(html_body.to_string())?;
@@ -77,8 +77,25 @@ pub async fn send_html_email(
     Ok(())
 }
 
+/// Mail sender abstraction so handlers can be tested without SMTP
+#[axum::async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send_html(&self, to: &str, subject: &str, html_body: &str) -> Result<()>;
+}
+
+/// SMTP mail sender using EMAIL_NAME / EMAIL_PWD credentials
+pub struct SmtpMailSender;
+
+#[axum::async_trait]
+impl MailSender for SmtpMailSender {
+    async fn send_html(&self, to: &str, subject: &str, html_body: &str) -> Result<()> {
+        send_html_email(to, subject, html_body).await
+    }
+}
+
 /// Send password reset email
 pub async fn send_password_reset_email(
+    mailer: &dyn MailSender,
     to: &str,
     reset_token: &str,
 ) -> Result<()> {
@@ -100,11 +117,86 @@ pub async fn send_password_reset_email(
         reset_link
     );
 
-    send_html_email(to, "Password Reset Request", &body).await
+    mailer.send_html(to, "Password Reset Request", &body).await
+}
+
+/// Send email verification link after registration
+pub async fn send_verification_email(
+    mailer: &dyn MailSender,
+    to: &str,
+    verification_token: &str,
+) -> Result<()> {
+    let base_url = env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8002".to_string());
+    let verify_link = format!("{}/auth/verify-email?token={}", base_url, verification_token);
+
+    let body = format!(
+        r#"
+        <html>
+        <body>
+            <h2>Verify Your Email</h2>
+            <p>Thank you for registering. Click the link below to verify your email address:</p>
+            <p><a href="{}">Verify Email</a></p>
+            <p>This link will expire in 24 hours.</p>
+            <p>If you did not create an account, please ignore this email.</p>
+        </body>
+        </html>
+        "#,
+        verify_link
+    );
+
+    mailer.send_html(to, "Verify Your Email", &body).await
+}
+
+/// Tell the owner of an email that someone tried to register with it
+/// Carries no link that verifies or signs in, so registration does not reveal or take over accounts
+pub async fn send_account_exists_email(mailer: &dyn MailSender, to: &str) -> Result<()> {
+    let base_url = env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8002".to_string());
+
+    let body = format!(
+        r#"
+        <html>
+        <body>
+            <h2>You Already Have an Account</h2>
+            <p>Someone tried to register on APSTH Clinic with this email address, which already has an account.</p>
+            <p>If this was you, log in instead, or reset your password if you have forgotten it:</p>
+            <p><a href="{}">Open APSTH Clinic</a></p>
+            <p>If it wasn't you, you can ignore this email.</p>
+        </body>
+        </html>
+        "#,
+        base_url
+    );
+
+    mailer.send_html(to, "Registration attempt - APSTH Clinic", &body).await
+}
+
+/// Send shop invitation email
+pub async fn send_shop_invite_email(
+    mailer: &dyn MailSender,
+    to: &str,
+    shop_name: &str,
+) -> Result<()> {
+    let base_url = env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8002".to_string());
+
+    let body = format!(
+        r#"
+        <html>
+        <body>
+            <h2>You're Invited to {}</h2>
+            <p>You have been invited to join <b>{}</b> on APSTH Clinic.</p>
+            <p>Log in, or register with this email address, to accept the invitation:</p>
+            <p><a href="{}">Open APSTH Clinic</a></p>
+        </body>
+        </html>
+        "#,
+        shop_name, shop_name, base_url
+    );
+
+    mailer.send_html(to, &format!("Invitation to join {}", shop_name), &body).await
 }
 
 /// Send welcome email
-pub async fn send_welcome_email(to: &str, name: &str) -> Result<()> {
+pub async fn send_welcome_email(mailer: &dyn MailSender, to: &str, name: &str) -> Result<()> {
     let body = format!(
         r#"
         <html>
@@ -122,7 +214,63 @@ pub async fn send_welcome_email(to: &str, name: &str) -> Result<()> {
         name
     );
 
-    send_html_email(to, "Welcome to APSTH Clinic", &body).await
+    mailer.send_html(to, "Welcome to APSTH Clinic", &body).await
+}
+
+/// Send account lockout notification after repeated failed logins
+pub async fn send_account_locked_email(
+    mailer: &dyn MailSender,
+    to: &str,
+    locked_minutes: i64,
+    ip_address: &str,
+) -> Result<()> {
+    let body = format!(
+        r#"
+        <html>
+        <body>
+            <h2>Account Temporarily Locked</h2>
+            <p>Your account was locked for {} minutes after several failed login attempts.</p>
+            <p>The last attempt came from IP address {}.</p>
+            <p>If this wasn't you, we recommend resetting your password once the lock expires.</p>
+            <br>
+            <p>Best regards,</p>
+            <p>APSTH Team</p>
+        </body>
+        </html>
+        "#,
+        locked_minutes, ip_address
+    );
+
+    mailer.send_html(to, "Account Locked - APSTH Clinic", &body).await
+}
+
+/// Sent email captured by FakeMailSender
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct SentMail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Mail sender that records emails instead of sending them
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeMailSender {
+    pub sent: std::sync::Mutex<Vec<SentMail>>,
+}
+
+#[cfg(test)]
+#[axum::async_trait]
+impl MailSender for FakeMailSender {
+    async fn send_html(&self, to: &str, subject: &str, html_body: &str) -> Result<()> {
+        self.sent.lock().unwrap().push(SentMail {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body: html_body.to_string(),
+        });
+        Ok(())
+    }
 }
 
 #[cfg(test)]
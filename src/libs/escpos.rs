@@ -0,0 +1,536 @@
+use anyhow::{bail, Context, Result};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use super::document::{wrap_text, DocumentRow, TextAlign};
+
+// ESC/POS byte streams for thermal receipt printers
+// Prints the same rows as the PDF layout, in TIS-620 Thai, with an optional logo and QR code
+
+/// Characters per line on 80mm paper with the default font
+pub const ESCPOS_COLUMNS: usize = 48;
+
+/// Epson "Thai character code 18" code page; printers that number it differently can set
+/// ESCPOS_CODE_PAGE
+pub const DEFAULT_THAI_CODE_PAGE: u8 = 26;
+
+/// Printable width of an 80mm head in dots, the widest a logo is printed
+const LOGO_MAX_WIDTH: u32 = 384;
+
+/// Largest logo image accepted for decoding, per side in pixels
+const LOGO_MAX_SOURCE_SIDE: u32 = 4096;
+
+/// Raw printing port of network printers (JetDirect)
+pub const DEFAULT_PRINTER_PORT: u16 = 9100;
+
+const PRINTER_TIMEOUT: Duration = Duration::from_secs(10);
+
+const ESC: u8 = 0x1b;
+const GS: u8 = 0x1d;
+
+/// What to print besides the document rows
+#[derive(Debug, Clone)]
+pub struct EscPosOptions {
+    pub code_page: u8,
+    pub columns: usize,
+    /// Logo printed above the document
+    pub logo: Option<Arc<LogoBitmap>>,
+    /// Printed as a QR code under the document (e.g. the e-receipt link)
+    pub qr: Option<String>,
+}
+
+impl Default for EscPosOptions {
+    fn default() -> Self {
+        Self {
+            code_page: DEFAULT_THAI_CODE_PAGE,
+            columns: ESCPOS_COLUMNS,
+            logo: None,
+            qr: None,
+        }
+    }
+}
+
+/// Thai vowels and tone marks printed above or below the previous character
+fn is_thai_combining(c: char) -> bool {
+    matches!(c, '\u{0E31}' | '\u{0E34}'..='\u{0E3A}' | '\u{0E47}'..='\u{0E4E}')
+}
+
+/// Number of print columns a text takes; Thai combining marks take none
+pub fn display_width(text: &str) -> usize {
+    text.chars().filter(|&c| !is_thai_combining(c)).count()
+}
+
+/// Encode text as TIS-620 (ASCII plus Thai at 0xA1-0xFB); anything else prints as '?'
+pub fn encode_tis620(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\0'..='\x7f' => c as u8,
+            '\u{0E01}'..='\u{0E5B}' => (c as u32 - 0x0E00 + 0xA0) as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Receipt printer command stream
+struct EscPos {
+    bytes: Vec<u8>,
+    columns: usize,
+}
+
+impl EscPos {
+    fn command(&mut self, command: &[u8]) {
+        self.bytes.extend_from_slice(command);
+    }
+
+    fn align(&mut self, align: TextAlign) {
+        let n = match align {
+            TextAlign::Left => 0,
+            TextAlign::Center => 1,
+        };
+        self.command(&[ESC, b'a', n]);
+    }
+
+    fn line(&mut self, text: &str) {
+        self.bytes.extend(encode_tis620(text));
+        self.bytes.push(b'\n');
+    }
+
+    fn wrapped(&mut self, text: &str) {
+        for part in wrap_text(text, self.columns as f32, |s| display_width(s) as f32) {
+            self.line(&part);
+        }
+    }
+
+    /// Left text and right-aligned value on one line; a long left text wraps above the value
+    fn columns(&mut self, left: &str, right: &str) {
+        let room = self.columns.saturating_sub(display_width(right) + 1).max(1);
+        let mut parts = wrap_text(left, room as f32, |s| display_width(s) as f32);
+        let last = parts.pop().unwrap_or_default();
+        for part in parts {
+            self.line(&part);
+        }
+        let padding = self.columns.saturating_sub(display_width(&last) + display_width(right));
+        self.line(&format!("{}{}{}", last, " ".repeat(padding.max(1)), right));
+    }
+
+    fn row(&mut self, row: &DocumentRow) {
+        match row {
+            DocumentRow::Title(text) => {
+                // Emphasized, double height
+                self.align(TextAlign::Center);
+                self.command(&[ESC, b'!', 0x18]);
+                self.wrapped(text);
+                self.command(&[ESC, b'!', 0x00]);
+                self.align(TextAlign::Left);
+            }
+            DocumentRow::Text { text, align } => {
+                self.align(*align);
+                self.wrapped(text);
+                self.align(TextAlign::Left);
+            }
+            DocumentRow::Columns { left, right } => self.columns(left, right),
+            DocumentRow::ItemHeader => self.columns("รายการ / Item", "จำนวนเงิน / Amount"),
+            DocumentRow::Item { name, quantity, unit_price, amount } => {
+                self.wrapped(name);
+                self.columns(&format!("  {} x {}", quantity, unit_price), amount);
+            }
+            DocumentRow::Rule => self.line(&"-".repeat(self.columns)),
+        }
+    }
+
+    /// GS v 0: print a monochrome raster image, 8 dots per byte, most significant bit first
+    fn raster(&mut self, width: u32, height: u32, dark: impl Fn(u32, u32) -> bool) {
+        let width_bytes = width.div_ceil(8);
+        self.command(&[
+            GS, b'v', b'0', 0,
+            (width_bytes & 0xff) as u8, (width_bytes >> 8) as u8,
+            (height & 0xff) as u8, (height >> 8) as u8,
+        ]);
+        for y in 0..height {
+            for byte in 0..width_bytes {
+                let mut bits = 0u8;
+                for bit in 0..8 {
+                    let x = byte * 8 + bit;
+                    if x < width && dark(x, y) {
+                        bits |= 0x80 >> bit;
+                    }
+                }
+                self.bytes.push(bits);
+            }
+        }
+    }
+
+    /// GS ( k: the printer draws the QR code itself (model 2, error correction M)
+    fn qr(&mut self, data: &str) {
+        let stored = data.len() + 3;
+        self.command(&[GS, b'(', b'k', 4, 0, 49, 65, 50, 0]);
+        self.command(&[GS, b'(', b'k', 3, 0, 49, 67, 6]);
+        self.command(&[GS, b'(', b'k', 3, 0, 49, 69, 49]);
+        self.command(&[GS, b'(', b'k', (stored & 0xff) as u8, (stored >> 8) as u8, 49, 80, 48]);
+        self.command(data.as_bytes());
+        self.command(&[GS, b'(', b'k', 3, 0, 49, 81, 48]);
+    }
+}
+
+/// Logo scaled down to the paper and reduced to black dots, ready to print
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogoBitmap {
+    width: u32,
+    height: u32,
+    dots: Vec<bool>,
+}
+
+impl LogoBitmap {
+    /// Decode a PNG or JPEG logo; transparent pixels are left blank
+    /// Images over LOGO_MAX_SOURCE_SIDE pixels a side are refused rather than decoded
+    pub fn decode(image: &[u8]) -> Result<Self> {
+        let mut limits = image::io::Limits::default();
+        limits.max_image_width = Some(LOGO_MAX_SOURCE_SIDE);
+        limits.max_image_height = Some(LOGO_MAX_SOURCE_SIDE);
+
+        let mut reader = image::io::Reader::new(std::io::Cursor::new(image))
+            .with_guessed_format()
+            .context("Failed to read logo image")?;
+        reader.limits(limits);
+        let mut image = reader.decode().context("Failed to read logo image")?;
+
+        if image.width() > LOGO_MAX_WIDTH {
+            image = image.resize(LOGO_MAX_WIDTH, u32::MAX, image::imageops::FilterType::Triangle);
+        }
+        let image = image.to_rgba8();
+
+        let dots = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b, a] = pixel.0;
+                let luma = (u32::from(r) * 299 + u32::from(g) * 587 + u32::from(b) * 114) / 1000;
+                a >= 128 && luma < 128
+            })
+            .collect();
+
+        Ok(LogoBitmap {
+            width: image.width(),
+            height: image.height(),
+            dots,
+        })
+    }
+}
+
+fn print_logo(printer: &mut EscPos, logo: &LogoBitmap) {
+    printer.align(TextAlign::Center);
+    printer.raster(logo.width, logo.height, |x, y| logo.dots[(y * logo.width + x) as usize]);
+    printer.align(TextAlign::Left);
+}
+
+/// Render document rows as an ESC/POS job, ending with a paper cut
+pub fn render_escpos(rows: &[DocumentRow], options: &EscPosOptions) -> Result<Vec<u8>> {
+    let mut printer = EscPos {
+        bytes: Vec::new(),
+        columns: options.columns,
+    };
+
+    // Initialize, then select the Thai code page
+    printer.command(&[ESC, b'@']);
+    printer.command(&[ESC, b't', options.code_page]);
+
+    if let Some(logo) = &options.logo {
+        print_logo(&mut printer, logo);
+    }
+
+    for row in rows {
+        printer.row(row);
+    }
+
+    if let Some(qr) = &options.qr {
+        printer.align(TextAlign::Center);
+        printer.qr(qr);
+        printer.align(TextAlign::Left);
+    }
+
+    // Feed past the cutter, then partial cut
+    printer.command(&[ESC, b'd', 4]);
+    printer.command(&[GS, b'V', 66, 0]);
+
+    Ok(printer.bytes)
+}
+
+/// Ports a printer may listen on: PRINTER_PORTS (comma separated) or the raw port 9100
+pub fn printer_ports() -> Vec<u16> {
+    std::env::var("PRINTER_PORTS")
+        .ok()
+        .map(|ports| ports.split(',').filter_map(|port| port.trim().parse().ok()).collect::<Vec<u16>>())
+        .filter(|ports| !ports.is_empty())
+        .unwrap_or_else(|| vec![DEFAULT_PRINTER_PORT])
+}
+
+/// Whether an IP is on a private LAN (10/8, 172.16/12, 192.168/16 or IPv6 unique local)
+/// Loopback, link-local and public addresses are not, so the server cannot be pointed at itself,
+/// cloud metadata or the internet
+pub fn is_lan_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => ip.is_private(),
+            None => ip.is_unique_local(),
+        },
+    }
+}
+
+/// Whether an IP is reachable on the public internet
+/// Anything private, loopback, link-local, shared (CGNAT), reserved or multicast is not, so
+/// server-side fetches of user-supplied URLs cannot reach internal services
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    fn is_public_v4(ip: Ipv4Addr) -> bool {
+        let [a, b, ..] = ip.octets();
+        !(ip.is_private()
+            || ip.is_loopback()
+            || ip.is_link_local()
+            || ip.is_unspecified()
+            || ip.is_broadcast()
+            || ip.is_multicast()
+            || ip.is_documentation()
+            || a == 0
+            || a >= 240
+            || (a == 100 && (64..128).contains(&b))
+            || (a == 192 && b == 0 && ip.octets()[2] == 0)
+            || (a == 198 && (b == 18 || b == 19)))
+    }
+
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_v4(ip);
+            }
+            let segments = ip.segments();
+            // NAT64 (64:ff9b::/96) carries an IPv4 address in the last 32 bits
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., a, b, c, d] = ip.octets();
+                return is_public_v4(Ipv4Addr::new(a, b, c, d));
+            }
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                || segments[..2] == [0x2001, 0xdb8])
+        }
+    }
+}
+
+/// Parse a printer address, "ip" or "ip:port" ("[ipv6]:port"), port 9100 by default
+/// Only LAN IPs and the allowed ports are accepted; host names are not, as they can resolve anywhere
+pub fn parse_printer_address(address: &str, allowed_ports: &[u16]) -> Result<SocketAddr> {
+    let address = address.trim();
+    let socket = match address.parse::<SocketAddr>() {
+        Ok(socket) => socket,
+        Err(_) => match address.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, DEFAULT_PRINTER_PORT),
+            Err(_) => bail!("Printer address must be an IP address, optionally with :port"),
+        },
+    };
+
+    if !is_lan_ip(socket.ip()) {
+        bail!("Printer must be on a private network (10.x, 172.16-31.x or 192.168.x)");
+    }
+    if !allowed_ports.contains(&socket.port()) {
+        bail!("Printer port must be one of {:?}", allowed_ports);
+    }
+
+    Ok(socket)
+}
+
+/// Send a job to a network printer's raw port; the address is checked with parse_printer_address
+pub async fn send_to_printer(address: &str, job: &[u8]) -> Result<()> {
+    let socket = parse_printer_address(address, &printer_ports())?;
+    send_job(socket, job).await
+}
+
+async fn send_job(address: SocketAddr, job: &[u8]) -> Result<()> {
+    let send = async {
+        let mut stream = TcpStream::connect(address).await?;
+        stream.write_all(job).await?;
+        stream.shutdown().await
+    };
+
+    tokio::time::timeout(PRINTER_TIMEOUT, send)
+        .await
+        .with_context(|| format!("Printer {} timed out", address))?
+        .with_context(|| format!("Failed to print to {}", address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn test_encode_tis620() {
+        assert_eq!(encode_tis620("A1 "), b"A1 ".to_vec());
+        // ก, ข, ่ (tone mark), ๙
+        assert_eq!(encode_tis620("กข่๙"), vec![0xa1, 0xa2, 0xe8, 0xf9]);
+        assert_eq!(encode_tis620("€"), b"?".to_vec());
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("Total"), 5);
+        // น้ำ: the tone mark sits on น, sara am takes a column
+        assert_eq!(display_width("น้ำ"), 2);
+        assert_eq!(display_width("ที่"), 1);
+    }
+
+    #[test]
+    fn test_render_columns() {
+        let rows = vec![
+            DocumentRow::Columns { left: "ยอดสุทธิ / Total".to_string(), right: "1,900.00".to_string() },
+            DocumentRow::Rule,
+        ];
+        let options = EscPosOptions { columns: 32, ..Default::default() };
+        let job = render_escpos(&rows, &options).unwrap();
+
+        assert!(job.starts_with(&[ESC, b'@', ESC, b't', DEFAULT_THAI_CODE_PAGE]));
+        assert!(job.ends_with(&[GS, b'V', 66, 0]));
+
+        let mut line = encode_tis620("ยอดสุทธิ / Total");
+        line.extend(std::iter::repeat_n(b' ', 32 - 14 - 8));
+        line.extend(b"1,900.00\n");
+        assert!(contains(&job, &line));
+        assert!(contains(&job, &[b'-'; 32]));
+    }
+
+    #[test]
+    fn test_render_qr() {
+        let options = EscPosOptions { qr: Some("https://example.com/r/1".to_string()), ..Default::default() };
+        let job = render_escpos(&[], &options).unwrap();
+
+        // Store 23 bytes of data (+3 for the function header), then print
+        assert!(contains(&job, &[GS, b'(', b'k', 26, 0, 49, 80, 48]));
+        assert!(contains(&job, b"https://example.com/r/1"));
+        assert!(contains(&job, &[GS, b'(', b'k', 3, 0, 49, 81, 48]));
+    }
+
+    #[test]
+    fn test_render_logo() {
+        // 10x2 logo, left pixel black on the first row only
+        let mut image = image::RgbaImage::from_pixel(10, 2, image::Rgba([255, 255, 255, 255]));
+        image.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let logo = LogoBitmap::decode(&png).unwrap();
+        let options = EscPosOptions { logo: Some(Arc::new(logo)), ..Default::default() };
+        let job = render_escpos(&[], &options).unwrap();
+
+        assert!(contains(&job, &[GS, b'v', b'0', 0, 2, 0, 2, 0, 0x80, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_logo_too_large_is_not_decoded() {
+        let image = image::RgbaImage::new(LOGO_MAX_SOURCE_SIDE + 1, 1);
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+
+        assert!(LogoBitmap::decode(&png).is_err());
+        assert!(LogoBitmap::decode(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["93.184.216.34", "2606:2800:220:1::1", "::ffff:93.184.216.34"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.20.0.1",
+            "192.168.0.10",
+            "169.254.169.254",
+            "100.100.100.200",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:169.254.169.254",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_to_printer() {
+        // Fake printer: accept one job and read it to the end
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let printer = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            socket.read_to_end(&mut received).await.unwrap();
+            received
+        });
+
+        let job = render_escpos(&[DocumentRow::Rule], &EscPosOptions::default()).unwrap();
+        send_job(address, &job).await.unwrap();
+
+        assert_eq!(printer.await.unwrap(), job);
+    }
+
+    #[tokio::test]
+    async fn test_send_to_printer_unreachable() {
+        // Bind then drop a listener to get a port nothing listens on
+        let address = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+
+        assert!(send_job(address, b"job").await.is_err());
+    }
+
+    #[test]
+    fn test_parse_printer_address() {
+        let ports = [DEFAULT_PRINTER_PORT];
+        assert_eq!(
+            parse_printer_address("192.168.1.50", &ports).unwrap(),
+            "192.168.1.50:9100".parse::<SocketAddr>().unwrap()
+        );
+        assert!(parse_printer_address("10.0.0.7:9100", &ports).is_ok());
+        assert!(parse_printer_address("172.16.3.4", &ports).is_ok());
+        assert!(parse_printer_address("[fd12::7]:9100", &ports).is_ok());
+
+        // Other ports only when configured
+        assert!(parse_printer_address("192.168.1.50:22", &ports).is_err());
+        assert!(parse_printer_address("192.168.1.50:9101", &[9100, 9101]).is_ok());
+    }
+
+    #[test]
+    fn test_printer_address_must_be_on_the_lan() {
+        let ports = [DEFAULT_PRINTER_PORT];
+        for address in [
+            "127.0.0.1",
+            "0.0.0.0",
+            "169.254.169.254",
+            "8.8.8.8",
+            "100.64.0.1",
+            "[::1]:9100",
+            "[fe80::1]:9100",
+            "[::ffff:127.0.0.1]:9100",
+            "printer.local",
+            "metadata.google.internal:9100",
+        ] {
+            assert!(parse_printer_address(address, &ports).is_err(), "{}", address);
+        }
+        assert!(parse_printer_address("[::ffff:192.168.1.50]:9100", &ports).is_ok());
+    }
+}
@@ -4,8 +4,28 @@
 pub mod sms;
 pub mod calendar;
 pub mod email;
+pub mod otp;
+pub mod qr;
+pub mod money;
+pub mod discount;
+pub mod vat;
+pub mod document;
+pub mod escpos;
+pub mod promptpay;
+pub mod phone;
+pub mod name_match;
 
 // Re-export commonly used functions
 pub use sms::*;
 pub use calendar::*;
 pub use email::*;
+pub use otp::*;
+pub use qr::*;
+pub use money::*;
+pub use discount::*;
+pub use vat::*;
+pub use document::*;
+pub use escpos::*;
+pub use promptpay::*;
+pub use phone::*;
+pub use name_match::*;
@@ -0,0 +1,240 @@
+// Money type backed by a fixed-point decimal
+//
+// Amounts are stored as MySQL DECIMAL(12,2) and serialized as strings ("1250.50")
+// so no value ever passes through a float. Rounding rule: every computed amount
+// (percentages, tax, splits) is rounded to the satang, half away from zero.
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// Decimal places of a satang
+const SATANG_DP: u32 = 2;
+
+/// Amount in Thai baht
+/// Deserializing rejects fractions of a satang ("99.995"), which DECIMAL(12,2) would silently round
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct Money(Decimal);
+
+impl Money {
+    pub const ZERO: Money = Money(Decimal::ZERO);
+
+    #[cfg(test)]
+    pub fn new(amount: Decimal) -> Self {
+        Money(amount)
+    }
+
+    /// Whole baht amount
+    #[cfg(test)]
+    pub fn from_baht(baht: i64) -> Self {
+        Money(Decimal::from(baht))
+    }
+
+    /// Amount from satang (1 baht = 100 satang)
+    #[cfg(test)]
+    pub fn from_satang(satang: i64) -> Self {
+        Money(Decimal::new(satang, SATANG_DP))
+    }
+
+    /// Convert a float from a legacy column (e.g. shop_roles.sr_discount), rounded to the satang
+    pub fn from_f64_lossy(value: f64) -> Self {
+        Money(Decimal::try_from(value).unwrap_or_default()).round_satang()
+    }
+
+    pub fn amount(self) -> Decimal {
+        self.0
+    }
+
+    /// Round to the satang, half away from zero (0.005 -> 0.01)
+    pub fn round_satang(self) -> Self {
+        Money(self.0.round_dp_with_strategy(SATANG_DP, RoundingStrategy::MidpointAwayFromZero))
+    }
+
+    /// `percent` percent of this amount, rounded to the satang
+    pub fn percent(self, percent: Decimal) -> Self {
+        Money(self.0 * percent / Decimal::ONE_HUNDRED).round_satang()
+    }
+
+    /// This amount times part/whole, rounded to the satang (e.g. a line's share of the bill)
+    /// Zero when `whole` is zero
+    pub fn prorate(self, part: Decimal, whole: Decimal) -> Self {
+        if whole.is_zero() {
+            return Money::ZERO;
+        }
+        Money(self.0 * part / whole).round_satang()
+    }
+
+    /// Split this amount in proportion to `weights`; the share with the largest weight takes
+    /// the rounding remainder so the shares always add up to the amount and a zero weight
+    /// always gets zero
+    pub fn allocate(self, weights: &[Money]) -> Vec<Money> {
+        let whole: Money = weights.iter().sum();
+        let mut shares: Vec<Money> = weights
+            .iter()
+            .map(|weight| self.prorate(weight.0, whole.0))
+            .collect();
+
+        if let Some((largest, _)) = weights.iter().enumerate().max_by_key(|(_, weight)| **weight) {
+            let rounded: Money = shares.iter().sum();
+            shares[largest] += self - rounded;
+        }
+        shares
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0.is_sign_negative() && !self.0.is_zero()
+    }
+}
+
+impl fmt::Display for Money {
+    /// Always two decimal places, e.g. "1250.50"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}", self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let amount = <Decimal as Deserialize>::deserialize(deserializer)?;
+        // Trailing zeros ("99.500") are still whole satang
+        if amount.normalize().scale() > SATANG_DP {
+            return Err(serde::de::Error::custom(format!(
+                "amount {} has more than {} decimal places",
+                amount, SATANG_DP
+            )));
+        }
+        Ok(Money(amount))
+    }
+}
+
+impl From<Decimal> for Money {
+    fn from(amount: Decimal) -> Self {
+        Money(amount)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+/// Unit price times quantity
+impl Mul<i32> for Money {
+    type Output = Money;
+
+    fn mul(self, quantity: i32) -> Money {
+        Money(self.0 * Decimal::from(quantity))
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_round_satang_half_away_from_zero() {
+        assert_eq!(Money::new(dec!(1.005)).round_satang(), Money::new(dec!(1.01)));
+        assert_eq!(Money::new(dec!(1.004)).round_satang(), Money::new(dec!(1.00)));
+        assert_eq!(Money::new(dec!(-1.005)).round_satang(), Money::new(dec!(-1.01)));
+    }
+
+    #[test]
+    fn test_money_arithmetic_is_exact() {
+        // 0.1 + 0.2 is not 0.3 in floating point
+        let total: Money = [Money::from_satang(10), Money::from_satang(20)].iter().sum();
+        assert_eq!(total, Money::from_satang(30));
+
+        assert_eq!(Money::from_satang(1999) * 3, Money::from_satang(5997));
+        assert_eq!(Money::from_baht(100).percent(dec!(7)), Money::from_baht(7));
+        assert_eq!(Money::from_satang(3333).percent(dec!(10)), Money::from_satang(333));
+        assert_eq!(Money::from_baht(100).prorate(dec!(1), dec!(3)), Money::from_satang(3333));
+        assert_eq!(Money::from_baht(100).prorate(dec!(1), Decimal::ZERO), Money::ZERO);
+
+        let shares = Money::from_baht(100).allocate(&[Money::from_baht(1), Money::from_baht(1), Money::from_baht(1)]);
+        assert_eq!(shares, vec![Money::from_satang(3333), Money::from_satang(3333), Money::from_satang(3334)]);
+    }
+
+    #[test]
+    fn test_allocate_remainder_goes_to_largest_share() {
+        // A zero weight (e.g. a free item) never takes the remainder
+        let shares = Money::from_baht(100).allocate(&[Money::from_baht(1), Money::from_baht(1), Money::from_baht(1), Money::ZERO]);
+        assert_eq!(shares, vec![Money::from_satang(3333), Money::from_satang(3333), Money::from_satang(3334), Money::ZERO]);
+
+        // 57.14 + 3 x 14.29 rounds to 100.01; the largest share gives the satang back
+        let weights = [Money::from_baht(4), Money::from_baht(1), Money::from_baht(1), Money::from_baht(1)];
+        let shares = Money::from_baht(100).allocate(&weights);
+        assert_eq!(shares[0], Money::from_satang(5713));
+        assert_eq!(shares.iter().sum::<Money>(), Money::from_baht(100));
+    }
+
+    #[test]
+    fn test_money_serializes_as_string() {
+        let json = serde_json::to_string(&Money::from_satang(125050)).unwrap();
+        assert_eq!(json, "\"1250.50\"");
+
+        let parsed: Money = serde_json::from_str("99.5").unwrap();
+        assert_eq!(parsed, Money::from_satang(9950));
+        assert_eq!(parsed.to_string(), "99.50");
+    }
+
+    #[test]
+    fn test_money_rejects_fractions_of_a_satang() {
+        assert!(serde_json::from_str::<Money>("\"99.995\"").is_err());
+        assert!(serde_json::from_str::<Money>("0.001").is_err());
+
+        assert_eq!(serde_json::from_str::<Money>("\"99.500\"").unwrap(), Money::from_satang(9950));
+        assert_eq!(serde_json::from_str::<Money>("\"100\"").unwrap(), Money::from_baht(100));
+    }
+}
@@ -0,0 +1,124 @@
+// Fuzzy person-name matching for finding duplicate customers
+// Thai names are often re-typed with a different tone mark, a missing thanthakhat, a title
+// in front or different spacing, so names are normalized before they are compared
+
+/// Similarity from which two names are treated as the same person on their own (Jaro-Winkler)
+/// One typo in a typical Thai full name scores about 0.95, different first names below 0.9
+pub const NAME_MATCH_THRESHOLD: f64 = 0.92;
+
+/// Titles that can only be a title, removed even when the name follows without a space
+const TITLES: [&str; 6] = ["นางสาว", "น.ส.", "ด.ช.", "ด.ญ.", "ดร.", "ศ.ดร."];
+
+/// Titles that are also the start of some names, removed only when followed by a space or "."
+const SHORT_TITLES: [&str; 9] = ["นาย", "นาง", "คุณ", "mr", "mrs", "ms", "miss", "dr", "khun"];
+
+/// Normalize a name for comparison: lower case, no title, no spaces or punctuation, no Thai
+/// tone marks, thanthakhat or maitaikhu
+/// e.g. "นางสาว สุดารัตน์  ศรีสุข" -> "สุดารัตนศรีสุข"
+pub fn normalize_person_name(name: &str) -> String {
+    let mut name = name.trim().to_lowercase();
+
+    for title in TITLES {
+        if let Some(rest) = name.strip_prefix(title) {
+            name = rest.trim_start().to_string();
+            break;
+        }
+    }
+    for title in SHORT_TITLES {
+        if let Some(rest) = name.strip_prefix(title) {
+            if rest.starts_with([' ', '.']) {
+                name = rest.trim_start_matches([' ', '.']).to_string();
+                break;
+            }
+        }
+    }
+
+    name.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_ascii_punctuation())
+        // Maitaikhu, the four tone marks and thanthakhat (U+0E47-U+0E4C)
+        .filter(|c| !('\u{0E47}'..='\u{0E4C}').contains(c))
+        .collect()
+}
+
+/// Similarity of two normalized names between 0.0 and 1.0 (Jaro-Winkler)
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    strsim::jaro_winkler(a, b)
+}
+
+/// Width in characters of the name length bands used for blocking
+const NAME_LENGTH_BAND: usize = 4;
+
+/// Buckets for comparing names: the first letter that is not a leading Thai vowel, with the
+/// name's length band and the band above it
+/// Only names sharing a bucket are compared. Neighbouring bands share one, so names up to
+/// NAME_LENGTH_BAND characters apart in length are always compared, while names that merely
+/// start with a common letter such as ส are split over several buckets
+pub fn name_block_keys(normalized: &str) -> Vec<(char, usize)> {
+    // Sara e, ae, o, ai maimuan, ai maimalai are written before the consonant they follow
+    let Some(first) = normalized
+        .chars()
+        .find(|c| !('\u{0E40}'..='\u{0E44}').contains(c))
+    else {
+        return Vec::new();
+    };
+
+    let band = normalized.chars().count() / NAME_LENGTH_BAND;
+    vec![(first, band), (first, band + 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_person_name() {
+        assert_eq!(normalize_person_name("นางสาว สุดารัตน์  ศรีสุข"), "สุดารัตนศรีสุข");
+        assert_eq!(normalize_person_name("น.ส.สุดารัตน์ ศรีสุข"), "สุดารัตนศรีสุข");
+        assert_eq!(normalize_person_name("นาย สมชาย ใจดี"), "สมชายใจดี");
+        assert_eq!(normalize_person_name("Mr. Somchai  Jai-dee"), "somchaijaidee");
+        // Names that start like a title keep it
+        assert_eq!(normalize_person_name("นางนวล ทองดี"), "นางนวลทองดี");
+        assert_eq!(normalize_person_name("Mrinal Das"), "mrinaldas");
+        // Tone marks are dropped so a mistyped mark still matches
+        assert_eq!(normalize_person_name("ประเสริฐ วงศ์ทอง"), normalize_person_name("ประเสริฐ วงศทอง"));
+        assert_eq!(normalize_person_name("ก้อง"), normalize_person_name("ก๊อง"));
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        let same = |a: &str, b: &str| {
+            name_similarity(&normalize_person_name(a), &normalize_person_name(b)) >= NAME_MATCH_THRESHOLD
+        };
+
+        assert!(same("สมชาย ใจดี", "นาย สมชาย ใจดี"));
+        assert!(same("สมชาย ใจดี", "สมชัย ใจดี"));
+        assert!(same("สุดารัตน์ ศรีสุข", "สุดารัต ศรีสุข"));
+        assert!(same("Somchai Jaidee", "somchai jaide"));
+
+        assert!(!same("สมชาย ใจดี", "สมศักดิ์ ใจดี"));
+        assert!(!same("วิชัย มั่นคง", "วิไล มั่นคง"));
+        assert!(!same("John Smith", "Jane Smith"));
+        assert_eq!(name_similarity("", "สมชาย"), 0.0);
+    }
+
+    #[test]
+    fn test_name_block_keys() {
+        assert_eq!(name_block_keys("สมชาย"), vec![('ส', 1), ('ส', 2)]);
+        assert_eq!(name_block_keys("เกศินี"), vec![('ก', 1), ('ก', 2)]);
+        assert_eq!(name_block_keys("ไพโรจน"), vec![('พ', 1), ('พ', 2)]);
+        assert!(name_block_keys("").is_empty());
+
+        // A dropped letter stays in a shared bucket, a much longer name does not
+        let shares = |a: &str, b: &str| {
+            let keys = name_block_keys(&normalize_person_name(b));
+            name_block_keys(&normalize_person_name(a)).iter().any(|key| keys.contains(key))
+        };
+        assert!(shares("สุดารัตน์ ศรีสุข", "สุดารัต ศรีสุข"));
+        assert!(shares("สมชาย ใจดี", "สมชาย ใจดีมาก"));
+        assert!(!shares("สมชาย ใจดี", "สมศักดิ์ศรี เจริญสุขสวัสดิ์"));
+        assert!(!shares("สมชาย ใจดี", "มานะ ใจดี"));
+    }
+}
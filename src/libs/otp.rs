@@ -0,0 +1,79 @@
+use anyhow::Result;
+use std::env;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+// TOTP (2FA) utilities
+// Equivalent to Go's OTP handling with pquerna/otp
+
+/// Default issuer shown in authenticator apps
+const DEFAULT_OTP_ISSUER: &str = "APSTH Clinic";
+
+/// Generate a new otpauth URL with a random secret for the given account
+pub fn generate_otp_url(account_name: &str) -> Result<String> {
+    let issuer = env::var("OTP_ISSUER").unwrap_or_else(|_| DEFAULT_OTP_ISSUER.to_string());
+
+    let secret = Secret::generate_secret()
+        .to_bytes()
+        .map_err(|e| anyhow::anyhow!("Failed to generate OTP secret: {:?}", e))?;
+
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(issuer),
+        account_name.to_string(),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to create TOTP: {}", e))?;
+
+    Ok(totp.get_url())
+}
+
+/// Verify an OTP code against a stored otpauth URL
+/// Secrets created by the Go system may be shorter than 128 bits,
+/// so the URL is parsed without the RFC length check.
+pub fn verify_otp(otp_url: &str, otp_code: &str) -> Result<bool> {
+    let totp = TOTP::from_url_unchecked(otp_url)
+        .map_err(|e| anyhow::anyhow!("Invalid OTP URL: {}", e))?;
+
+    let code = otp_code.trim();
+    if code.len() != totp.digits || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(false);
+    }
+
+    Ok(totp.check_current(code)?)
+}
+
+/// Get base32 secret from otpauth URL (for manual entry in authenticator apps)
+pub fn otp_secret_from_url(otp_url: &str) -> Result<String> {
+    let totp = TOTP::from_url_unchecked(otp_url)
+        .map_err(|e| anyhow::anyhow!("Invalid OTP URL: {}", e))?;
+
+    Ok(totp.get_secret_base32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_verify_otp() {
+        let url = generate_otp_url("test@example.com").unwrap();
+        assert!(url.starts_with("otpauth://totp/"));
+
+        let totp = TOTP::from_url_unchecked(&url).unwrap();
+        let code = totp.generate_current().unwrap();
+
+        assert!(verify_otp(&url, &code).unwrap());
+        assert!(!verify_otp(&url, "abcdef").unwrap());
+        assert!(!verify_otp(&url, "12345").unwrap());
+    }
+
+    #[test]
+    fn test_otp_secret_from_url() {
+        let url = generate_otp_url("test@example.com").unwrap();
+        let secret = otp_secret_from_url(&url).unwrap();
+        assert!(url.contains(&secret));
+    }
+}
@@ -0,0 +1,109 @@
+// Thai phone numbers
+// Customers type numbers as "081-234-5678", "081 234 5678", "+66 81 234 5678" or "(02) 123 4567";
+// they are stored in one form so search and duplicate checks compare like with like
+
+/// Normalize a Thai phone number to its national form: digits only, starting with 0
+/// Mobile numbers have 10 digits (06, 08, 09), landlines 9 (e.g. 02-123-4567)
+/// Returns None if the input is not a Thai phone number
+pub fn normalize_thai_phone(phone: &str) -> Option<String> {
+    let trimmed = phone.trim();
+    if !trimmed
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '-' | ' ' | '.' | '(' | ')' | '+'))
+    {
+        return None;
+    }
+
+    // "+" is only allowed as the start of the country code
+    if trimmed.chars().skip(1).any(|c| c == '+') {
+        return None;
+    }
+
+    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
+
+    // International form: +66 81 234 5678 or 66812345678, sometimes written +66 (0)81 ...
+    let national = if trimmed.starts_with('+') || (digits.starts_with("66") && !digits.starts_with('0')) {
+        let rest = digits.strip_prefix("66")?;
+        format!("0{}", rest.strip_prefix('0').unwrap_or(rest))
+    } else {
+        digits
+    };
+
+    let valid = national.len() >= 9
+        && national.starts_with('0')
+        && !national.starts_with("00")
+        && match &national[1..2] {
+            "6" | "8" | "9" => national.len() == 10,
+            _ => national.len() == 9,
+        };
+
+    valid.then_some(national)
+}
+
+/// Digits a partial phone number should match in a search, e.g. "081-23" -> "08123"
+/// Returns None if the text is not a (partial) phone number
+pub fn phone_search_digits(text: &str) -> Option<String> {
+    if let Some(phone) = normalize_thai_phone(text) {
+        return Some(phone);
+    }
+
+    let trimmed = text.trim();
+    let (international, rest) = match trimmed.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+    let phone_like = rest
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '-' | ' ' | '.' | '(' | ')'));
+    let digits: String = rest.chars().filter(|c| c.is_ascii_digit()).collect();
+
+    // "+6681" is the start of 081...
+    let digits = if international {
+        let national = digits.strip_prefix("66")?;
+        format!("0{}", national.strip_prefix('0').unwrap_or(national))
+    } else {
+        digits
+    };
+
+    (phone_like && digits.len() >= 3).then_some(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_thai_phone() {
+        assert_eq!(normalize_thai_phone("081-234-5678"), Some("0812345678".to_string()));
+        assert_eq!(normalize_thai_phone(" 081 234 5678 "), Some("0812345678".to_string()));
+        assert_eq!(normalize_thai_phone("+66 81 234 5678"), Some("0812345678".to_string()));
+        assert_eq!(normalize_thai_phone("+66 (0)81 234 5678"), Some("0812345678".to_string()));
+        assert_eq!(normalize_thai_phone("66812345678"), Some("0812345678".to_string()));
+        assert_eq!(normalize_thai_phone("(02) 123-4567"), Some("021234567".to_string()));
+        assert_eq!(normalize_thai_phone("+662 123 4567"), Some("021234567".to_string()));
+    }
+
+    #[test]
+    fn test_normalize_thai_phone_rejects() {
+        // Too short/long for the area, no leading 0, foreign country code, letters, stray "+"
+        assert_eq!(normalize_thai_phone("081234567"), None);
+        assert_eq!(normalize_thai_phone("02123456789"), None);
+        assert_eq!(normalize_thai_phone("812345678"), None);
+        assert_eq!(normalize_thai_phone("+1 415 555 0100"), None);
+        assert_eq!(normalize_thai_phone("081-234-567x"), None);
+        assert_eq!(normalize_thai_phone("081+2345678"), None);
+        assert_eq!(normalize_thai_phone("0012345678"), None);
+        assert_eq!(normalize_thai_phone(""), None);
+    }
+
+    #[test]
+    fn test_phone_search_digits() {
+        assert_eq!(phone_search_digits("081-23"), Some("08123".to_string()));
+        assert_eq!(phone_search_digits("+66 81 234 5678"), Some("0812345678".to_string()));
+        assert_eq!(phone_search_digits("+6681234"), Some("081234".to_string()));
+        assert_eq!(phone_search_digits("5678"), Some("5678".to_string()));
+        assert_eq!(phone_search_digits("08"), None);
+        assert_eq!(phone_search_digits("+1 415"), None);
+        assert_eq!(phone_search_digits("Somchai"), None);
+    }
+}
@@ -0,0 +1,164 @@
+use super::money::Money;
+use super::vat::is_valid_tax_id;
+
+// PromptPay QR payloads (Thai QR Payment, EMVCo merchant-presented mode)
+// Each field is ID + 2-digit length + value; the payload ends with a CRC16 of everything before it
+
+/// PromptPay application ID (credit transfer, tag 29)
+const PROMPTPAY_AID: &str = "A000000677010111";
+
+/// ISO 4217 numeric code for Thai baht
+const CURRENCY_THB: &str = "764";
+
+/// Shop's PromptPay account: a mobile number, a 13-digit tax/national ID or a 15-digit e-wallet ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptPayId {
+    Phone(String),
+    TaxId(String),
+    EWallet(String),
+}
+
+impl PromptPayId {
+    /// Read an ID as typed ("081-234-5678", "0105536000313", ...); the kind follows from the
+    /// number of digits
+    pub fn parse(id: &str) -> Option<Self> {
+        let digits: String = id.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        match digits.len() {
+            10 if digits.starts_with('0') => Some(PromptPayId::Phone(digits)),
+            13 if is_valid_tax_id(&digits) => Some(PromptPayId::TaxId(digits)),
+            15 => Some(PromptPayId::EWallet(digits)),
+            _ => None,
+        }
+    }
+
+    /// The ID as digits only
+    pub fn as_str(&self) -> &str {
+        match self {
+            PromptPayId::Phone(id) | PromptPayId::TaxId(id) | PromptPayId::EWallet(id) => id,
+        }
+    }
+
+    /// Sub-tag and value inside the merchant account field
+    fn account_field(&self) -> String {
+        match self {
+            // Mobile numbers go international: 0812345678 -> 0066812345678
+            PromptPayId::Phone(phone) => tlv("01", &format!("0066{}", &phone[1..])),
+            PromptPayId::TaxId(tax_id) => tlv("02", tax_id),
+            PromptPayId::EWallet(wallet) => tlv("03", wallet),
+        }
+    }
+}
+
+fn tlv(id: &str, value: &str) -> String {
+    format!("{}{:02}{}", id, value.len(), value)
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF) as used by EMVCo QR codes
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xffff;
+    for &byte in data {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Build the PromptPay payload; with an amount the QR is single-use ("dynamic") and the
+/// banking app fills the amount in
+pub fn promptpay_payload(id: &PromptPayId, amount: Option<Money>) -> String {
+    let mut payload = String::new();
+    payload.push_str(&tlv("00", "01"));
+    payload.push_str(&tlv("01", if amount.is_some() { "12" } else { "11" }));
+    payload.push_str(&tlv("29", &format!("{}{}", tlv("00", PROMPTPAY_AID), id.account_field())));
+    payload.push_str(&tlv("58", "TH"));
+    payload.push_str(&tlv("53", CURRENCY_THB));
+    if let Some(amount) = amount {
+        payload.push_str(&tlv("54", &amount.round_satang().to_string()));
+    }
+
+    // The checksum covers its own ID and length
+    payload.push_str("6304");
+    let crc = crc16_ccitt(payload.as_bytes());
+    payload.push_str(&format!("{:04X}", crc));
+
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_crc16_ccitt() {
+        // Standard check value of CRC-16/CCITT-FALSE
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29b1);
+    }
+
+    #[test]
+    fn test_parse_promptpay_id() {
+        assert_eq!(PromptPayId::parse("081-234-5678"), Some(PromptPayId::Phone("0812345678".to_string())));
+        assert_eq!(PromptPayId::parse("0105536000313"), Some(PromptPayId::TaxId("0105536000313".to_string())));
+        assert_eq!(PromptPayId::parse("012345678901234"), Some(PromptPayId::EWallet("012345678901234".to_string())));
+
+        // Bad check digit, wrong length, not a mobile number, letters
+        assert_eq!(PromptPayId::parse("0105536000314"), None);
+        assert_eq!(PromptPayId::parse("081234567"), None);
+        assert_eq!(PromptPayId::parse("8123456789"), None);
+        assert_eq!(PromptPayId::parse("08l2345678"), None);
+    }
+
+    #[test]
+    fn test_promptpay_payload_phone() {
+        let id = PromptPayId::parse("0801234567").unwrap();
+        assert_eq!(
+            promptpay_payload(&id, None),
+            "00020101021129370016A000000677010111011300668012345675802TH530376463046197"
+        );
+
+        let id = PromptPayId::parse("000-000-0000").unwrap();
+        assert_eq!(
+            promptpay_payload(&id, None),
+            "00020101021129370016A000000677010111011300660000000005802TH530376463048956"
+        );
+    }
+
+    #[test]
+    fn test_promptpay_payload_tax_id() {
+        let id = PromptPayId::TaxId("1111111111111".to_string());
+        assert_eq!(
+            promptpay_payload(&id, None),
+            "00020101021129370016A000000677010111021311111111111115802TH530376463047B5A"
+        );
+    }
+
+    #[test]
+    fn test_promptpay_payload_amount() {
+        let id = PromptPayId::parse("000-000-0000").unwrap();
+        assert_eq!(
+            promptpay_payload(&id, Some(Money::from(dec!(4.22)))),
+            "00020101021229370016A000000677010111011300660000000005802TH530376454044.226304E469"
+        );
+    }
+
+    #[test]
+    fn test_promptpay_payload_ewallet() {
+        let id = PromptPayId::parse("012345678901234").unwrap();
+        let payload = promptpay_payload(&id, Some(Money::from(dec!(1500))));
+
+        assert!(payload.contains("29390016A0000006770101110315012345678901234"));
+        assert!(payload.contains("54071500.00"));
+        let (body, crc) = payload.split_at(payload.len() - 4);
+        assert_eq!(crc, format!("{:04X}", crc16_ccitt(body.as_bytes())));
+    }
+}
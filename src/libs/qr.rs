@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use image::{GrayImage, ImageOutputFormat, Luma};
+use qrcode::{Color, QrCode};
+
+// QR code rendering utilities
+// Equivalent to Go's QR code generation with skip2/go-qrcode
+
+/// Pixels per QR module
+const QR_MODULE_SIZE: u32 = 8;
+
+/// Quiet zone width in modules (required by the QR spec)
+const QR_QUIET_ZONE: u32 = 4;
+
+/// Render data as a QR code PNG image
+pub fn render_qr_png(data: &str) -> Result<Vec<u8>> {
+    let code = QrCode::new(data.as_bytes()).context("Failed to encode QR code")?;
+
+    let modules = code.width() as u32;
+    let colors = code.to_colors();
+    let size = (modules + QR_QUIET_ZONE * 2) * QR_MODULE_SIZE;
+
+    let img = GrayImage::from_fn(size, size, |x, y| {
+        let mx = (x / QR_MODULE_SIZE) as i64 - QR_QUIET_ZONE as i64;
+        let my = (y / QR_MODULE_SIZE) as i64 - QR_QUIET_ZONE as i64;
+
+        let dark = mx >= 0
+            && my >= 0
+            && (mx as u32) < modules
+            && (my as u32) < modules
+            && colors[(my as u32 * modules + mx as u32) as usize] == Color::Dark;
+
+        if dark {
+            Luma([0u8])
+        } else {
+            Luma([255u8])
+        }
+    });
+
+    let mut output = Vec::new();
+    let mut cursor = std::io::Cursor::new(&mut output);
+
+    img.write_to(&mut cursor, ImageOutputFormat::Png)
+        .context("Failed to encode QR code PNG")?;
+
+    Ok(output)
+}
+
+/// Render data as a base64-encoded QR code PNG image
+pub fn render_qr_png_base64(data: &str) -> Result<String> {
+    use base64::Engine;
+
+    let png = render_qr_png(data)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_qr_png() {
+        let png = render_qr_png("otpauth://totp/test?secret=JBSWY3DPEHPK3PXP").unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!(img.width(), img.height());
+    }
+}
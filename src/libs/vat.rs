@@ -0,0 +1,187 @@
+// Thai VAT calculation and tax ID validation
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use super::money::Money;
+
+/// Thai VAT rate in percent
+pub const VAT_RATE_PERCENT: Decimal = Decimal::from_parts(7, 0, 0, false, 0);
+
+/// How VAT applies to a product's price (products.product_vat_type)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[repr(i8)]
+pub enum VatType {
+    /// Price includes VAT (usual for retail)
+    #[default]
+    Inclusive = 1,
+    /// VAT is added on top of the price
+    Exclusive = 2,
+    /// Not subject to VAT (e.g. medical services)
+    Exempt = 3,
+}
+
+impl VatType {
+    /// Price as the customer pays it; VAT is added to exclusive prices
+    pub fn gross(self, price: Money) -> Money {
+        match self {
+            VatType::Exclusive => price + price.percent(VAT_RATE_PERCENT),
+            VatType::Inclusive | VatType::Exempt => price,
+        }
+    }
+}
+
+/// VAT totals of an order
+/// vatable is the amount before VAT, so vatable + vat + exempt is what the customer pays
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VatBreakdown {
+    pub vatable: Money,
+    pub vat: Money,
+    pub exempt: Money,
+}
+
+impl VatBreakdown {
+    pub fn payable(&self) -> Money {
+        self.vatable + self.vat + self.exempt
+    }
+}
+
+/// Line priced after the bill discount, with its share of the order VAT
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PricedLine {
+    /// What the customer pays for the line, VAT included
+    pub net: Money,
+    pub vat: Money,
+}
+
+/// Apply the bill discount and VAT to lines given as (VAT type, total after item discount)
+/// VAT is calculated once per VAT type on the order totals, as printed on the tax invoice,
+/// then shared back to the lines.
+pub fn price_lines(lines: &[(VatType, Money)], bill_discount: Money) -> (Vec<PricedLine>, VatBreakdown) {
+    let totals: Vec<Money> = lines.iter().map(|&(_, total)| total).collect();
+    let discounts = bill_discount.allocate(&totals);
+    let amounts: Vec<Money> = totals.iter().zip(&discounts).map(|(&t, &d)| t - d).collect();
+
+    let group_total = |vat_type: VatType| -> Money {
+        lines
+            .iter()
+            .zip(&amounts)
+            .filter(|((t, _), _)| *t == vat_type)
+            .map(|(_, &amount)| amount)
+            .sum()
+    };
+
+    let inclusive = group_total(VatType::Inclusive);
+    let exclusive = group_total(VatType::Exclusive);
+    let exempt = group_total(VatType::Exempt);
+
+    let inclusive_vat = inclusive.prorate(VAT_RATE_PERCENT, Decimal::ONE_HUNDRED + VAT_RATE_PERCENT);
+    let exclusive_vat = exclusive.percent(VAT_RATE_PERCENT);
+
+    // Share each group's VAT over its lines
+    let mut vats = vec![Money::ZERO; lines.len()];
+    for (vat_type, group_vat) in [(VatType::Inclusive, inclusive_vat), (VatType::Exclusive, exclusive_vat)] {
+        let indexes: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].0 == vat_type).collect();
+        let weights: Vec<Money> = indexes.iter().map(|&i| amounts[i]).collect();
+        for (&i, share) in indexes.iter().zip(group_vat.allocate(&weights)) {
+            vats[i] = share;
+        }
+    }
+
+    let priced = lines
+        .iter()
+        .zip(amounts.iter().zip(&vats))
+        .map(|(&(vat_type, _), (&amount, &vat))| PricedLine {
+            net: if vat_type == VatType::Exclusive { amount + vat } else { amount },
+            vat,
+        })
+        .collect();
+
+    let breakdown = VatBreakdown {
+        vatable: inclusive - inclusive_vat + exclusive,
+        vat: inclusive_vat + exclusive_vat,
+        exempt,
+    };
+
+    (priced, breakdown)
+}
+
+/// Validate a 13-digit Thai tax ID (also used for national ID numbers)
+/// The last digit is a mod-11 checksum of the first twelve weighted 13 down to 2
+pub fn is_valid_tax_id(tax_id: &str) -> bool {
+    if tax_id.len() != 13 || !tax_id.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+
+    let digits: Vec<u32> = tax_id.bytes().map(|b| (b - b'0') as u32).collect();
+    let sum: u32 = digits[..12]
+        .iter()
+        .zip((2..=13).rev())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+
+    (11 - sum % 11) % 10 == digits[12]
+}
+
+/// Branch code printed on tax invoices: 5 digits, "00000" for the head office
+pub fn is_valid_branch_code(branch_code: &str) -> bool {
+    branch_code.len() == 5 && branch_code.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vat_rate() {
+        assert_eq!(VAT_RATE_PERCENT, Decimal::from(7));
+    }
+
+    #[test]
+    fn test_inclusive_vat() {
+        let (lines, vat) = price_lines(&[(VatType::Inclusive, Money::from_baht(107))], Money::ZERO);
+        assert_eq!(vat.vatable, Money::from_baht(100));
+        assert_eq!(vat.vat, Money::from_baht(7));
+        assert_eq!(lines[0].net, Money::from_baht(107));
+        assert_eq!(vat.payable(), Money::from_baht(107));
+    }
+
+    #[test]
+    fn test_exclusive_vat_is_added() {
+        let (lines, vat) = price_lines(&[(VatType::Exclusive, Money::from_baht(200))], Money::ZERO);
+        assert_eq!(vat.vatable, Money::from_baht(200));
+        assert_eq!(vat.vat, Money::from_baht(14));
+        assert_eq!(lines[0].net, Money::from_baht(214));
+    }
+
+    #[test]
+    fn test_mixed_vat_with_bill_discount() {
+        // 10% bill discount shared over the lines: 900 inclusive, 90 exclusive, 180 exempt
+        let (lines, vat) = price_lines(
+            &[
+                (VatType::Inclusive, Money::from_baht(1000)),
+                (VatType::Exclusive, Money::from_baht(100)),
+                (VatType::Exempt, Money::from_baht(200)),
+            ],
+            Money::from_baht(130),
+        );
+
+        assert_eq!(vat.exempt, Money::from_baht(180));
+        assert_eq!(vat.vat, Money::from_satang(5888 + 630));
+        assert_eq!(vat.vatable, Money::from_satang(84112 + 9000));
+        assert_eq!(lines.iter().map(|l| l.net).sum::<Money>(), vat.payable());
+        assert_eq!(lines[2].vat, Money::ZERO);
+    }
+
+    #[test]
+    fn test_tax_id_checksum() {
+        assert!(is_valid_tax_id("0105536000313"));
+        assert!(is_valid_tax_id("1101700203450"));
+        assert!(!is_valid_tax_id("1101700203451"));
+        assert!(!is_valid_tax_id("110170020345"));
+        assert!(!is_valid_tax_id("11017002034a1"));
+
+        assert!(is_valid_branch_code("00000"));
+        assert!(!is_valid_branch_code("0001"));
+    }
+}
@@ -7,8 +7,10 @@ mod structs;
 mod libs;
 
 use anyhow::Result;
+use axum::http::{HeaderName, HeaderValue};
 use dotenvy::dotenv;
 use std::env;
+use std::net::SocketAddr;
 use tower_http::cors::{CorsLayer, Any};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -35,16 +37,22 @@ async fn main() -> Result<()> {
     tracing::info!("Database connections established");
 
     // Setup CORS
+    let mut origins: Vec<HeaderValue> = vec![
+        "https://www.app-apsx.com".parse()?,
+        "https://app-apsx.com".parse()?,
+        "https://clinic.app-apsx.com".parse()?,
+        "https://admin.app-apsx.com".parse()?,
+        "http://localhost:3000".parse()?,
+    ];
+    // The local print agent fetches receipts itself
+    if let Ok(print_url) = env::var("PRINT_URL") {
+        origins.push(print_url.trim_end_matches('/').parse()?);
+    }
     let cors = CorsLayer::new()
-        .allow_origin([
-            "https://www.app-apsx.com".parse()?,
-            "https://app-apsx.com".parse()?,
-            "https://clinic.app-apsx.com".parse()?,
-            "https://admin.app-apsx.com".parse()?,
-            "http://localhost:3000".parse()?,
-        ])
+        .allow_origin(origins)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(Any)
+        .expose_headers([HeaderName::from_static("x-document-url")]);
 
     // Create router
     let app = routes::create_router(app_state)
@@ -64,7 +72,7 @@ async fn main() -> Result<()> {
     tracing::info!("Environment: {}", env::var("ENV").unwrap_or_else(|_| "DEV".to_string()));
 
     // Start server
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Maximum entries before expired entries are swept on insert
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// Small in-process cache with a fixed time-to-live per entry
+/// Used by middlewares to avoid a database hit on every request
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (V, Instant)>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get a value if present and not expired
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        match entries.get(key) {
+            Some((value, inserted_at)) if inserted_at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Insert or replace a value
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        if entries.len() >= SWEEP_THRESHOLD {
+            let ttl = self.ttl;
+            entries.retain(|_, (_, inserted_at)| inserted_at.elapsed() < ttl);
+        }
+
+        entries.insert(key, (value, Instant::now()));
+    }
+
+    /// Remove a value so the next lookup goes to the database
+    pub fn remove(&self, key: &K) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ttl_cache_get_insert_remove() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        assert_eq!(cache.get(&1), None);
+
+        cache.insert(1, "a".to_string());
+        assert_eq!(cache.get(&1), Some("a".to_string()));
+
+        cache.remove(&1);
+        assert_eq!(cache.get(&1), None);
+    }
+
+    #[test]
+    fn test_ttl_cache_expiry() {
+        let cache = TtlCache::new(Duration::from_millis(10));
+        cache.insert(1, 42);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get(&1), None);
+    }
+}
@@ -1,6 +1,9 @@
 use chrono::{NaiveDate, NaiveDateTime};
 use anyhow::Result;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
 use rand::{thread_rng, Rng};
 use rand::distributions::Alphanumeric;
 
@@ -27,6 +30,65 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
     verify(password, hash).map_err(|e| anyhow::anyhow!(e))
 }
 
+/// Result of checking a password against a stored hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordCheck {
+    /// Password does not match
+    Invalid,
+    /// Password matches a bcrypt hash
+    Valid,
+    /// Password matches a legacy hash and should be re-hashed with bcrypt
+    ValidLegacy,
+}
+
+/// Check password against a stored hash, accepting legacy formats
+/// Legacy accounts migrated from the Go system may still hold unsalted
+/// MD5 (32 hex chars) or SHA-256 (64 hex chars) hashes.
+pub fn check_password(password: &str, stored_hash: &str) -> Result<PasswordCheck> {
+    if stored_hash.starts_with("$2") {
+        return Ok(if verify_password(password, stored_hash)? {
+            PasswordCheck::Valid
+        } else {
+            PasswordCheck::Invalid
+        });
+    }
+
+    let is_hex = stored_hash.chars().all(|c| c.is_ascii_hexdigit());
+    let computed = match stored_hash.len() {
+        32 if is_hex => hex::encode(Md5::digest(password.as_bytes())),
+        64 if is_hex => hex::encode(Sha256::digest(password.as_bytes())),
+        _ => anyhow::bail!("Unsupported password hash format"),
+    };
+
+    Ok(if constant_time_eq(&computed, &stored_hash.to_lowercase()) {
+        PasswordCheck::ValidLegacy
+    } else {
+        PasswordCheck::Invalid
+    })
+}
+
+/// Hash a throwaway password so unknown accounts take as long as real ones
+pub fn dummy_password_check(password: &str) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    let dummy_hash = DUMMY_HASH.get_or_init(|| hash("dummy_password", DEFAULT_COST).unwrap_or_default());
+    let _ = verify(password, dummy_hash);
+}
+
+/// Hash a one-time token (e.g. password reset) for storage
+/// Tokens are random and high-entropy, so an unsalted SHA-256 is sufficient
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Compare two strings without short-circuiting on the first mismatch
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
 /// Parse date string to NaiveDate
 /// Equivalent to Go's date parsing functions
 pub fn parse_date(date_str: &str) -> Result<NaiveDate> {
@@ -136,6 +198,7 @@ pub fn calculate_percentage(value: f64, total: f64) -> f64 {
 }
 
 /// Round float to n decimal places
+/// Not for money: amounts use libs::Money, which is exact and rounds half away from zero
 pub fn round_to_decimal(value: f64, decimal_places: u32) -> f64 {
     let multiplier = 10_f64.powi(decimal_places as i32);
     (value * multiplier).round() / multiplier
@@ -181,6 +244,33 @@ mod tests {
         assert!(!verify_password("wrong_password", &hashed).unwrap());
     }
 
+    #[test]
+    fn test_check_password_formats() {
+        let bcrypt_hash = hash_password("secret123").unwrap();
+        assert_eq!(check_password("secret123", &bcrypt_hash).unwrap(), PasswordCheck::Valid);
+        assert_eq!(check_password("wrong", &bcrypt_hash).unwrap(), PasswordCheck::Invalid);
+
+        // md5("secret123")
+        let md5_hash = "5d7845ac6ee7cfffafc5fe5f35cf666d";
+        assert_eq!(check_password("secret123", md5_hash).unwrap(), PasswordCheck::ValidLegacy);
+        assert_eq!(check_password("wrong", md5_hash).unwrap(), PasswordCheck::Invalid);
+
+        // sha256("secret123")
+        let sha_hash = "fcf730b6d95236ecd3c9fc2d92d7b6b2bb061514961aec041d6c7a7192f592e4";
+        assert_eq!(check_password("secret123", sha_hash).unwrap(), PasswordCheck::ValidLegacy);
+
+        assert!(check_password("secret123", "plaintext").is_err());
+    }
+
+    #[test]
+    fn test_hash_token() {
+        let token = generate_random_string(48);
+        let hashed = hash_token(&token);
+        assert_eq!(hashed.len(), 64);
+        assert_ne!(hashed, token);
+        assert_eq!(hashed, hash_token(&token));
+    }
+
     #[test]
     fn test_distinct_vec() {
         let vec = vec![1, 2, 2, 3, 3, 3, 4];
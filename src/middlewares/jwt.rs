@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Request, FromRequestParts},
+    extract::{Request, FromRequestParts, State},
     middleware::Next,
     response::{Response, IntoResponse},
     http::{StatusCode, HeaderMap, header},
@@ -9,6 +9,25 @@ use serde::{Deserialize, Serialize};
 use std::env;
 use chrono::{Utc, Duration};
 use anyhow::Result;
+use std::sync::LazyLock;
+use crate::configs::AppState;
+use crate::models::{UserAuthStatus, UserModel, RevokedTokenModel};
+use uuid::Uuid;
+use super::cache::TtlCache;
+
+/// How long a user's password_version/active status is trusted before re-checking
+const USER_STATUS_CACHE_TTL_SECS: u64 = 5;
+
+/// Cached user auth status keyed by user_id
+static USER_STATUS_CACHE: LazyLock<TtlCache<i32, UserAuthStatus>> =
+    LazyLock::new(|| TtlCache::new(std::time::Duration::from_secs(USER_STATUS_CACHE_TTL_SECS)));
+
+/// How long a token's revocation lookup is trusted before re-checking
+const REVOKED_TOKEN_CACHE_TTL_SECS: u64 = 5;
+
+/// Cached revocation lookups keyed by jti
+static REVOKED_TOKEN_CACHE: LazyLock<TtlCache<String, bool>> =
+    LazyLock::new(|| TtlCache::new(std::time::Duration::from_secs(REVOKED_TOKEN_CACHE_TTL_SECS)));
 
 /// Access Token Claims structure
 /// Equivalent to Go's AccessTokenClaims in middlewares/jwt.go
@@ -23,6 +42,8 @@ pub struct AccessTokenClaims {
     pub sr_discount_type_id: i32,
     pub sr_discount: f32,
     pub password_version: i32,
+    pub session_epoch: i32,
+    pub jti: String,
     pub exp: i64,
     pub iat: i64,
 }
@@ -40,15 +61,17 @@ pub struct RefreshTokenClaims {
     pub sr_discount_type_id: i32,
     pub sr_discount: f32,
     pub password_version: i32,
+    pub session_epoch: i32,
     pub user_type: i32,
+    pub jti: String,
+    pub family_id: String,
     pub exp: i64,
     pub iat: i64,
 }
 
-/// Authenticated user information extracted from JWT
-/// Used in request handlers as an extractor
+/// Who a token is issued to: the user, their shop and role, and the versions checked on every request
 #[derive(Debug, Clone)]
-pub struct AuthUser {
+pub struct TokenSubject {
     pub user_id: i32,
     pub shop_id: i32,
     pub shop_mother_id: i32,
@@ -58,6 +81,21 @@ pub struct AuthUser {
     pub sr_discount_type_id: i32,
     pub sr_discount: f32,
     pub password_version: i32,
+    pub session_epoch: i32,
+}
+
+/// Authenticated user information extracted from JWT
+/// Used in request handlers as an extractor
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: i32,
+    pub shop_id: i32,
+    pub role_id: i32,
+    pub shop_role_id: i32,
+    pub sr_discount_type_id: i32,
+    pub sr_discount: f32,
+    pub jti: String,
+    pub exp: i64,
 }
 
 #[axum::async_trait]
@@ -84,17 +122,7 @@ where
 
 /// Create Access Token (short-lived, default 90 minutes)
 /// Equivalent to Go's CreateAccessToken function
-pub fn create_access_token(
-    user_id: i32,
-    shop_id: i32,
-    shop_mother_id: i32,
-    role_id: i32,
-    shop_role_id: i32,
-    user_email: String,
-    sr_discount_type_id: i32,
-    sr_discount: f32,
-    password_version: i32,
-) -> Result<String> {
+pub fn create_access_token(subject: &TokenSubject) -> Result<String> {
     let expiration_minutes = env::var("JWT_AC_EXPIRE")
         .unwrap_or_else(|_| "90".to_string())
         .parse::<i64>()
@@ -104,15 +132,17 @@ pub fn create_access_token(
     let exp = (now + Duration::minutes(expiration_minutes)).timestamp();
 
     let claims = AccessTokenClaims {
-        user_id,
-        shop_id,
-        shop_mother_id,
-        role_id,
-        shop_role_id,
-        user_email,
-        sr_discount_type_id,
-        sr_discount,
-        password_version,
+        user_id: subject.user_id,
+        shop_id: subject.shop_id,
+        shop_mother_id: subject.shop_mother_id,
+        role_id: subject.role_id,
+        shop_role_id: subject.shop_role_id,
+        user_email: subject.user_email.clone(),
+        sr_discount_type_id: subject.sr_discount_type_id,
+        sr_discount: subject.sr_discount,
+        password_version: subject.password_version,
+        session_epoch: subject.session_epoch,
+        jti: Uuid::new_v4().to_string(),
         exp,
         iat: now.timestamp(),
     };
@@ -130,36 +160,28 @@ pub fn create_access_token(
 /// Create Refresh Token (long-lived, default 720 hours)
 /// Equivalent to Go's CreateRefreshToken function
 pub fn create_refresh_token(
-    user_id: i32,
-    shop_id: i32,
-    shop_mother_id: i32,
-    role_id: i32,
-    shop_role_id: i32,
-    user_email: String,
-    sr_discount_type_id: i32,
-    sr_discount: f32,
-    password_version: i32,
+    subject: &TokenSubject,
     user_type: i32,
+    jti: String,
+    family_id: String,
 ) -> Result<String> {
-    let expiration_hours = env::var("JWT_RF_EXPIRE")
-        .unwrap_or_else(|_| "720".to_string())
-        .parse::<i64>()
-        .unwrap_or(720);
-
     let now = Utc::now();
-    let exp = (now + Duration::hours(expiration_hours)).timestamp();
+    let exp = (now + Duration::hours(refresh_token_expire_hours())).timestamp();
 
     let claims = RefreshTokenClaims {
-        user_id,
-        shop_id,
-        shop_mother_id,
-        role_id,
-        shop_role_id,
-        user_email,
-        sr_discount_type_id,
-        sr_discount,
-        password_version,
+        user_id: subject.user_id,
+        shop_id: subject.shop_id,
+        shop_mother_id: subject.shop_mother_id,
+        role_id: subject.role_id,
+        shop_role_id: subject.shop_role_id,
+        user_email: subject.user_email.clone(),
+        sr_discount_type_id: subject.sr_discount_type_id,
+        sr_discount: subject.sr_discount,
+        password_version: subject.password_version,
+        session_epoch: subject.session_epoch,
         user_type,
+        jti,
+        family_id,
         exp,
         iat: now.timestamp(),
     };
@@ -174,9 +196,31 @@ pub fn create_refresh_token(
     Ok(token)
 }
 
+/// Refresh token lifetime in hours (JWT_RF_EXPIRE, default 720)
+pub fn refresh_token_expire_hours() -> i64 {
+    env::var("JWT_RF_EXPIRE")
+        .unwrap_or_else(|_| "720".to_string())
+        .parse::<i64>()
+        .unwrap_or(720)
+}
+
+/// Decode and validate a refresh token
+pub fn decode_refresh_token(token: &str) -> Result<RefreshTokenClaims> {
+    let secret = env::var("JWT_RF_KEY").expect("JWT_RF_KEY must be set");
+    let token_data = decode::<RefreshTokenClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )?;
+
+    Ok(token_data.claims)
+}
+
 /// Middleware to check access token validity
 /// Equivalent to Go's CheckAccessToken middleware
+/// Also verifies password_version and active status against the database
 pub async fn check_access_token(
+    State(state): State<AppState>,
     headers: HeaderMap,
     mut request: Request,
     next: Next,
@@ -213,17 +257,51 @@ pub async fn check_access_token(
         return Err((StatusCode::UNAUTHORIZED, "Token expired".to_string()));
     }
 
+    // Reject tokens revoked by logout
+    let is_revoked = is_token_revoked(&state, &token_data.claims.jti)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check token revocation: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to validate session".to_string(),
+            )
+        })?;
+
+    if is_revoked {
+        return Err((StatusCode::UNAUTHORIZED, "Token has been revoked".to_string()));
+    }
+
+    // Reject tokens issued before a password change or for deactivated users
+    let status = get_user_auth_status(&state, token_data.claims.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to validate session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to validate session".to_string(),
+            )
+        })?
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "User not found".to_string()))?;
+
+    if status.user_is_active != 1 {
+        return Err((StatusCode::UNAUTHORIZED, "User account is deactivated".to_string()));
+    }
+
+    if !status.accepts_session(token_data.claims.password_version, token_data.claims.session_epoch) {
+        return Err((StatusCode::UNAUTHORIZED, "Session is no longer valid".to_string()));
+    }
+
     // Store user info in request extensions
     let auth_user = AuthUser {
         user_id: token_data.claims.user_id,
         shop_id: token_data.claims.shop_id,
-        shop_mother_id: token_data.claims.shop_mother_id,
         role_id: token_data.claims.role_id,
         shop_role_id: token_data.claims.shop_role_id,
-        user_email: token_data.claims.user_email.clone(),
         sr_discount_type_id: token_data.claims.sr_discount_type_id,
         sr_discount: token_data.claims.sr_discount,
-        password_version: token_data.claims.password_version,
+        jti: token_data.claims.jti.clone(),
+        exp: token_data.claims.exp,
     };
 
     request.extensions_mut().insert(auth_user);
@@ -231,9 +309,53 @@ pub async fn check_access_token(
     Ok(next.run(request).await)
 }
 
+/// Get user auth status, cached briefly to avoid a database hit per request
+async fn get_user_auth_status(state: &AppState, user_id: i32) -> Result<Option<UserAuthStatus>> {
+    if let Some(status) = USER_STATUS_CACHE.get(&user_id) {
+        return Ok(Some(status));
+    }
+
+    let status = UserModel::get_user_auth_status(&state.db1, user_id).await?;
+    if let Some(status) = &status {
+        USER_STATUS_CACHE.insert(user_id, status.clone());
+    }
+
+    Ok(status)
+}
+
+/// Drop cached auth status so password, active or session changes apply immediately
+pub fn invalidate_user_auth_status(user_id: i32) {
+    USER_STATUS_CACHE.remove(&user_id);
+}
+
+/// Check whether a token jti is on the revocation list
+async fn is_token_revoked(state: &AppState, jti: &str) -> Result<bool> {
+    if let Some(is_revoked) = REVOKED_TOKEN_CACHE.get(&jti.to_string()) {
+        return Ok(is_revoked);
+    }
+
+    let is_revoked = RevokedTokenModel::is_revoked(&state.dbl1, jti).await?;
+    REVOKED_TOKEN_CACHE.insert(jti.to_string(), is_revoked);
+
+    Ok(is_revoked)
+}
+
+/// Add a token jti to the revocation list until it expires
+pub async fn revoke_token(state: &AppState, jti: &str, user_id: i32, exp: i64) -> Result<()> {
+    let expires_at = chrono::DateTime::from_timestamp(exp, 0)
+        .unwrap_or_else(Utc::now)
+        .naive_utc();
+
+    RevokedTokenModel::revoke_token(&state.dbl1, jti, user_id, expires_at).await?;
+    REVOKED_TOKEN_CACHE.insert(jti.to_string(), true);
+
+    Ok(())
+}
+
 /// Middleware to check refresh token validity
 /// Equivalent to Go's CheckRefreshToken middleware
 pub async fn check_refresh_token(
+    State(state): State<AppState>,
     headers: HeaderMap,
     mut request: Request,
     next: Next,
@@ -249,13 +371,7 @@ pub async fn check_refresh_token(
             )
         })?;
 
-    let secret = env::var("JWT_RF_KEY").expect("JWT_RF_KEY must be set");
-    let token_data = decode::<RefreshTokenClaims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::new(Algorithm::HS256),
-    )
-    .map_err(|_| {
+    let claims = decode_refresh_token(token).map_err(|_| {
         (
             StatusCode::UNAUTHORIZED,
             "Invalid or expired refresh token".to_string(),
@@ -263,11 +379,25 @@ pub async fn check_refresh_token(
     })?;
 
     let now = Utc::now().timestamp();
-    if token_data.claims.exp < now {
+    if claims.exp < now {
         return Err((StatusCode::UNAUTHORIZED, "Refresh token expired".to_string()));
     }
 
-    request.extensions_mut().insert(token_data.claims);
+    let is_revoked = is_token_revoked(&state, &claims.jti)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check token revocation: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to validate session".to_string(),
+            )
+        })?;
+
+    if is_revoked {
+        return Err((StatusCode::UNAUTHORIZED, "Refresh token has been revoked".to_string()));
+    }
+
+    request.extensions_mut().insert(claims);
 
     Ok(next.run(request).await)
 }
@@ -329,23 +459,42 @@ pub async fn check_tele_public_key(
 mod tests {
     use super::*;
 
+    fn subject() -> TokenSubject {
+        TokenSubject {
+            user_id: 1,
+            shop_id: 2,
+            shop_mother_id: 1,
+            role_id: 1,
+            shop_role_id: 3,
+            user_email: "test@example.com".to_string(),
+            sr_discount_type_id: 0,
+            sr_discount: 0.0,
+            password_version: 1,
+            session_epoch: 0,
+        }
+    }
+
     #[test]
     fn test_create_access_token() {
         std::env::set_var("JWT_AC_KEY", "test_secret_key");
         std::env::set_var("JWT_AC_EXPIRE", "90");
 
-        let token = create_access_token(
-            1,
-            1,
-            1,
-            1,
-            1,
-            "test@example.com".to_string(),
-            0,
-            0.0,
-            1,
-        );
+        let token = create_access_token(&subject());
 
         assert!(token.is_ok());
     }
+
+    #[test]
+    fn test_create_and_decode_refresh_token() {
+        std::env::set_var("JWT_RF_KEY", "test_refresh_key");
+        std::env::set_var("JWT_RF_EXPIRE", "720");
+
+        let token = create_refresh_token(&subject(), 1, "jti-1".to_string(), "family-1".to_string()).unwrap();
+
+        let claims = decode_refresh_token(&token).unwrap();
+        assert_eq!(claims.shop_id, 2);
+        assert_eq!(claims.jti, "jti-1");
+        assert_eq!(claims.family_id, "family-1");
+        assert!(decode_refresh_token("not-a-token").is_err());
+    }
 }
@@ -0,0 +1,134 @@
+use axum::http::HeaderMap;
+use chrono::NaiveDateTime;
+use std::env;
+use std::net::SocketAddr;
+use crate::models::UserLoginLock;
+
+/// Consecutive failed logins before an account is locked
+pub const MAX_FAILED_LOGINS: i32 = 5;
+
+/// How long an account stays locked
+pub const LOCKOUT_MINUTES: i64 = 15;
+
+/// Failed logins allowed from one IP address within IP_WINDOW_MINUTES
+pub const MAX_IP_FAILURES: i64 = 20;
+
+/// Window used for the per-IP failure counter
+pub const IP_WINDOW_MINUTES: i64 = 15;
+
+/// Upper bound for the delay between attempts on one account
+const MAX_BACKOFF_SECS: i64 = 60;
+
+/// Why an account may not attempt a login right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginBlock {
+    /// Locked after too many failures
+    Locked { retry_after_secs: i64 },
+    /// Too soon after the previous failure
+    Backoff { retry_after_secs: i64 },
+}
+
+/// Delay required after a number of consecutive failures: 1s, 2s, 4s, ... capped at 60s
+pub fn backoff_seconds(failed_count: i32) -> i64 {
+    if failed_count <= 0 {
+        return 0;
+    }
+
+    let exponent = (failed_count - 1).min(16) as u32;
+    (1i64 << exponent).min(MAX_BACKOFF_SECS)
+}
+
+/// Check an account's lockout and backoff state at the given UTC time
+pub fn check_login_lock(lock: &UserLoginLock, now: NaiveDateTime) -> Option<LoginBlock> {
+    if let Some(locked_until) = lock.user_locked_until {
+        if locked_until > now {
+            return Some(LoginBlock::Locked {
+                retry_after_secs: (locked_until - now).num_seconds().max(1),
+            });
+        }
+    }
+
+    let last_failed_at = lock.user_last_failed_login_at?;
+    let allowed_at = last_failed_at + chrono::Duration::seconds(backoff_seconds(lock.user_failed_login_count));
+
+    (allowed_at > now).then(|| LoginBlock::Backoff {
+        retry_after_secs: (allowed_at - now).num_seconds().max(1),
+    })
+}
+
+/// Client IP address for throttling and audit
+/// X-Forwarded-For/X-Real-IP are only trusted when TRUST_PROXY=true (API behind a reverse proxy)
+pub fn client_ip(headers: &HeaderMap, addr: SocketAddr) -> String {
+    let trust_proxy = env::var("TRUST_PROXY")
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+
+    if trust_proxy {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.split(',').next())
+            .or_else(|| headers.get("x-real-ip").and_then(|h| h.to_str().ok()))
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty());
+
+        if let Some(ip) = forwarded {
+            return ip.to_string();
+        }
+    }
+
+    addr.ip().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(h: u32, m: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(h, m, s).unwrap()
+    }
+
+    #[test]
+    fn test_backoff_seconds() {
+        assert_eq!(backoff_seconds(0), 0);
+        assert_eq!(backoff_seconds(1), 1);
+        assert_eq!(backoff_seconds(2), 2);
+        assert_eq!(backoff_seconds(4), 8);
+        assert_eq!(backoff_seconds(10), MAX_BACKOFF_SECS);
+        assert_eq!(backoff_seconds(1000), MAX_BACKOFF_SECS);
+    }
+
+    #[test]
+    fn test_check_login_lock() {
+        let lock = UserLoginLock {
+            user_failed_login_count: 3,
+            user_last_failed_login_at: Some(at(10, 0, 0)),
+            user_locked_until: None,
+        };
+        assert_eq!(
+            check_login_lock(&lock, at(10, 0, 1)),
+            Some(LoginBlock::Backoff { retry_after_secs: 3 })
+        );
+        assert_eq!(check_login_lock(&lock, at(10, 0, 4)), None);
+
+        let locked = UserLoginLock {
+            user_locked_until: Some(at(10, 15, 0)),
+            ..lock
+        };
+        assert_eq!(
+            check_login_lock(&locked, at(10, 14, 0)),
+            Some(LoginBlock::Locked { retry_after_secs: 60 })
+        );
+    }
+
+    #[test]
+    fn test_client_ip_ignores_proxy_headers_by_default() {
+        std::env::remove_var("TRUST_PROXY");
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "198.51.100.1".parse().unwrap());
+        let addr: SocketAddr = "203.0.113.7:5000".parse().unwrap();
+
+        assert_eq!(client_ip(&headers, addr), "203.0.113.7");
+    }
+}
@@ -1,12 +1,18 @@
 pub mod jwt;
 pub mod uploadfile;
 pub mod common;
+pub mod cache;
+pub mod permission;
+pub mod login_guard;
 
 pub use jwt::{
     check_access_token, check_refresh_token, check_public_key,
     check_tele_public_key, create_access_token, create_refresh_token,
-    AccessTokenClaims, RefreshTokenClaims, AuthUser,
+    decode_refresh_token, refresh_token_expire_hours, invalidate_user_auth_status, revoke_token,
+    AccessTokenClaims, RefreshTokenClaims, AuthUser, TokenSubject,
 };
 
+pub use permission::{require_permission, ensure_permission, RequirePermission};
+
 pub use uploadfile::{upload_file, upload_s3, upload_excel};
 pub use common::*;
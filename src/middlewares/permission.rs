@@ -0,0 +1,142 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+    http::StatusCode,
+    Json,
+};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::{Arc, LazyLock};
+use crate::configs::AppState;
+use crate::models::PermissionModel;
+use crate::structs::ApiResponse;
+use super::cache::TtlCache;
+use super::jwt::AuthUser;
+
+/// Role of the shop owner; owners hold every permission so a shop cannot lock itself out
+pub const SHOP_OWNER_ROLE_ID: i32 = 1;
+
+/// How long a shop role's permission set is trusted before re-loading
+const PERMISSION_CACHE_TTL_SECS: u64 = 60;
+
+/// Cached permission codes keyed by shop_role_id
+static PERMISSION_CACHE: LazyLock<TtlCache<i32, Arc<HashSet<String>>>> =
+    LazyLock::new(|| TtlCache::new(std::time::Duration::from_secs(PERMISSION_CACHE_TTL_SECS)));
+
+/// Permission required by a route
+/// Attached per route with `middleware::from_fn_with_state(RequirePermission::new(&state, "order.delete"), require_permission)`;
+/// must run inside check_access_token so AuthUser is available
+#[derive(Clone)]
+pub struct RequirePermission {
+    state: AppState,
+    code: &'static str,
+}
+
+impl RequirePermission {
+    pub fn new(state: &AppState, code: &'static str) -> Self {
+        Self {
+            state: state.clone(),
+            code,
+        }
+    }
+}
+
+/// Middleware to reject requests whose shop role lacks the required permission
+pub async fn require_permission(
+    State(required): State<RequirePermission>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<ApiResponse<()>>)> {
+    let auth = request
+        .extensions()
+        .get::<AuthUser>()
+        .cloned()
+        .ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ApiResponse::error("Unauthorized: No auth user found".to_string())),
+            )
+        })?;
+
+    ensure_permission(&required.state, &auth, required.code).await?;
+
+    Ok(next.run(request).await)
+}
+
+/// Check a permission inside a handler, returning the same 403 as the middleware
+pub async fn ensure_permission(
+    state: &AppState,
+    auth: &AuthUser,
+    code: &str,
+) -> Result<(), (StatusCode, Json<ApiResponse<()>>)> {
+    let allowed = has_permission(state, auth, code).await.map_err(|e| {
+        tracing::error!("Failed to load permissions for shop role {}: {}", auth.shop_role_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error("Failed to check permissions".to_string())),
+        )
+    })?;
+
+    if !allowed {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::error(format!("Permission denied: {}", code))),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check whether the authenticated user's shop role grants a permission
+pub async fn has_permission(state: &AppState, auth: &AuthUser, code: &str) -> Result<bool> {
+    if auth.role_id == SHOP_OWNER_ROLE_ID {
+        return Ok(true);
+    }
+
+    let permissions = get_shop_role_permissions(state, auth.shop_role_id).await?;
+
+    Ok(grants(&permissions, code))
+}
+
+/// Get a shop role's permission codes, cached to avoid a database hit per request
+async fn get_shop_role_permissions(state: &AppState, shop_role_id: i32) -> Result<Arc<HashSet<String>>> {
+    if let Some(permissions) = PERMISSION_CACHE.get(&shop_role_id) {
+        return Ok(permissions);
+    }
+
+    let codes = PermissionModel::get_shop_role_permissions(&state.db1, shop_role_id).await?;
+    let permissions = Arc::new(codes.into_iter().collect::<HashSet<_>>());
+    PERMISSION_CACHE.insert(shop_role_id, permissions.clone());
+
+    Ok(permissions)
+}
+
+/// Match a permission code exactly or through a "module.*" wildcard
+fn grants(permissions: &HashSet<String>, code: &str) -> bool {
+    if permissions.contains(code) {
+        return true;
+    }
+
+    code.split_once('.')
+        .map(|(module, _)| permissions.contains(&format!("{}.*", module)))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grants() {
+        let permissions: HashSet<String> = ["order.view", "user.*"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert!(grants(&permissions, "order.view"));
+        assert!(!grants(&permissions, "order.delete"));
+        assert!(grants(&permissions, "user.invite"));
+        assert!(!grants(&permissions, "customer"));
+    }
+}
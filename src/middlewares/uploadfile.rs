@@ -17,6 +17,7 @@ const ALLOWED_EXCEL_TYPES: &[&str] = &[
     "application/vnd.ms-excel",
     "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
 ];
+const ALLOWED_DOCUMENT_TYPES: &[&str] = &["application/pdf"];
 
 /// File upload configuration
 pub struct UploadConfig {
@@ -113,7 +114,7 @@ pub async fn upload_s3(
     folder: Option<&str>,
 ) -> Result<UploadResult, (StatusCode, String)> {
     // Validate file type
-    if !ALLOWED_IMAGE_TYPES.contains(&content_type) {
+    if !ALLOWED_IMAGE_TYPES.contains(&content_type) && !ALLOWED_DOCUMENT_TYPES.contains(&content_type) {
         return Err((
             StatusCode::BAD_REQUEST,
             format!("Invalid file type: {}", content_type),
@@ -140,7 +141,9 @@ pub async fn upload_s3(
 
     // Initialize S3 client
     let config = aws_config::defaults(BehaviorVersion::latest())
-        .region(env::var("AWS_REGION").unwrap_or_else(|_| "ap-southeast-1".to_string()))
+        .region(aws_config::Region::new(
+            env::var("AWS_REGION").unwrap_or_else(|_| "ap-southeast-1".to_string()),
+        ))
         .load()
         .await;
 
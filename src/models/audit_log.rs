@@ -0,0 +1,37 @@
+use sqlx::{MySql, Pool};
+use anyhow::Result;
+
+/// Audit log model (stored in the logging database)
+/// One row per sensitive action, e.g. entity "order", action "delete"
+pub struct AuditLogModel;
+
+impl AuditLogModel {
+    /// Record an action on an entity
+    pub async fn record(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        user_id: i32,
+        entity: &str,
+        entity_id: i32,
+        action: &str,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_logs
+            (shop_id, user_id, al_entity, al_entity_id, al_action, al_detail, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, NOW())
+            "#,
+        )
+        .bind(shop_id)
+        .bind(user_id)
+        .bind(entity)
+        .bind(entity_id)
+        .bind(action)
+        .bind(detail)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -1,27 +1,148 @@
-use sqlx::{FromRow, MySql, Pool};
+use sqlx::{FromRow, MySql, Pool, QueryBuilder};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
+use super::document_sequence::{DocumentSequenceModel, DocumentType};
+use super::order::escape_like;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::libs::{name_block_keys, name_similarity, normalize_person_name, phone_search_digits, thailand_today, NAME_MATCH_THRESHOLD};
 
+/// Customer database model
+/// customer_tel is stored normalized (digits only, national form) so it can be matched exactly
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Customer {
     pub id: i32,
     pub shop_id: i32,
+    pub customer_code: String,
     pub customer_fname: String,
     pub customer_lname: String,
     pub customer_tel: String,
     pub customer_email: Option<String>,
+    pub customer_national_id: Option<String>,
+    pub customer_birthdate: Option<NaiveDate>,
+    pub customer_address: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub deleted_by: Option<i32>,
 }
 
+/// Customer fields saved on create and update; the customer code comes from the shop's
+/// customer sequence and never changes
+#[derive(Debug, Clone)]
+pub struct CustomerFields {
+    pub fname: String,
+    pub lname: String,
+    /// Normalized with normalize_thai_phone
+    pub tel: String,
+    pub email: Option<String>,
+    pub national_id: Option<String>,
+    pub birthdate: Option<NaiveDate>,
+    pub address: Option<String>,
+}
+
+/// Customer search filters
+/// keyword matches any of name, phone, email and customer code; the other filters must all match
+/// sort_column must come from a fixed whitelist since it is pushed into the SQL as-is
+#[derive(Debug, Clone)]
+pub struct CustomerSearch {
+    pub shop_id: i32,
+    pub keyword: Option<String>,
+    pub name: Option<String>,
+    /// Digits of a full or partial phone number
+    pub tel: Option<String>,
+    pub email: Option<String>,
+    pub customer_code: Option<String>,
+    pub sort_column: &'static str,
+    pub sort_desc: bool,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Tables with a customer_id column whose rows move to the surviving customer on a merge
+/// appointments and attachments are written by the clinic modules of the Go API
+pub const CUSTOMER_REFERENCES: [&str; 3] = ["orders", "appointments", "attachments"];
+
+/// Why two customers look like the same person
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchReason {
+    NationalId,
+    Phone,
+    Email,
+    /// Names at least NAME_MATCH_THRESHOLD similar after normalization
+    Name,
+}
+
+/// Two customers that may be the same person (customer_id < duplicate_id)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateMatch {
+    pub customer_id: i32,
+    pub duplicate_id: i32,
+    pub reasons: Vec<MatchReason>,
+    pub name_similarity: f64,
+}
+
+/// Result of a shop-wide duplicate scan, kept so the pages of the list do not rescan the shop
+#[derive(Debug)]
+pub struct DuplicateScan {
+    pub customers: HashMap<i32, Customer>,
+    /// Most likely first
+    pub matches: Vec<DuplicateMatch>,
+}
+
+impl DuplicateScan {
+    /// Scan the customers for duplicates; CPU-bound, call it from a blocking task
+    pub fn new(customers: Vec<Customer>) -> Self {
+        let matches = find_duplicate_pairs(&customers);
+        DuplicateScan {
+            customers: customers.into_iter().map(|c| (c.id, c)).collect(),
+            matches,
+        }
+    }
+}
+
+/// Merge of one customer into another (customer_merges row)
+/// cm_moved is a JSON object of the row IDs re-pointed per table, e.g. {"orders":[12,15]}, so an
+/// undo moves back exactly those rows
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CustomerMerge {
+    pub id: i32,
+    pub shop_id: i32,
+    /// Surviving customer
+    pub cm_target_id: i32,
+    /// Customer merged away; soft-deleted with merged_into set
+    pub cm_source_id: i32,
+    pub cm_moved: String,
+    pub merged_by: i32,
+    /// UTC
+    pub merged_at: NaiveDateTime,
+    /// UTC time until which the merge can be undone
+    pub cm_undo_until: NaiveDateTime,
+    /// UTC
+    pub undone_at: Option<NaiveDateTime>,
+    pub undone_by: Option<i32>,
+}
+
+impl CustomerMerge {
+    /// Row IDs moved to the target customer, per table
+    pub fn moved_rows(&self) -> BTreeMap<String, Vec<i32>> {
+        serde_json::from_str(&self.cm_moved).unwrap_or_default()
+    }
+}
+
+/// Customer model with database operations
 pub struct CustomerModel;
 
 impl CustomerModel {
+    /// Get customer by ID (soft-deleted customers are excluded)
     pub async fn get_customer_by_id(
         db: &Pool<MySql>,
         customer_id: i32,
         shop_id: i32,
     ) -> Result<Customer> {
         let customer = sqlx::query_as::<_, Customer>(
-            "SELECT * FROM customers WHERE id = ? AND shop_id = ?",
+            "SELECT * FROM customers WHERE id = ? AND shop_id = ? AND deleted_at IS NULL",
         )
         .bind(customer_id)
         .bind(shop_id)
@@ -29,4 +150,800 @@ impl CustomerModel {
         .await?;
         Ok(customer)
     }
+
+    /// Search customers with filters
+    /// Returns one page of customers and the total number of matches
+    pub async fn search_customers(
+        db: &Pool<MySql>,
+        search: &CustomerSearch,
+    ) -> Result<(Vec<Customer>, i64)> {
+        let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM customers");
+        push_search_filters(&mut count_query, search);
+
+        let total: i64 = count_query
+            .build_query_scalar()
+            .fetch_one(db)
+            .await?;
+
+        let mut query = QueryBuilder::new("SELECT * FROM customers");
+        push_search_filters(&mut query, search);
+        push_search_order(&mut query, search);
+
+        let customers = query
+            .build_query_as::<Customer>()
+            .fetch_all(db)
+            .await?;
+
+        Ok((customers, total))
+    }
+
+    /// Customers of the shop with the same phone number, email or national ID
+    /// exclude_id leaves out the customer being edited
+    pub async fn find_duplicates(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        customer: &CustomerFields,
+        exclude_id: Option<i32>,
+    ) -> Result<Vec<Customer>> {
+        let mut query = QueryBuilder::new("SELECT * FROM customers");
+        push_duplicate_filters(&mut query, shop_id, customer, exclude_id);
+
+        let customers = query
+            .build_query_as::<Customer>()
+            .fetch_all(db)
+            .await?;
+
+        Ok(customers)
+    }
+
+    /// Create a customer with the next customer code of the shop
+    pub async fn create_customer(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        customer: &CustomerFields,
+    ) -> Result<i32> {
+        let mut tx = db.begin().await?;
+
+        let customer_code = DocumentSequenceModel::next_number(&mut tx, shop_id, DocumentType::Customer, thailand_today()).await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO customers
+            (shop_id, customer_code, customer_fname, customer_lname, customer_tel, customer_email,
+             customer_national_id, customer_birthdate, customer_address, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, NOW(), NOW())
+            "#,
+        )
+        .bind(shop_id)
+        .bind(&customer_code)
+        .bind(&customer.fname)
+        .bind(&customer.lname)
+        .bind(&customer.tel)
+        .bind(&customer.email)
+        .bind(&customer.national_id)
+        .bind(customer.birthdate)
+        .bind(&customer.address)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    /// Update a customer's details
+    /// Returns false if the customer does not exist or is deleted
+    pub async fn update_customer(
+        db: &Pool<MySql>,
+        customer_id: i32,
+        shop_id: i32,
+        customer: &CustomerFields,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE customers
+            SET customer_fname = ?,
+                customer_lname = ?,
+                customer_tel = ?,
+                customer_email = ?,
+                customer_national_id = ?,
+                customer_birthdate = ?,
+                customer_address = ?,
+                updated_at = NOW()
+            WHERE id = ? AND shop_id = ? AND deleted_at IS NULL
+            "#,
+        )
+        .bind(&customer.fname)
+        .bind(&customer.lname)
+        .bind(&customer.tel)
+        .bind(&customer.email)
+        .bind(&customer.national_id)
+        .bind(customer.birthdate)
+        .bind(&customer.address)
+        .bind(customer_id)
+        .bind(shop_id)
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Soft delete customer; their orders keep pointing at the deleted row
+    /// Returns false if the customer does not exist or is already deleted
+    pub async fn delete_customer(
+        db: &Pool<MySql>,
+        customer_id: i32,
+        shop_id: i32,
+        deleted_by: i32,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE customers
+            SET deleted_at = NOW(), deleted_by = ?
+            WHERE id = ? AND shop_id = ? AND deleted_at IS NULL
+            "#,
+        )
+        .bind(deleted_by)
+        .bind(customer_id)
+        .bind(shop_id)
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// All customers of the shop, for the duplicate finder
+    pub async fn get_shop_customers(
+        db: &Pool<MySql>,
+        shop_id: i32,
+    ) -> Result<Vec<Customer>> {
+        let customers = sqlx::query_as::<_, Customer>(
+            "SELECT * FROM customers WHERE shop_id = ? AND deleted_at IS NULL ORDER BY id",
+        )
+        .bind(shop_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(customers)
+    }
+
+    /// Merge source into target in one transaction: the source's orders, appointments and
+    /// attachments move to the target and the source is soft-deleted
+    /// Returns the merge ID, or None if either customer does not exist or is deleted
+    pub async fn merge_customers(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        target_id: i32,
+        source_id: i32,
+        merged_by: i32,
+        undo_hours: i64,
+    ) -> Result<Option<i32>> {
+        let mut tx = db.begin().await?;
+
+        // Lock both customers so neither is edited, deleted or merged elsewhere meanwhile
+        let locked: Vec<i32> = sqlx::query_scalar(
+            "SELECT id FROM customers WHERE shop_id = ? AND id IN (?, ?) AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(shop_id)
+        .bind(target_id)
+        .bind(source_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if locked.len() != 2 {
+            return Ok(None);
+        }
+
+        let mut moved = BTreeMap::new();
+        for table in CUSTOMER_REFERENCES {
+            let ids: Vec<i32> = sqlx::query_scalar(&format!("SELECT id FROM {} WHERE customer_id = ? FOR UPDATE", table))
+                .bind(source_id)
+                .fetch_all(&mut *tx)
+                .await?;
+
+            if !ids.is_empty() {
+                sqlx::query(&format!("UPDATE {} SET customer_id = ? WHERE customer_id = ?", table))
+                    .bind(target_id)
+                    .bind(source_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            moved.insert(table.to_string(), ids);
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE customers
+            SET deleted_at = NOW(), deleted_by = ?, merged_into = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(merged_by)
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO customer_merges
+            (shop_id, cm_target_id, cm_source_id, cm_moved, merged_by, merged_at, cm_undo_until)
+            VALUES (?, ?, ?, ?, ?, UTC_TIMESTAMP(), UTC_TIMESTAMP() + INTERVAL ? HOUR)
+            "#,
+        )
+        .bind(shop_id)
+        .bind(target_id)
+        .bind(source_id)
+        .bind(serde_json::to_string(&moved)?)
+        .bind(merged_by)
+        .bind(undo_hours)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(result.last_insert_id() as i32))
+    }
+
+    /// Get a merge of the shop
+    pub async fn get_merge(
+        db: &Pool<MySql>,
+        merge_id: i32,
+        shop_id: i32,
+    ) -> Result<Option<CustomerMerge>> {
+        let merge = sqlx::query_as::<_, CustomerMerge>(
+            "SELECT * FROM customer_merges WHERE id = ? AND shop_id = ?",
+        )
+        .bind(merge_id)
+        .bind(shop_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(merge)
+    }
+
+    /// Merges of the shop that can still be undone, latest first
+    pub async fn get_undoable_merges(
+        db: &Pool<MySql>,
+        shop_id: i32,
+    ) -> Result<Vec<CustomerMerge>> {
+        let merges = sqlx::query_as::<_, CustomerMerge>(
+            r#"
+            SELECT *
+            FROM customer_merges
+            WHERE shop_id = ? AND undone_at IS NULL AND cm_undo_until > UTC_TIMESTAMP()
+            ORDER BY id DESC
+            "#,
+        )
+        .bind(shop_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(merges)
+    }
+
+    /// Undo a merge in one transaction: the moved rows go back to the source customer, which is
+    /// restored
+    /// Rows the target has gained since the merge stay with the target
+    /// Returns false if the merge was already undone or has expired, the target has since been
+    /// deleted or merged away, or the source is no longer merged into the target
+    pub async fn undo_merge(
+        db: &Pool<MySql>,
+        merge: &CustomerMerge,
+        undone_by: i32,
+    ) -> Result<bool> {
+        let mut tx = db.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE customer_merges
+            SET undone_at = UTC_TIMESTAMP(), undone_by = ?
+            WHERE id = ? AND undone_at IS NULL AND cm_undo_until > UTC_TIMESTAMP()
+            "#,
+        )
+        .bind(undone_by)
+        .bind(merge.id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        // Lock both customers so the target cannot be deleted or merged away, and the source
+        // cannot change, while the rows move back
+        let target: Option<i32> = sqlx::query_scalar(
+            "SELECT id FROM customers WHERE id = ? AND shop_id = ? AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(merge.cm_target_id)
+        .bind(merge.shop_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let source: Option<i32> = sqlx::query_scalar(
+            "SELECT id FROM customers WHERE id = ? AND shop_id = ? AND merged_into = ? FOR UPDATE",
+        )
+        .bind(merge.cm_source_id)
+        .bind(merge.shop_id)
+        .bind(merge.cm_target_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if target.is_none() || source.is_none() {
+            return Ok(false);
+        }
+
+        for (table, ids) in merge.moved_rows() {
+            // The table name goes into the SQL as-is, so only known tables are accepted
+            let Some(table) = CUSTOMER_REFERENCES.iter().find(|&&t| t == table) else {
+                continue;
+            };
+            if ids.is_empty() {
+                continue;
+            }
+
+            let mut query = QueryBuilder::new(format!("UPDATE {} SET customer_id = ", table));
+            push_move_back(&mut query, merge, &ids);
+            query.build().execute(&mut *tx).await?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE customers
+            SET deleted_at = NULL, deleted_by = NULL, merged_into = NULL, updated_at = NOW()
+            WHERE id = ? AND merged_into = ?
+            "#,
+        )
+        .bind(merge.cm_source_id)
+        .bind(merge.cm_target_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+}
+
+/// Append WHERE clause for a customer search
+fn push_search_filters(query: &mut QueryBuilder<'_, MySql>, search: &CustomerSearch) {
+    query.push(" WHERE shop_id = ").push_bind(search.shop_id);
+    query.push(" AND deleted_at IS NULL");
+
+    if let Some(keyword) = search.keyword.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+        let pattern = format!("%{}%", escape_like(keyword));
+        query
+            .push(" AND (CONCAT(customer_fname, ' ', customer_lname) LIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR customer_email LIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR customer_code LIKE ")
+            .push_bind(pattern);
+
+        if let Some(digits) = phone_search_digits(keyword) {
+            query
+                .push(" OR customer_tel LIKE ")
+                .push_bind(format!("%{}%", digits));
+        }
+
+        query.push(")");
+    }
+
+    if let Some(name) = search.name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+        query
+            .push(" AND CONCAT(customer_fname, ' ', customer_lname) LIKE ")
+            .push_bind(format!("%{}%", escape_like(name)));
+    }
+
+    if let Some(tel) = search.tel.as_deref().filter(|t| !t.is_empty()) {
+        query
+            .push(" AND customer_tel LIKE ")
+            .push_bind(format!("%{}%", escape_like(tel)));
+    }
+
+    if let Some(email) = search.email.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+        query
+            .push(" AND customer_email LIKE ")
+            .push_bind(format!("%{}%", escape_like(email)));
+    }
+
+    if let Some(code) = search.customer_code.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+        query
+            .push(" AND customer_code LIKE ")
+            .push_bind(format!("{}%", escape_like(code)));
+    }
+}
+
+/// Append ORDER BY and LIMIT for a customer search
+fn push_search_order(query: &mut QueryBuilder<'_, MySql>, search: &CustomerSearch) {
+    query
+        .push(" ORDER BY ")
+        .push(search.sort_column)
+        .push(if search.sort_desc { " DESC" } else { " ASC" })
+        // Tie-break on id so pages are stable
+        .push(", id DESC")
+        .push(" LIMIT ")
+        .push_bind(search.limit)
+        .push(" OFFSET ")
+        .push_bind(search.offset);
+}
+
+/// Append WHERE clause matching customers that share a phone number, email or national ID
+fn push_duplicate_filters(
+    query: &mut QueryBuilder<'_, MySql>,
+    shop_id: i32,
+    customer: &CustomerFields,
+    exclude_id: Option<i32>,
+) {
+    query.push(" WHERE shop_id = ").push_bind(shop_id);
+    query.push(" AND deleted_at IS NULL");
+
+    if let Some(exclude_id) = exclude_id {
+        query.push(" AND id <> ").push_bind(exclude_id);
+    }
+
+    // Emails compare case-insensitively under the table's collation
+    query.push(" AND (customer_tel = ").push_bind(customer.tel.clone());
+    if let Some(email) = customer.email.as_deref() {
+        query.push(" OR customer_email = ").push_bind(email.to_string());
+    }
+    if let Some(national_id) = customer.national_id.as_deref() {
+        query.push(" OR customer_national_id = ").push_bind(national_id.to_string());
+    }
+    query.push(") ORDER BY id LIMIT 10");
+}
+
+/// Append the rest of an undo UPDATE: moved rows still on the target go back to the source
+fn push_move_back(query: &mut QueryBuilder<'_, MySql>, merge: &CustomerMerge, ids: &[i32]) {
+    query
+        .push_bind(merge.cm_source_id)
+        .push(" WHERE customer_id = ")
+        .push_bind(merge.cm_target_id)
+        .push(" AND id IN (");
+    let mut separated = query.separated(", ");
+    for &id in ids {
+        separated.push_bind(id);
+    }
+    separated.push_unseparated(")");
+}
+
+/// Customer with their name normalized once for pairwise comparison
+struct MatchCandidate<'a> {
+    customer: &'a Customer,
+    name: String,
+}
+
+impl<'a> MatchCandidate<'a> {
+    fn new(customer: &'a Customer) -> Self {
+        let name = normalize_person_name(&format!("{} {}", customer.customer_fname, customer.customer_lname));
+        MatchCandidate { customer, name }
+    }
+}
+
+/// Compare two customers; None if nothing matches
+fn match_candidates(a: &MatchCandidate, b: &MatchCandidate) -> Option<DuplicateMatch> {
+    let (x, y) = (a.customer, b.customer);
+    if x.id == y.id {
+        return None;
+    }
+
+    let same = |p: Option<&str>, q: Option<&str>| match (p, q) {
+        (Some(p), Some(q)) => !p.is_empty() && p.eq_ignore_ascii_case(q),
+        _ => false,
+    };
+
+    let name_similarity = name_similarity(&a.name, &b.name);
+    let mut reasons = Vec::new();
+    if same(x.customer_national_id.as_deref(), y.customer_national_id.as_deref()) {
+        reasons.push(MatchReason::NationalId);
+    }
+    if same(Some(&x.customer_tel), Some(&y.customer_tel)) {
+        reasons.push(MatchReason::Phone);
+    }
+    if same(x.customer_email.as_deref(), y.customer_email.as_deref()) {
+        reasons.push(MatchReason::Email);
+    }
+    if name_similarity >= NAME_MATCH_THRESHOLD {
+        reasons.push(MatchReason::Name);
+    }
+
+    if reasons.is_empty() {
+        return None;
+    }
+
+    Some(DuplicateMatch {
+        customer_id: x.id.min(y.id),
+        duplicate_id: x.id.max(y.id),
+        reasons,
+        name_similarity,
+    })
+}
+
+/// Find customers that may be the same person, most likely first
+/// Pairs sharing a phone, email or national ID are always compared; for names alone only
+/// customers in the same name bucket (first letter and length band) are, to keep a shop-wide
+/// scan fast
+/// CPU-bound on large shops; call it from a blocking task
+pub fn find_duplicate_pairs(customers: &[Customer]) -> Vec<DuplicateMatch> {
+    let candidates: Vec<MatchCandidate> = customers.iter().map(MatchCandidate::new).collect();
+
+    let mut groups: HashMap<(u8, String), Vec<usize>> = HashMap::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        let customer = candidate.customer;
+        let mut keys: Vec<(u8, String)> = [
+            customer.customer_national_id.clone(),
+            Some(customer.customer_tel.clone()),
+            customer.customer_email.as_deref().map(str::to_lowercase),
+        ]
+        .into_iter()
+        .enumerate()
+        .filter_map(|(kind, key)| key.map(|key| (kind as u8, key)))
+        .collect();
+        keys.extend(
+            name_block_keys(&candidate.name)
+                .into_iter()
+                .map(|(letter, band)| (3, format!("{}{}", letter, band))),
+        );
+
+        for key in keys.into_iter().filter(|(_, key)| !key.is_empty()) {
+            groups.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut pairs = HashSet::new();
+    for members in groups.values() {
+        for (n, &i) in members.iter().enumerate() {
+            for &j in &members[n + 1..] {
+                pairs.insert((i.min(j), i.max(j)));
+            }
+        }
+    }
+
+    let mut matches: Vec<DuplicateMatch> = pairs
+        .into_iter()
+        .filter_map(|(i, j)| match_candidates(&candidates[i], &candidates[j]))
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.reasons
+            .len()
+            .cmp(&a.reasons.len())
+            .then(b.name_similarity.total_cmp(&a.name_similarity))
+            .then(a.customer_id.cmp(&b.customer_id))
+            .then(a.duplicate_id.cmp(&b.duplicate_id))
+    });
+    matches
+}
+
+/// Customers that may be the same person as `customer`, most likely first
+/// Compares against every customer; call it from a blocking task
+pub fn find_duplicates_of(customer: &Customer, customers: &[Customer]) -> Vec<DuplicateMatch> {
+    let target = MatchCandidate::new(customer);
+    let mut matches: Vec<DuplicateMatch> = customers
+        .iter()
+        .filter_map(|other| match_candidates(&target, &MatchCandidate::new(other)))
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.reasons
+            .len()
+            .cmp(&a.reasons.len())
+            .then(b.name_similarity.total_cmp(&a.name_similarity))
+    });
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search() -> CustomerSearch {
+        CustomerSearch {
+            shop_id: 1,
+            keyword: None,
+            name: None,
+            tel: None,
+            email: None,
+            customer_code: None,
+            sort_column: "created_at",
+            sort_desc: true,
+            limit: 20,
+            offset: 0,
+        }
+    }
+
+    fn fields() -> CustomerFields {
+        CustomerFields {
+            fname: "สมชาย".to_string(),
+            lname: "ใจดี".to_string(),
+            tel: "0812345678".to_string(),
+            email: None,
+            national_id: None,
+            birthdate: None,
+            address: None,
+        }
+    }
+
+    #[test]
+    fn test_search_query_binds_only_given_filters() {
+        let mut query = QueryBuilder::new("SELECT * FROM customers");
+        push_search_filters(&mut query, &search());
+        push_search_order(&mut query, &search());
+
+        assert_eq!(
+            query.sql(),
+            "SELECT * FROM customers WHERE shop_id = ? AND deleted_at IS NULL ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?"
+        );
+    }
+
+    #[test]
+    fn test_search_keyword_matches_phone_only_when_it_looks_like_one() {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM customers");
+        push_search_filters(&mut query, &CustomerSearch { keyword: Some("สมชาย".to_string()), ..search() });
+        assert_eq!(
+            query.sql(),
+            "SELECT COUNT(*) FROM customers WHERE shop_id = ? AND deleted_at IS NULL \
+             AND (CONCAT(customer_fname, ' ', customer_lname) LIKE ? OR customer_email LIKE ? OR customer_code LIKE ?)"
+        );
+
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM customers");
+        push_search_filters(&mut query, &CustomerSearch { keyword: Some("081-234".to_string()), ..search() });
+        assert!(query.sql().ends_with("OR customer_code LIKE ? OR customer_tel LIKE ?)"));
+    }
+
+    #[test]
+    fn test_search_query_with_all_filters() {
+        let filters = CustomerSearch {
+            name: Some("สมชาย".to_string()),
+            tel: Some("0812".to_string()),
+            email: Some("@example.com".to_string()),
+            customer_code: Some("HN".to_string()),
+            ..search()
+        };
+
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM customers");
+        push_search_filters(&mut query, &filters);
+
+        assert_eq!(
+            query.sql(),
+            "SELECT COUNT(*) FROM customers WHERE shop_id = ? AND deleted_at IS NULL \
+             AND CONCAT(customer_fname, ' ', customer_lname) LIKE ? AND customer_tel LIKE ? \
+             AND customer_email LIKE ? AND customer_code LIKE ?"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_query() {
+        let mut query = QueryBuilder::new("SELECT * FROM customers");
+        push_duplicate_filters(&mut query, 1, &fields(), None);
+        assert_eq!(
+            query.sql(),
+            "SELECT * FROM customers WHERE shop_id = ? AND deleted_at IS NULL \
+             AND (customer_tel = ?) ORDER BY id LIMIT 10"
+        );
+
+        let customer = CustomerFields {
+            email: Some("somchai@example.com".to_string()),
+            national_id: Some("1101700203450".to_string()),
+            ..fields()
+        };
+        let mut query = QueryBuilder::new("SELECT * FROM customers");
+        push_duplicate_filters(&mut query, 1, &customer, Some(7));
+        assert_eq!(
+            query.sql(),
+            "SELECT * FROM customers WHERE shop_id = ? AND deleted_at IS NULL AND id <> ? \
+             AND (customer_tel = ? OR customer_email = ? OR customer_national_id = ?) ORDER BY id LIMIT 10"
+        );
+    }
+
+    fn customer(id: i32, fname: &str, lname: &str, tel: &str) -> Customer {
+        Customer {
+            id,
+            shop_id: 1,
+            customer_code: format!("HN{:05}", id),
+            customer_fname: fname.to_string(),
+            customer_lname: lname.to_string(),
+            customer_tel: tel.to_string(),
+            customer_email: None,
+            customer_national_id: None,
+            customer_birthdate: None,
+            customer_address: None,
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    fn match_customers(a: &Customer, b: &Customer) -> Option<DuplicateMatch> {
+        match_candidates(&MatchCandidate::new(a), &MatchCandidate::new(b))
+    }
+
+    #[test]
+    fn test_match_customers() {
+        let somchai = customer(1, "สมชาย", "ใจดี", "0812345678");
+
+        // Same phone, name typed with a typo
+        let found = match_customers(&somchai, &customer(2, "สมชัย", "ใจดี", "0812345678")).unwrap();
+        assert_eq!(found.reasons, vec![MatchReason::Phone, MatchReason::Name]);
+        assert_eq!((found.customer_id, found.duplicate_id), (1, 2));
+
+        // Family members sharing a phone still match, on the phone only
+        let found = match_customers(&customer(3, "สมหญิง", "ใจดี", "0812345678"), &somchai).unwrap();
+        assert_eq!(found.reasons, vec![MatchReason::Phone]);
+        assert_eq!((found.customer_id, found.duplicate_id), (1, 3));
+
+        // National ID and email, whatever the name
+        let mut a = customer(4, "Somchai", "Jaidee", "0899999999");
+        a.customer_national_id = Some("1101700203450".to_string());
+        a.customer_email = Some("Somchai@Example.com".to_string());
+        let mut b = a.clone();
+        b.id = 5;
+        b.customer_tel = "0811111111".to_string();
+        b.customer_email = Some("somchai@example.com".to_string());
+        assert_eq!(
+            match_customers(&a, &b).unwrap().reasons,
+            vec![MatchReason::NationalId, MatchReason::Email, MatchReason::Name]
+        );
+
+        assert_eq!(match_customers(&somchai, &customer(6, "วิไล", "มั่นคง", "0822222222")), None);
+        assert_eq!(match_customers(&somchai, &somchai), None);
+    }
+
+    #[test]
+    fn test_find_duplicate_pairs() {
+        let customers = vec![
+            customer(1, "สมชาย", "ใจดี", "0812345678"),
+            customer(2, "นาย สมชาย", "ใจดี", "021234567"),
+            customer(3, "สมหญิง", "ใจดี", "0812345678"),
+            customer(4, "สมชัย", "ใจดี", "0812345678"),
+            customer(5, "วิไล", "มั่นคง", "0822222222"),
+        ];
+
+        let pairs: Vec<(i32, i32, usize)> = find_duplicate_pairs(&customers)
+            .iter()
+            .map(|m| (m.customer_id, m.duplicate_id, m.reasons.len()))
+            .collect();
+
+        // Phone and name first, then name only and phone only
+        assert_eq!(pairs[0], (1, 4, 2));
+        assert!(pairs.contains(&(1, 2, 1)));
+        assert!(pairs.contains(&(2, 4, 1)));
+        assert!(pairs.contains(&(1, 3, 1)));
+        assert!(pairs.contains(&(3, 4, 1)));
+        assert!(!pairs.iter().any(|&(a, b, _)| a == 5 || b == 5));
+        assert_eq!(pairs.len(), 5);
+
+        let of_vilai = find_duplicates_of(&customers[4], &customers);
+        assert!(of_vilai.is_empty());
+        let of_somchai = find_duplicates_of(&customers[0], &customers);
+        assert_eq!(of_somchai.len(), 3);
+        assert_eq!(of_somchai[0].duplicate_id, 4);
+    }
+
+    #[test]
+    fn test_undo_moves_back_only_recorded_rows() {
+        let merge = CustomerMerge {
+            id: 1,
+            shop_id: 1,
+            cm_target_id: 3,
+            cm_source_id: 9,
+            cm_moved: r#"{"appointments":[],"orders":[12,15]}"#.to_string(),
+            merged_by: 1,
+            merged_at: NaiveDate::from_ymd_opt(2026, 10, 17).unwrap().and_hms_opt(9, 0, 0).unwrap(),
+            cm_undo_until: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap().and_hms_opt(2, 0, 0).unwrap(),
+            undone_at: None,
+            undone_by: None,
+        };
+        let moved = merge.moved_rows();
+        assert_eq!(moved["orders"], vec![12, 15]);
+
+        let mut query = QueryBuilder::new("UPDATE orders SET customer_id = ");
+        push_move_back(&mut query, &merge, &moved["orders"]);
+        assert_eq!(
+            query.sql(),
+            "UPDATE orders SET customer_id = ? WHERE customer_id = ? AND id IN (?, ?)"
+        );
+    }
 }
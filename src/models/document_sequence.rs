@@ -0,0 +1,362 @@
+use sqlx::{FromRow, MySql, Pool, Transaction};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use crate::libs::buddhist_year;
+
+/// Running number used when a shop has not configured a sequence
+const DEFAULT_PADDING: i32 = 5;
+
+/// Document kinds with their own running number per shop (document_sequences.ds_type)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[repr(i8)]
+pub enum DocumentType {
+    Order = 1,
+    Receipt = 2,
+    TaxInvoice = 3,
+    CreditNote = 4,
+    /// Customer codes (HN), kept for the life of the customer
+    Customer = 5,
+}
+
+impl DocumentType {
+    pub const ALL: [DocumentType; 5] = [
+        DocumentType::Order,
+        DocumentType::Receipt,
+        DocumentType::TaxInvoice,
+        DocumentType::CreditNote,
+        DocumentType::Customer,
+    ];
+
+    /// Prefix used until the shop configures its own
+    pub fn default_prefix(self) -> &'static str {
+        match self {
+            DocumentType::Order => "ORD",
+            DocumentType::Receipt => "RC",
+            DocumentType::TaxInvoice => "INV",
+            DocumentType::CreditNote => "CN",
+            DocumentType::Customer => "HN",
+        }
+    }
+
+    /// Reset period used until the shop configures its own; customer codes never restart
+    pub fn default_reset(self) -> SequenceReset {
+        match self {
+            DocumentType::Customer => SequenceReset::Never,
+            _ => SequenceReset::default(),
+        }
+    }
+}
+
+/// When the running number starts again from 1
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[repr(i8)]
+pub enum SequenceReset {
+    Never = 0,
+    #[default]
+    Yearly = 1,
+    Monthly = 2,
+}
+
+impl SequenceReset {
+    /// Period key of a date in the Buddhist Era: "" (never), "2569" (yearly), "256910" (monthly)
+    pub fn period(self, date: &NaiveDate) -> String {
+        match self {
+            SequenceReset::Never => String::new(),
+            SequenceReset::Yearly => buddhist_year(date).to_string(),
+            SequenceReset::Monthly => format!("{}{:02}", buddhist_year(date), date.month()),
+        }
+    }
+}
+
+/// Document sequence of a shop (document_sequences row)
+/// ds_period/ds_last_number hold the last number handed out and the period it belongs to
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DocumentSequence {
+    pub shop_id: i32,
+    pub ds_type: DocumentType,
+    pub ds_prefix: String,
+    pub ds_reset: SequenceReset,
+    pub ds_padding: i32,
+    pub ds_period: String,
+    pub ds_last_number: i32,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl DocumentSequence {
+    /// Sequence of a shop that has not configured or used a document type yet
+    pub fn default_for(shop_id: i32, document_type: DocumentType) -> Self {
+        DocumentSequence {
+            shop_id,
+            ds_type: document_type,
+            ds_prefix: document_type.default_prefix().to_string(),
+            ds_reset: document_type.default_reset(),
+            ds_padding: DEFAULT_PADDING,
+            ds_period: String::new(),
+            ds_last_number: 0,
+            updated_at: None,
+        }
+    }
+
+    /// Period and running number of the next document dated `date`
+    pub fn next_for(&self, date: &NaiveDate) -> (String, i32) {
+        let period = self.ds_reset.period(date);
+        let number = if self.ds_period == period {
+            self.ds_last_number + 1
+        } else {
+            1
+        };
+        (period, number)
+    }
+
+    /// Whether the prefix and reset period can change on `date`
+    /// Once a number of the current period is out, another format or period key could lead
+    /// back to numbers already issued, so the change waits for the next period
+    pub fn can_change_format(&self, date: &NaiveDate) -> bool {
+        self.ds_last_number == 0 || self.ds_period != self.ds_reset.period(date)
+    }
+
+    /// Formatted number of the next document dated `date`
+    pub fn preview(&self, date: &NaiveDate) -> String {
+        let (period, number) = self.next_for(date);
+        format_document_number(&self.ds_prefix, &period, self.ds_padding, number)
+    }
+}
+
+/// Format a document number, e.g. "INV" + "256910" + 42 padded to 5 -> "INV256910-00042"
+pub fn format_document_number(prefix: &str, period: &str, padding: i32, number: i32) -> String {
+    let width = padding.max(1) as usize;
+    if period.is_empty() {
+        format!("{}{:0width$}", prefix, number, width = width)
+    } else {
+        format!("{}{}-{:0width$}", prefix, period, number, width = width)
+    }
+}
+
+/// Document sequence model with database operations
+pub struct DocumentSequenceModel;
+
+impl DocumentSequenceModel {
+    /// Get the configured sequences of a shop
+    pub async fn get_shop_sequences(
+        db: &Pool<MySql>,
+        shop_id: i32,
+    ) -> Result<Vec<DocumentSequence>> {
+        let sequences = sqlx::query_as::<_, DocumentSequence>(
+            r#"
+            SELECT shop_id, ds_type, ds_prefix, ds_reset, ds_padding, ds_period, ds_last_number, updated_at
+            FROM document_sequences
+            WHERE shop_id = ?
+            ORDER BY ds_type
+            "#,
+        )
+        .bind(shop_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(sequences)
+    }
+
+    /// Create or update the numbering format of a document type; the counter is kept
+    /// Returns false, changing nothing, if the prefix or reset period would change after
+    /// numbers of the current period were issued
+    pub async fn save_settings(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        document_type: DocumentType,
+        prefix: &str,
+        reset: SequenceReset,
+        padding: i32,
+        date: NaiveDate,
+    ) -> Result<bool> {
+        let mut tx = db.begin().await?;
+
+        // Lock the row so no number is issued while the format changes
+        let current = sqlx::query_as::<_, DocumentSequence>(
+            r#"
+            SELECT shop_id, ds_type, ds_prefix, ds_reset, ds_padding, ds_period, ds_last_number, updated_at
+            FROM document_sequences
+            WHERE shop_id = ? AND ds_type = ?
+            FOR UPDATE
+            "#,
+        )
+        .bind(shop_id)
+        .bind(document_type)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(current) = current {
+            let format_changed = current.ds_prefix != prefix || current.ds_reset != reset;
+            if format_changed && !current.can_change_format(&date) {
+                return Ok(false);
+            }
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO document_sequences
+            (shop_id, ds_type, ds_prefix, ds_reset, ds_padding, ds_period, ds_last_number, updated_at)
+            VALUES (?, ?, ?, ?, ?, '', 0, NOW())
+            ON DUPLICATE KEY UPDATE
+                ds_prefix = VALUES(ds_prefix),
+                ds_reset = VALUES(ds_reset),
+                ds_padding = VALUES(ds_padding),
+                updated_at = NOW()
+            "#,
+        )
+        .bind(shop_id)
+        .bind(document_type)
+        .bind(prefix)
+        .bind(reset)
+        .bind(padding)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Allocate the next document number inside the caller's transaction
+    /// The sequence row stays locked until the transaction ends, so numbers are unique, and a
+    /// rolled back document gives its number back, so there are no gaps
+    pub async fn next_number(
+        tx: &mut Transaction<'_, MySql>,
+        shop_id: i32,
+        document_type: DocumentType,
+        date: NaiveDate,
+    ) -> Result<String> {
+        // First document of this type for the shop
+        sqlx::query(
+            r#"
+            INSERT IGNORE INTO document_sequences
+            (shop_id, ds_type, ds_prefix, ds_reset, ds_padding, ds_period, ds_last_number, updated_at)
+            VALUES (?, ?, ?, ?, ?, '', 0, NOW())
+            "#,
+        )
+        .bind(shop_id)
+        .bind(document_type)
+        .bind(document_type.default_prefix())
+        .bind(document_type.default_reset())
+        .bind(DEFAULT_PADDING)
+        .execute(&mut **tx)
+        .await?;
+
+        let sequence = sqlx::query_as::<_, DocumentSequence>(
+            r#"
+            SELECT shop_id, ds_type, ds_prefix, ds_reset, ds_padding, ds_period, ds_last_number, updated_at
+            FROM document_sequences
+            WHERE shop_id = ? AND ds_type = ?
+            FOR UPDATE
+            "#,
+        )
+        .bind(shop_id)
+        .bind(document_type)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        let (period, number) = sequence.next_for(&date);
+
+        sqlx::query(
+            r#"
+            UPDATE document_sequences
+            SET ds_period = ?, ds_last_number = ?, updated_at = NOW()
+            WHERE shop_id = ? AND ds_type = ?
+            "#,
+        )
+        .bind(&period)
+        .bind(number)
+        .bind(shop_id)
+        .bind(document_type)
+        .execute(&mut **tx)
+        .await?;
+
+        let document_number = format_document_number(&sequence.ds_prefix, &period, sequence.ds_padding, number);
+
+        // The unique key on the issued numbers fails the transaction rather than reissue one
+        sqlx::query(
+            r#"
+            INSERT INTO issued_document_numbers
+            (shop_id, ds_type, idn_number, created_at)
+            VALUES (?, ?, ?, NOW())
+            "#,
+        )
+        .bind(shop_id)
+        .bind(document_type)
+        .bind(&document_number)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(document_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_periods() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 15).unwrap();
+        assert_eq!(SequenceReset::Never.period(&date), "");
+        assert_eq!(SequenceReset::Yearly.period(&date), "2569");
+        assert_eq!(SequenceReset::Monthly.period(&date), "256903");
+    }
+
+    #[test]
+    fn test_next_number_resets_with_period() {
+        let mut sequence = DocumentSequence::default_for(1, DocumentType::TaxInvoice);
+        let date = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        assert_eq!(sequence.preview(&date), "INV2569-00001");
+
+        sequence.ds_period = "2569".to_string();
+        sequence.ds_last_number = 41;
+        assert_eq!(sequence.preview(&date), "INV2569-00042");
+
+        // New Buddhist Era year starts again from 1
+        let next_year = NaiveDate::from_ymd_opt(2027, 1, 1).unwrap();
+        assert_eq!(sequence.next_for(&next_year), ("2570".to_string(), 1));
+    }
+
+    #[test]
+    fn test_format_is_fixed_once_the_period_has_numbers() {
+        let mut sequence = DocumentSequence::default_for(1, DocumentType::TaxInvoice);
+        let date = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        assert!(sequence.can_change_format(&date));
+
+        // INV2569-00001 is out: going monthly and back to yearly would issue it again
+        sequence.ds_period = "2569".to_string();
+        sequence.ds_last_number = 1;
+        assert!(!sequence.can_change_format(&date));
+
+        // Nothing issued yet in the new year
+        let next_year = NaiveDate::from_ymd_opt(2027, 1, 1).unwrap();
+        assert!(sequence.can_change_format(&next_year));
+
+        // Customer codes have a single period
+        let mut sequence = DocumentSequence::default_for(1, DocumentType::Customer);
+        sequence.ds_last_number = 1;
+        assert!(!sequence.can_change_format(&next_year));
+    }
+
+    #[test]
+    fn test_customer_codes_never_reset() {
+        let mut sequence = DocumentSequence::default_for(1, DocumentType::Customer);
+        let date = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        assert_eq!(sequence.preview(&date), "HN00001");
+
+        sequence.ds_last_number = 41;
+        let next_year = NaiveDate::from_ymd_opt(2027, 1, 1).unwrap();
+        assert_eq!(sequence.preview(&next_year), "HN00042");
+    }
+
+    #[test]
+    fn test_format_document_number() {
+        assert_eq!(format_document_number("INV", "256910", 5, 42), "INV256910-00042");
+        assert_eq!(format_document_number("RC-", "", 6, 7), "RC-000007");
+        // Numbers wider than the padding are not truncated
+        assert_eq!(format_document_number("CN", "2569", 2, 1234), "CN2569-1234");
+    }
+}
@@ -0,0 +1,218 @@
+use sqlx::{FromRow, MySql, Pool};
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use crate::middlewares::{generate_random_string, hash_token};
+
+/// Email verification row (email_verifications)
+/// Timestamps are UTC
+#[derive(Debug, Clone, FromRow)]
+pub struct EmailVerification {
+    pub id: i32,
+    pub user_id: i32,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+impl EmailVerification {
+    /// Whether the token can still verify the email at `now`: not used yet and not expired
+    pub fn is_usable(&self, now: NaiveDateTime) -> bool {
+        self.used_at.is_none() && self.expires_at > now
+    }
+}
+
+/// Newly issued verification token: `token` goes into the emailed link, only `token_hash` is stored
+#[derive(Debug, Clone)]
+pub struct NewEmailVerification {
+    pub token: String,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+}
+
+impl NewEmailVerification {
+    pub fn generate(token_length: usize, expires_at: NaiveDateTime) -> Self {
+        let token = generate_random_string(token_length);
+        let token_hash = hash_token(&token);
+        NewEmailVerification {
+            token,
+            token_hash,
+            expires_at,
+        }
+    }
+}
+
+/// Email verification token model
+/// Only the SHA-256 hash of the token is stored. A user with an unused row has
+/// not verified their email yet; users created before registration existed have none.
+pub struct EmailVerificationModel;
+
+impl EmailVerificationModel {
+    /// Store a new verification token hash, replacing outstanding tokens for the user
+    pub async fn create_verification(
+        db: &Pool<MySql>,
+        user_id: i32,
+        token_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM email_verifications
+            WHERE user_id = ? AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO email_verifications (user_id, token_hash, expires_at, created_at)
+            VALUES (?, ?, ?, NOW())
+            "#,
+        )
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Consume a verification token
+    /// Returns the user_id if the token was valid, unused and not expired
+    pub async fn consume_verification(
+        db: &Pool<MySql>,
+        token_hash: &str,
+    ) -> Result<Option<i32>> {
+        let mut tx = db.begin().await?;
+
+        // Locked so two requests racing with the same token cannot both use it
+        let verification = sqlx::query_as::<_, EmailVerification>(
+            r#"
+            SELECT id, user_id, expires_at, used_at
+            FROM email_verifications
+            WHERE token_hash = ?
+            FOR UPDATE
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(verification) = verification.filter(|v| v.is_usable(Utc::now().naive_utc())) else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE email_verifications
+            SET used_at = UTC_TIMESTAMP()
+            WHERE id = ?
+            "#,
+        )
+        .bind(verification.id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(verification.user_id))
+    }
+
+    /// Check whether the user still has to verify their email
+    pub async fn is_pending(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM email_verifications
+            WHERE user_id = ? AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(db)
+        .await?;
+
+        Ok(count > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::{send_account_exists_email, send_verification_email, FakeMailSender};
+    use chrono::NaiveDate;
+
+    fn at(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verification_link_carries_token_and_only_hash_is_stored() {
+        let mailer = FakeMailSender::default();
+        let verification = NewEmailVerification::generate(48, at(11, 0));
+
+        send_verification_email(&mailer, "new@example.com", &verification.token)
+            .await
+            .unwrap();
+
+        let sent = mailer.sent.lock().unwrap();
+        let link_token = sent[0]
+            .body
+            .split("token=")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap();
+
+        // The link's token is what verify_email hashes and looks up
+        assert_eq!(link_token, verification.token);
+        assert_eq!(hash_token(link_token), verification.token_hash);
+        assert!(!sent[0].body.contains(&verification.token_hash));
+    }
+
+    #[tokio::test]
+    async fn test_registering_a_taken_email_sends_no_verification_link() {
+        let mailer = FakeMailSender::default();
+
+        send_account_exists_email(&mailer, "taken@example.com").await.unwrap();
+
+        // The owner is told; the registrant gets nothing that verifies or signs in
+        let sent = mailer.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "taken@example.com");
+        assert_eq!(sent[0].subject, "Registration attempt - APSTH Clinic");
+        assert!(!sent[0].body.contains("token="));
+        assert!(!sent[0].body.contains("/auth/verify-email"));
+    }
+
+    #[test]
+    fn test_verification_token_is_single_use() {
+        let verification = EmailVerification {
+            id: 1,
+            user_id: 7,
+            expires_at: at(11, 0),
+            used_at: None,
+        };
+        assert!(verification.is_usable(at(10, 30)));
+
+        let used = EmailVerification {
+            used_at: Some(at(10, 31)),
+            ..verification
+        };
+        assert!(!used.is_usable(at(10, 32)));
+    }
+
+    #[test]
+    fn test_verification_token_expires() {
+        let verification = EmailVerification {
+            id: 1,
+            user_id: 7,
+            expires_at: at(11, 0),
+            used_at: None,
+        };
+        assert!(verification.is_usable(at(10, 59)));
+        assert!(!verification.is_usable(at(11, 0)));
+    }
+}
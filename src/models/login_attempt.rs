@@ -0,0 +1,58 @@
+use sqlx::{MySql, Pool};
+use anyhow::Result;
+
+/// Login attempt audit model (stored in the logging database)
+/// Every attempt is recorded, including ones for unknown usernames
+pub struct LoginAttemptModel;
+
+impl LoginAttemptModel {
+    /// Record a login attempt
+    pub async fn record_attempt(
+        db: &Pool<MySql>,
+        user_id: Option<i32>,
+        username: &str,
+        ip_address: &str,
+        success: bool,
+        failure_reason: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO login_attempts
+            (user_id, username, ip_address, la_success, la_failure_reason, created_at)
+            VALUES (?, ?, ?, ?, ?, UTC_TIMESTAMP())
+            "#,
+        )
+        .bind(user_id)
+        .bind(username)
+        .bind(ip_address)
+        .bind(success)
+        .bind(failure_reason)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Count failed attempts from an IP address within the last window_minutes
+    pub async fn count_recent_ip_failures(
+        db: &Pool<MySql>,
+        ip_address: &str,
+        window_minutes: i64,
+    ) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM login_attempts
+            WHERE ip_address = ?
+                AND la_success = 0
+                AND created_at > UTC_TIMESTAMP() - INTERVAL ? MINUTE
+            "#,
+        )
+        .bind(ip_address)
+        .bind(window_minutes)
+        .fetch_one(db)
+        .await?;
+
+        Ok(count)
+    }
+}
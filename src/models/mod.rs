@@ -3,15 +3,41 @@
 
 pub mod user;
 pub mod order;
+pub mod order_status;
+pub mod payment;
+pub mod refund;
+pub mod tax_invoice;
 pub mod customer;
 pub mod product;
 pub mod category;
 pub mod shop;
+pub mod refresh_token;
+pub mod revoked_token;
+pub mod password_reset;
+pub mod email_verification;
+pub mod user_shop;
+pub mod permission;
+pub mod login_attempt;
+pub mod audit_log;
+pub mod document_sequence;
 
 // Re-export commonly used models
-pub use user::{User, UserModel};
-pub use order::{Order, OrderModel};
-pub use customer::{Customer, CustomerModel};
+pub use user::{User, UserShop, UserAuthStatus, UserLoginLock, UserModel};
+pub use order::{Order, OrderLine, OrderSearch, NewOrder, NewOrderLine, OrderModel};
+pub use order_status::{OrderStatus, OrderStatusModel};
+pub use payment::{Payment, PaymentMethod, PaymentSummary, NewTender, PaymentModel, allocate_tenders};
+pub use refund::{Refund, RefundLine, NewRefund, RefundRequestLine, RefundModel, allocate_refund, is_full_refund, void_refund_items};
+pub use tax_invoice::{TaxInvoice, NewTaxInvoice, TaxInvoiceModel};
+pub use customer::{Customer, CustomerFields, CustomerSearch, CustomerMerge, DuplicateMatch, DuplicateScan, MatchReason, CustomerModel, find_duplicates_of};
 pub use product::{Product, ProductModel};
 pub use category::{Category, CategoryModel};
 pub use shop::{Shop, ShopModel};
+pub use refresh_token::RefreshTokenModel;
+pub use revoked_token::RevokedTokenModel;
+pub use password_reset::{NewPasswordReset, PasswordResetModel};
+pub use email_verification::{EmailVerificationModel, NewEmailVerification};
+pub use user_shop::{ShopInvite, UserShopModel};
+pub use permission::PermissionModel;
+pub use login_attempt::LoginAttemptModel;
+pub use audit_log::AuditLogModel;
+pub use document_sequence::{DocumentSequence, DocumentType, SequenceReset, DocumentSequenceModel};
@@ -1,7 +1,10 @@
-use sqlx::{FromRow, MySql, Pool};
+use sqlx::{FromRow, MySql, Pool, QueryBuilder};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
+use super::order_status::{OrderStatus, OrderStatusModel};
+use super::document_sequence::{DocumentSequenceModel, DocumentType};
+use crate::libs::{thailand_today, Money, VatBreakdown, VatType};
 
 /// Order database model
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -11,19 +14,103 @@ pub struct Order {
     pub customer_id: i32,
     pub order_code: String,
     pub order_date: NaiveDateTime,
-    pub order_total: f64,
-    pub order_discount: f64,
-    pub order_net: f64,
-    pub order_status: i8,
+    pub order_total: Money,
+    pub order_discount: Money,
+    pub order_net: Money,
+    pub order_vatable: Money,
+    pub order_vat: Money,
+    pub order_exempt: Money,
+    pub order_status: OrderStatus,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub deleted_by: Option<i32>,
+    pub delete_reason: Option<String>,
+    pub discount_approved_by: Option<i32>,
+}
+
+/// Order line item database model (order_items joined with products and refund totals)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OrderLine {
+    pub id: i32,
+    pub order_id: i32,
+    pub product_id: i32,
+    pub product_name: String,
+    pub oi_quantity: i32,
+    pub oi_price: Money,
+    pub oi_discount: Money,
+    pub oi_total: Money,
+    pub oi_vat_type: VatType,
+    pub oi_vat: Money,
+    pub oi_net: Money,
+    pub oi_refunded_quantity: i64,
+    pub oi_refunded_amount: Money,
+}
+
+/// Order search filters
+/// sort_column must come from a fixed whitelist since it is pushed into the SQL as-is
+#[derive(Debug, Clone)]
+pub struct OrderSearch {
+    pub shop_id: i32,
+    pub customer_id: Option<i32>,
+    pub status: Option<OrderStatus>,
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    pub order_code_prefix: Option<String>,
+    pub min_net: Option<Money>,
+    pub max_net: Option<Money>,
+    pub sort_column: &'static str,
+    pub sort_desc: bool,
+    pub include_deleted: bool,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Order header to insert; the order code comes from the shop's order sequence
+#[derive(Debug, Clone)]
+pub struct NewOrder {
+    pub shop_id: i32,
+    pub customer_id: i32,
+    pub created_by: i32,
+    pub total: Money,
+    pub discount: Money,
+    /// Amount payable: total - discount, plus VAT on VAT-exclusive items
+    pub net: Money,
+    pub vat: VatBreakdown,
+    /// Manager who approved a discount above the staff member's cap
+    pub discount_approved_by: Option<i32>,
+}
+
+/// Line item to insert with a new order; price and VAT type come from the product, not the client
+/// vat/net are the line's share of the order VAT and its payable amount after the bill discount
+#[derive(Debug, Clone)]
+pub struct NewOrderLine {
+    pub product_id: i32,
+    pub quantity: i32,
+    pub price: Money,
+    pub discount: Money,
+    pub vat_type: VatType,
+    pub vat: Money,
+    pub net: Money,
+}
+
+impl NewOrderLine {
+    /// Price times quantity, before the item discount
+    pub fn gross(&self) -> Money {
+        self.price * self.quantity
+    }
+
+    /// Line total after the item discount
+    pub fn total(&self) -> Money {
+        self.gross() - self.discount
+    }
 }
 
 /// Order model with database operations
 pub struct OrderModel;
 
 impl OrderModel {
-    /// Get order by ID
+    /// Get order by ID (soft-deleted orders are excluded)
     pub async fn get_order_by_id(
         db: &Pool<MySql>,
         order_id: i32,
@@ -33,7 +120,7 @@ impl OrderModel {
             r#"
             SELECT *
             FROM orders
-            WHERE id = ? AND shop_id = ?
+            WHERE id = ? AND shop_id = ? AND deleted_at IS NULL
             "#,
         )
         .bind(order_id)
@@ -45,114 +132,167 @@ impl OrderModel {
     }
 
     /// Search orders with filters
+    /// Returns one page of orders and the total number of matches
     pub async fn search_orders(
         db: &Pool<MySql>,
-        shop_id: i32,
-        customer_id: Option<i32>,
-        status: Option<i8>,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<Order>> {
-        let mut query = String::from(
-            "SELECT * FROM orders WHERE shop_id = ?"
-        );
-
-        let mut bindings: Vec<Box<dyn sqlx::Encode<'_, MySql> + Send>> = vec![
-            Box::new(shop_id)
-        ];
-
-        if let Some(cid) = customer_id {
-            query.push_str(" AND customer_id = ?");
-            bindings.push(Box::new(cid));
-        }
+        search: &OrderSearch,
+    ) -> Result<(Vec<Order>, i64)> {
+        let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM orders");
+        push_search_filters(&mut count_query, search);
 
-        if let Some(s) = status {
-            query.push_str(" AND order_status = ?");
-            bindings.push(Box::new(s));
-        }
+        let total: i64 = count_query
+            .build_query_scalar()
+            .fetch_one(db)
+            .await?;
 
-        query.push_str(" ORDER BY created_at DESC LIMIT ? OFFSET ?");
+        let mut query = QueryBuilder::new("SELECT * FROM orders");
+        push_search_filters(&mut query, search);
+        push_search_order(&mut query, search);
 
-        let orders = sqlx::query_as::<_, Order>(&query)
-            .bind(shop_id)
-            .bind(limit)
-            .bind(offset)
+        let orders = query
+            .build_query_as::<Order>()
             .fetch_all(db)
             .await?;
 
-        Ok(orders)
+        Ok((orders, total))
+    }
+
+    /// Get line items of an order
+    pub async fn get_order_items(
+        db: &Pool<MySql>,
+        order_id: i32,
+    ) -> Result<Vec<OrderLine>> {
+        let items = sqlx::query_as::<_, OrderLine>(
+            r#"
+            SELECT
+                order_items.id,
+                order_items.order_id,
+                order_items.product_id,
+                products.product_name,
+                order_items.oi_quantity,
+                order_items.oi_price,
+                order_items.oi_discount,
+                order_items.oi_total,
+                order_items.oi_vat_type,
+                order_items.oi_vat,
+                order_items.oi_net,
+                CAST(COALESCE(SUM(refund_items.ri_quantity), 0) AS SIGNED) AS oi_refunded_quantity,
+                COALESCE(SUM(refund_items.ri_amount), 0) AS oi_refunded_amount
+            FROM order_items
+            JOIN products ON order_items.product_id = products.id
+            LEFT JOIN refund_items ON refund_items.order_item_id = order_items.id
+            WHERE order_items.order_id = ?
+            GROUP BY order_items.id
+            ORDER BY order_items.id
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
     }
 
-    /// Create new order
+    /// Create new order with its line items in a single transaction
     pub async fn create_order(
         db: &Pool<MySql>,
-        shop_id: i32,
-        customer_id: i32,
-        order_code: &str,
-        total: f64,
-        discount: f64,
-        net: f64,
+        order: &NewOrder,
+        items: &[NewOrderLine],
     ) -> Result<i32> {
+        let mut tx = db.begin().await?;
+
+        let order_code = DocumentSequenceModel::next_number(&mut tx, order.shop_id, DocumentType::Order, thailand_today()).await?;
+
         let result = sqlx::query(
             r#"
             INSERT INTO orders
-            (shop_id, customer_id, order_code, order_date, order_total, order_discount, order_net, order_status)
-            VALUES (?, ?, ?, NOW(), ?, ?, ?, 1)
+            (shop_id, customer_id, order_code, order_date, order_total, order_discount, order_net,
+             order_vatable, order_vat, order_exempt, order_status, discount_approved_by)
+            VALUES (?, ?, ?, NOW(), ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind(shop_id)
-        .bind(customer_id)
-        .bind(order_code)
-        .bind(total)
-        .bind(discount)
-        .bind(net)
-        .execute(db)
+        .bind(order.shop_id)
+        .bind(order.customer_id)
+        .bind(&order_code)
+        .bind(order.total)
+        .bind(order.discount)
+        .bind(order.net)
+        .bind(order.vat.vatable)
+        .bind(order.vat.vat)
+        .bind(order.vat.exempt)
+        .bind(OrderStatus::Draft)
+        .bind(order.discount_approved_by)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(result.last_insert_id() as i32)
+        let order_id = result.last_insert_id() as i32;
+
+        OrderStatusModel::record_change(&mut tx, order_id, None, OrderStatus::Draft, order.created_by, None).await?;
+
+        for item in items {
+            sqlx::query(
+                r#"
+                INSERT INTO order_items
+                (order_id, product_id, oi_quantity, oi_price, oi_discount, oi_total, oi_vat_type, oi_vat, oi_net)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(order_id)
+            .bind(item.product_id)
+            .bind(item.quantity)
+            .bind(item.price)
+            .bind(item.discount)
+            .bind(item.total())
+            .bind(item.vat_type)
+            .bind(item.vat)
+            .bind(item.net)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(order_id)
     }
 
-    /// Update order
-    pub async fn update_order(
+    /// Soft delete order
+    /// Returns false if the order does not exist or is already deleted
+    pub async fn delete_order(
         db: &Pool<MySql>,
         order_id: i32,
-        total: f64,
-        discount: f64,
-        net: f64,
-        status: i8,
-    ) -> Result<()> {
-        sqlx::query(
+        shop_id: i32,
+        deleted_by: i32,
+        reason: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query(
             r#"
             UPDATE orders
-            SET order_total = ?,
-                order_discount = ?,
-                order_net = ?,
-                order_status = ?,
-                updated_at = NOW()
-            WHERE id = ?
+            SET deleted_at = NOW(), deleted_by = ?, delete_reason = ?
+            WHERE id = ? AND shop_id = ? AND deleted_at IS NULL
             "#,
         )
-        .bind(total)
-        .bind(discount)
-        .bind(net)
-        .bind(status)
+        .bind(deleted_by)
+        .bind(reason)
         .bind(order_id)
+        .bind(shop_id)
         .execute(db)
         .await?;
 
-        Ok(())
+        Ok(result.rows_affected() == 1)
     }
 
-    /// Delete order
-    pub async fn delete_order(
+    /// Restore a soft-deleted order
+    /// Returns false if the order does not exist or is not deleted
+    pub async fn restore_order(
         db: &Pool<MySql>,
         order_id: i32,
         shop_id: i32,
-    ) -> Result<()> {
-        sqlx::query(
+    ) -> Result<bool> {
+        let result = sqlx::query(
             r#"
-            DELETE FROM orders
-            WHERE id = ? AND shop_id = ?
+            UPDATE orders
+            SET deleted_at = NULL, deleted_by = NULL, delete_reason = NULL, updated_at = NOW()
+            WHERE id = ? AND shop_id = ? AND deleted_at IS NOT NULL
             "#,
         )
         .bind(order_id)
@@ -160,6 +300,134 @@ impl OrderModel {
         .execute(db)
         .await?;
 
-        Ok(())
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+/// Append WHERE clause for an order search
+fn push_search_filters(query: &mut QueryBuilder<'_, MySql>, search: &OrderSearch) {
+    query.push(" WHERE shop_id = ").push_bind(search.shop_id);
+
+    if !search.include_deleted {
+        query.push(" AND deleted_at IS NULL");
+    }
+
+    if let Some(customer_id) = search.customer_id {
+        query.push(" AND customer_id = ").push_bind(customer_id);
+    }
+
+    if let Some(status) = search.status {
+        query.push(" AND order_status = ").push_bind(status);
+    }
+
+    if let Some(date_from) = search.date_from {
+        query.push(" AND order_date >= ").push_bind(date_from);
+    }
+
+    // date_to is inclusive, so compare against the start of the next day
+    if let Some(date_to) = search.date_to.and_then(|d| d.succ_opt()) {
+        query.push(" AND order_date < ").push_bind(date_to);
+    }
+
+    if let Some(prefix) = search.order_code_prefix.as_deref().filter(|p| !p.is_empty()) {
+        query
+            .push(" AND order_code LIKE ")
+            .push_bind(format!("{}%", escape_like(prefix)));
+    }
+
+    if let Some(min_net) = search.min_net {
+        query.push(" AND order_net >= ").push_bind(min_net);
+    }
+
+    if let Some(max_net) = search.max_net {
+        query.push(" AND order_net <= ").push_bind(max_net);
+    }
+}
+
+/// Append ORDER BY and LIMIT for an order search
+fn push_search_order(query: &mut QueryBuilder<'_, MySql>, search: &OrderSearch) {
+    query
+        .push(" ORDER BY ")
+        .push(search.sort_column)
+        .push(if search.sort_desc { " DESC" } else { " ASC" })
+        // Tie-break on id so pages are stable
+        .push(", id DESC")
+        .push(" LIMIT ")
+        .push_bind(search.limit)
+        .push(" OFFSET ")
+        .push_bind(search.offset);
+}
+
+/// Escape LIKE wildcards so user input only matches literally
+pub(crate) fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search() -> OrderSearch {
+        OrderSearch {
+            shop_id: 1,
+            customer_id: None,
+            status: None,
+            date_from: None,
+            date_to: None,
+            order_code_prefix: None,
+            min_net: None,
+            max_net: None,
+            sort_column: "order_date",
+            sort_desc: true,
+            include_deleted: false,
+            limit: 20,
+            offset: 0,
+        }
+    }
+
+    #[test]
+    fn test_search_query_binds_only_given_filters() {
+        let mut query = QueryBuilder::new("SELECT * FROM orders");
+        push_search_filters(&mut query, &search());
+        push_search_order(&mut query, &search());
+
+        assert_eq!(
+            query.sql(),
+            "SELECT * FROM orders WHERE shop_id = ? AND deleted_at IS NULL ORDER BY order_date DESC, id DESC LIMIT ? OFFSET ?"
+        );
+    }
+
+    #[test]
+    fn test_search_query_with_all_filters() {
+        let filters = OrderSearch {
+            customer_id: Some(5),
+            status: Some(OrderStatus::Confirmed),
+            date_from: NaiveDate::from_ymd_opt(2024, 1, 1),
+            date_to: NaiveDate::from_ymd_opt(2024, 1, 31),
+            order_code_prefix: Some("ORD-1".to_string()),
+            min_net: Some(Money::from_baht(100)),
+            max_net: Some(Money::from_baht(500)),
+            include_deleted: true,
+            ..search()
+        };
+
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM orders");
+        push_search_filters(&mut query, &filters);
+
+        assert_eq!(
+            query.sql(),
+            "SELECT COUNT(*) FROM orders WHERE shop_id = ? AND customer_id = ? AND order_status = ? \
+             AND order_date >= ? AND order_date < ? AND order_code LIKE ? \
+             AND order_net >= ? AND order_net <= ?"
+        );
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("ORD-1"), "ORD-1");
+        assert_eq!(escape_like("50%_off"), "50\\%\\_off");
     }
 }
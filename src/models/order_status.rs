@@ -0,0 +1,191 @@
+use sqlx::{FromRow, MySql, Pool};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+
+/// Order status stored in orders.order_status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[repr(i8)]
+pub enum OrderStatus {
+    Draft = 1,
+    Confirmed = 2,
+    Paid = 3,
+    PartiallyPaid = 4,
+    Cancelled = 5,
+    Refunded = 6,
+}
+
+impl OrderStatus {
+    /// Whether an order may move from this status to `next`
+    /// Draft -> PartiallyPaid is a deposit taken before the order is confirmed
+    pub fn can_transition_to(self, next: OrderStatus) -> bool {
+        use OrderStatus::*;
+
+        matches!(
+            (self, next),
+            (Draft, Confirmed)
+                | (Draft, PartiallyPaid)
+                | (Draft, Cancelled)
+                | (Confirmed, PartiallyPaid)
+                | (Confirmed, Paid)
+                | (Confirmed, Cancelled)
+                | (PartiallyPaid, Paid)
+                | (PartiallyPaid, Cancelled)
+                | (PartiallyPaid, Refunded)
+                | (Paid, Cancelled)
+                | (Paid, Refunded)
+        )
+    }
+
+    /// Whether any payment has been taken on the order
+    pub fn has_payment(self) -> bool {
+        matches!(self, OrderStatus::Paid | OrderStatus::PartiallyPaid)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrderStatus::Draft => "draft",
+            OrderStatus::Confirmed => "confirmed",
+            OrderStatus::Paid => "paid",
+            OrderStatus::PartiallyPaid => "partially_paid",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Refunded => "refunded",
+        }
+    }
+}
+
+/// Order status transition (order_status_history row)
+/// from_status is NULL for the status an order was created with
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OrderStatusChange {
+    pub id: i32,
+    pub order_id: i32,
+    pub osh_from_status: Option<OrderStatus>,
+    pub osh_to_status: OrderStatus,
+    pub osh_reason: Option<String>,
+    pub changed_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// Order status model with database operations
+pub struct OrderStatusModel;
+
+impl OrderStatusModel {
+    /// Move an order from one status to another and record the transition
+    /// Returns false if the order is no longer in `from` (changed concurrently)
+    pub async fn change_status(
+        db: &Pool<MySql>,
+        order_id: i32,
+        shop_id: i32,
+        from: OrderStatus,
+        to: OrderStatus,
+        changed_by: i32,
+        reason: Option<&str>,
+    ) -> Result<bool> {
+        let mut tx = db.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE orders
+            SET order_status = ?, updated_at = NOW()
+            WHERE id = ? AND shop_id = ? AND order_status = ?
+            "#,
+        )
+        .bind(to)
+        .bind(order_id)
+        .bind(shop_id)
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        Self::record_change(&mut tx, order_id, Some(from), to, changed_by, reason).await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Insert a history row inside an existing transaction
+    pub async fn record_change(
+        tx: &mut sqlx::Transaction<'_, MySql>,
+        order_id: i32,
+        from: Option<OrderStatus>,
+        to: OrderStatus,
+        changed_by: i32,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO order_status_history
+            (order_id, osh_from_status, osh_to_status, osh_reason, changed_by, created_at)
+            VALUES (?, ?, ?, ?, ?, NOW())
+            "#,
+        )
+        .bind(order_id)
+        .bind(from)
+        .bind(to)
+        .bind(reason)
+        .bind(changed_by)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the status history of an order, oldest first
+    pub async fn get_history(
+        db: &Pool<MySql>,
+        order_id: i32,
+    ) -> Result<Vec<OrderStatusChange>> {
+        let history = sqlx::query_as::<_, OrderStatusChange>(
+            r#"
+            SELECT id, order_id, osh_from_status, osh_to_status, osh_reason, changed_by, created_at
+            FROM order_status_history
+            WHERE order_id = ?
+            ORDER BY id
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_status_transitions() {
+        use OrderStatus::*;
+
+        assert!(Draft.can_transition_to(Confirmed));
+        assert!(Draft.can_transition_to(PartiallyPaid));
+        assert!(Confirmed.can_transition_to(Paid));
+        assert!(PartiallyPaid.can_transition_to(Paid));
+        assert!(Paid.can_transition_to(Refunded));
+        assert!(Paid.can_transition_to(Cancelled));
+
+        assert!(!Draft.can_transition_to(Paid));
+        assert!(!Paid.can_transition_to(Draft));
+        assert!(!Cancelled.can_transition_to(Confirmed));
+        assert!(!Refunded.can_transition_to(Paid));
+        assert!(!Confirmed.can_transition_to(Confirmed));
+    }
+
+    #[test]
+    fn test_order_status_serialization() {
+        assert_eq!(serde_json::to_string(&OrderStatus::PartiallyPaid).unwrap(), "\"partially_paid\"");
+        assert_eq!(
+            serde_json::from_str::<OrderStatus>("\"cancelled\"").unwrap(),
+            OrderStatus::Cancelled
+        );
+    }
+}
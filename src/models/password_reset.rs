@@ -0,0 +1,198 @@
+use sqlx::{FromRow, MySql, Pool};
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use crate::middlewares::{generate_random_string, hash_token};
+
+/// Password reset row (password_resets)
+/// Timestamps are UTC
+#[derive(Debug, Clone, FromRow)]
+pub struct PasswordReset {
+    pub id: i32,
+    pub user_id: i32,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+impl PasswordReset {
+    /// Whether the token can still reset the password at `now`: not used yet and not expired
+    pub fn is_usable(&self, now: NaiveDateTime) -> bool {
+        self.used_at.is_none() && self.expires_at > now
+    }
+}
+
+/// Newly issued reset token: `token` goes into the emailed link, only `token_hash` is stored
+#[derive(Debug, Clone)]
+pub struct NewPasswordReset {
+    pub token: String,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+}
+
+impl NewPasswordReset {
+    pub fn generate(token_length: usize, expires_at: NaiveDateTime) -> Self {
+        let token = generate_random_string(token_length);
+        let token_hash = hash_token(&token);
+        NewPasswordReset {
+            token,
+            token_hash,
+            expires_at,
+        }
+    }
+}
+
+/// Password reset token model
+/// Only the SHA-256 hash of the token is stored; each token is single-use
+pub struct PasswordResetModel;
+
+impl PasswordResetModel {
+    /// Store a new reset token hash, invalidating any outstanding tokens for the user
+    pub async fn create_reset(
+        db: &Pool<MySql>,
+        user_id: i32,
+        token_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE password_resets
+            SET used_at = UTC_TIMESTAMP()
+            WHERE user_id = ? AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO password_resets (user_id, token_hash, expires_at, created_at)
+            VALUES (?, ?, ?, NOW())
+            "#,
+        )
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Consume a reset token
+    /// Returns the user_id if the token was valid, unused and not expired
+    pub async fn consume_reset(
+        db: &Pool<MySql>,
+        token_hash: &str,
+    ) -> Result<Option<i32>> {
+        let mut tx = db.begin().await?;
+
+        // Locked so two requests racing with the same token cannot both use it
+        let reset = sqlx::query_as::<_, PasswordReset>(
+            r#"
+            SELECT id, user_id, expires_at, used_at
+            FROM password_resets
+            WHERE token_hash = ?
+            FOR UPDATE
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(reset) = reset.filter(|reset| reset.is_usable(Utc::now().naive_utc())) else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE password_resets
+            SET used_at = UTC_TIMESTAMP()
+            WHERE id = ?
+            "#,
+        )
+        .bind(reset.id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(reset.user_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::{send_password_reset_email, FakeMailSender};
+    use chrono::NaiveDate;
+
+    fn at(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reset_link_carries_token_and_only_hash_is_stored() {
+        let mailer = FakeMailSender::default();
+        let reset = NewPasswordReset::generate(48, at(11, 0));
+
+        send_password_reset_email(&mailer, "user@example.com", &reset.token)
+            .await
+            .unwrap();
+
+        let sent = mailer.sent.lock().unwrap();
+        let link_token = sent[0]
+            .body
+            .split("token=")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap();
+
+        // The link's token is what reset_password hashes and looks up
+        assert_eq!(link_token, reset.token);
+        assert_eq!(hash_token(link_token), reset.token_hash);
+        assert!(!sent[0].body.contains(&reset.token_hash));
+
+        // A leaked stored hash does not work as a token
+        assert_ne!(hash_token(&reset.token_hash), reset.token_hash);
+    }
+
+    #[test]
+    fn test_reset_tokens_are_unique() {
+        let a = NewPasswordReset::generate(48, at(11, 0));
+        let b = NewPasswordReset::generate(48, at(11, 0));
+        assert_eq!(a.token.len(), 48);
+        assert_ne!(a.token, b.token);
+        assert_ne!(a.token_hash, b.token_hash);
+    }
+
+    #[test]
+    fn test_reset_token_is_single_use() {
+        let reset = PasswordReset {
+            id: 1,
+            user_id: 7,
+            expires_at: at(11, 0),
+            used_at: None,
+        };
+        assert!(reset.is_usable(at(10, 30)));
+
+        // consume_reset sets used_at, as does requesting a newer token
+        let used = PasswordReset {
+            used_at: Some(at(10, 31)),
+            ..reset
+        };
+        assert!(!used.is_usable(at(10, 32)));
+    }
+
+    #[test]
+    fn test_reset_token_expires() {
+        let reset = PasswordReset {
+            id: 1,
+            user_id: 7,
+            expires_at: at(11, 0),
+            used_at: None,
+        };
+        assert!(reset.is_usable(at(10, 59)));
+        assert!(!reset.is_usable(at(11, 0)));
+        assert!(!reset.is_usable(at(12, 0)));
+    }
+}
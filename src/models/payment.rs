@@ -0,0 +1,370 @@
+use sqlx::{FromRow, MySql, Pool};
+use serde::{Deserialize, Serialize};
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use super::order::Order;
+use super::order_status::{OrderStatus, OrderStatusModel};
+use super::document_sequence::{DocumentSequenceModel, DocumentType};
+use crate::libs::{thailand_today, Money};
+
+/// Payment method stored in payments.payment_method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[repr(i8)]
+pub enum PaymentMethod {
+    Cash = 1,
+    Card = 2,
+    BankTransfer = 3,
+    PromptPay = 4,
+}
+
+impl PaymentMethod {
+    /// Label printed on receipts
+    pub fn label(self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "เงินสด / Cash",
+            PaymentMethod::Card => "บัตร / Card",
+            PaymentMethod::BankTransfer => "โอนเงิน / Bank transfer",
+            PaymentMethod::PromptPay => "พร้อมเพย์ / PromptPay",
+        }
+    }
+}
+
+/// Payment database model
+/// payment_amount is what was applied to the order; for cash, payment_tendered is what the
+/// customer handed over and payment_change what was given back. Tenders taken together share
+/// one receipt number.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Payment {
+    pub id: i32,
+    pub order_id: i32,
+    pub shop_id: i32,
+    pub payment_receipt_number: String,
+    pub payment_method: PaymentMethod,
+    pub payment_amount: Money,
+    pub payment_tendered: Money,
+    pub payment_change: Money,
+    pub payment_reference: Option<String>,
+    pub payment_is_deposit: bool,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// Tender offered by the customer (card slip, transfer, cash handed over, ...)
+#[derive(Debug, Clone)]
+pub struct NewTender {
+    pub method: PaymentMethod,
+    pub amount: Money,
+    pub reference: Option<String>,
+}
+
+/// Tender allocated against an order's balance, ready to insert
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tender {
+    pub method: PaymentMethod,
+    pub amount: Money,
+    pub tendered: Money,
+    pub change: Money,
+    pub reference: Option<String>,
+}
+
+/// Allocate tenders against the outstanding balance
+/// Non-cash tenders are taken at face value and may not exceed the balance. Cash is applied
+/// last and may exceed what is left, the difference being change. Less than the balance is
+/// a partial payment. An order with nothing to pay (e.g. fully discounted) is settled with a
+/// single tender of zero.
+pub fn allocate_tenders(tenders: &[NewTender], outstanding: Money) -> Result<Vec<Tender>> {
+    if tenders.is_empty() {
+        bail!("At least one tender is required");
+    }
+    // Checked before any arithmetic: DECIMAL(12,2) would round the stored amount
+    if tenders.iter().any(|t| t.amount != t.amount.round_satang()) {
+        bail!("Tender amounts must be in whole satang (at most 2 decimal places)");
+    }
+
+    if outstanding.is_zero() {
+        return match tenders {
+            [tender] if tender.amount.is_zero() => Ok(vec![Tender {
+                method: tender.method,
+                amount: Money::ZERO,
+                tendered: Money::ZERO,
+                change: Money::ZERO,
+                reference: tender.reference.clone(),
+            }]),
+            _ => bail!("Nothing is owed on this order; settle it with a single tender of 0"),
+        };
+    }
+
+    if tenders.iter().any(|t| t.amount <= Money::ZERO) {
+        bail!("Tender amounts must be greater than zero");
+    }
+    if tenders.iter().filter(|t| t.method == PaymentMethod::Cash).count() > 1 {
+        bail!("Only one cash tender is allowed per payment");
+    }
+
+    let mut remaining = outstanding;
+    let mut allocated = Vec::with_capacity(tenders.len());
+
+    for tender in tenders.iter().filter(|t| t.method != PaymentMethod::Cash) {
+        if tender.amount > remaining {
+            bail!("Payment exceeds the outstanding balance of {}", outstanding);
+        }
+        remaining -= tender.amount;
+        allocated.push(Tender {
+            method: tender.method,
+            amount: tender.amount,
+            tendered: tender.amount,
+            change: Money::ZERO,
+            reference: tender.reference.clone(),
+        });
+    }
+
+    if let Some(cash) = tenders.iter().find(|t| t.method == PaymentMethod::Cash) {
+        if remaining.is_zero() {
+            bail!("Balance is already covered, cash is not needed");
+        }
+        let applied = if cash.amount > remaining { remaining } else { cash.amount };
+        allocated.push(Tender {
+            method: PaymentMethod::Cash,
+            amount: applied,
+            tendered: cash.amount,
+            change: cash.amount - applied,
+            reference: cash.reference.clone(),
+        });
+    }
+
+    Ok(allocated)
+}
+
+/// Totals of an order's payments
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaymentSummary {
+    pub paid: Money,
+    pub deposit: Money,
+    pub outstanding: Money,
+}
+
+impl PaymentSummary {
+    pub fn new(order_net: Money, payments: &[Payment]) -> Self {
+        let paid: Money = payments.iter().map(|p| p.payment_amount).sum();
+        let deposit: Money = payments
+            .iter()
+            .filter(|p| p.payment_is_deposit)
+            .map(|p| p.payment_amount)
+            .sum();
+
+        PaymentSummary {
+            paid,
+            deposit,
+            outstanding: order_net - paid,
+        }
+    }
+}
+
+/// Payment model with database operations
+pub struct PaymentModel;
+
+impl PaymentModel {
+    /// Get the payments of an order, oldest first
+    pub async fn get_order_payments(
+        db: &Pool<MySql>,
+        order_id: i32,
+    ) -> Result<Vec<Payment>> {
+        let payments = sqlx::query_as::<_, Payment>(
+            r#"
+            SELECT id, order_id, shop_id, payment_receipt_number, payment_method, payment_amount, payment_tendered,
+                payment_change, payment_reference, payment_is_deposit, created_by, created_at
+            FROM payments
+            WHERE order_id = ?
+            ORDER BY id
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(payments)
+    }
+
+    /// Insert allocated tenders under a new receipt number and move the order to paid/partially
+    /// paid in one transaction
+    /// `paid_before` is the paid total the tenders were allocated against; returns None if the
+    /// order's status or payments changed in the meantime or the order may not move to the new
+    /// status, otherwise the new status and receipt number
+    pub async fn record_payments(
+        db: &Pool<MySql>,
+        order: &Order,
+        paid_before: Money,
+        tenders: &[Tender],
+        is_deposit: bool,
+        created_by: i32,
+    ) -> Result<Option<(OrderStatus, String)>> {
+        let mut tx = db.begin().await?;
+
+        // Lock the order so concurrent payments serialize on it
+        let status: Option<OrderStatus> = sqlx::query_scalar(
+            r#"
+            SELECT order_status
+            FROM orders
+            WHERE id = ? AND shop_id = ? AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+        )
+        .bind(order.id)
+        .bind(order.shop_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let paid: Money = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(payment_amount), 0)
+            FROM payments
+            WHERE order_id = ?
+            "#,
+        )
+        .bind(order.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if status != Some(order.order_status) || paid != paid_before {
+            return Ok(None);
+        }
+
+        let paid_after = paid + tenders.iter().map(|t| t.amount).sum();
+        let new_status = if paid_after >= order.order_net {
+            OrderStatus::Paid
+        } else {
+            OrderStatus::PartiallyPaid
+        };
+
+        // The payment must move the order along an allowed transition
+        if new_status != order.order_status && !order.order_status.can_transition_to(new_status) {
+            return Ok(None);
+        }
+
+        let receipt_number = DocumentSequenceModel::next_number(&mut tx, order.shop_id, DocumentType::Receipt, thailand_today()).await?;
+
+        for tender in tenders {
+            sqlx::query(
+                r#"
+                INSERT INTO payments
+                (order_id, shop_id, payment_receipt_number, payment_method, payment_amount, payment_tendered,
+                 payment_change, payment_reference, payment_is_deposit, created_by, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, NOW())
+                "#,
+            )
+            .bind(order.id)
+            .bind(order.shop_id)
+            .bind(&receipt_number)
+            .bind(tender.method)
+            .bind(tender.amount)
+            .bind(tender.tendered)
+            .bind(tender.change)
+            .bind(&tender.reference)
+            .bind(is_deposit)
+            .bind(created_by)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        if new_status != order.order_status {
+            sqlx::query(
+                r#"
+                UPDATE orders
+                SET order_status = ?, updated_at = NOW()
+                WHERE id = ?
+                "#,
+            )
+            .bind(new_status)
+            .bind(order.id)
+            .execute(&mut *tx)
+            .await?;
+
+            OrderStatusModel::record_change(&mut tx, order.id, Some(order.order_status), new_status, created_by, None).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some((new_status, receipt_number)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tender(method: PaymentMethod, baht: i64) -> NewTender {
+        NewTender {
+            method,
+            amount: Money::from_baht(baht),
+            reference: None,
+        }
+    }
+
+    #[test]
+    fn test_cash_change() {
+        let allocated = allocate_tenders(&[tender(PaymentMethod::Cash, 1000)], Money::from_satang(85050)).unwrap();
+        assert_eq!(allocated.len(), 1);
+        assert_eq!(allocated[0].amount, Money::from_satang(85050));
+        assert_eq!(allocated[0].tendered, Money::from_baht(1000));
+        assert_eq!(allocated[0].change, Money::from_satang(14950));
+    }
+
+    #[test]
+    fn test_split_and_partial_payment() {
+        // Card first, cash covers the rest with change
+        let allocated = allocate_tenders(
+            &[tender(PaymentMethod::Cash, 500), tender(PaymentMethod::Card, 700)],
+            Money::from_baht(1000),
+        )
+        .unwrap();
+        assert_eq!(allocated[0].method, PaymentMethod::Card);
+        assert_eq!(allocated[1].amount, Money::from_baht(300));
+        assert_eq!(allocated[1].change, Money::from_baht(200));
+
+        // Less than the balance is a partial payment
+        let allocated = allocate_tenders(&[tender(PaymentMethod::PromptPay, 400)], Money::from_baht(1000)).unwrap();
+        assert_eq!(allocated[0].amount, Money::from_baht(400));
+    }
+
+    #[test]
+    fn test_invalid_tenders() {
+        let outstanding = Money::from_baht(100);
+        assert!(allocate_tenders(&[], outstanding).is_err());
+        assert!(allocate_tenders(&[tender(PaymentMethod::Card, 0)], outstanding).is_err());
+        assert!(allocate_tenders(&[tender(PaymentMethod::BankTransfer, 101)], outstanding).is_err());
+        assert!(allocate_tenders(&[tender(PaymentMethod::Cash, 50), tender(PaymentMethod::Cash, 60)], outstanding).is_err());
+        assert!(allocate_tenders(&[tender(PaymentMethod::Card, 100), tender(PaymentMethod::Cash, 20)], outstanding).is_err());
+    }
+
+    #[test]
+    fn test_tenders_must_be_whole_satang() {
+        let card = NewTender {
+            method: PaymentMethod::Card,
+            amount: Money::new(rust_decimal_macros::dec!(99.995)),
+            reference: None,
+        };
+        // Would be stored as 100.00 while the order was left partially paid
+        assert!(allocate_tenders(&[card], Money::from_baht(100)).is_err());
+
+        let card = NewTender {
+            method: PaymentMethod::Card,
+            amount: Money::new(rust_decimal_macros::dec!(99.990)),
+            reference: None,
+        };
+        assert!(allocate_tenders(&[card], Money::from_baht(100)).is_ok());
+    }
+
+    #[test]
+    fn test_zero_balance_is_settled_with_a_zero_tender() {
+        let allocated = allocate_tenders(&[tender(PaymentMethod::Cash, 0)], Money::ZERO).unwrap();
+        assert_eq!(allocated.len(), 1);
+        assert_eq!(allocated[0].amount, Money::ZERO);
+        assert_eq!(allocated[0].change, Money::ZERO);
+
+        // Anything else on a zero balance, or a zero tender on a real balance, is refused
+        assert!(allocate_tenders(&[tender(PaymentMethod::Cash, 10)], Money::ZERO).is_err());
+        assert!(allocate_tenders(&[tender(PaymentMethod::Card, 0), tender(PaymentMethod::Cash, 0)], Money::ZERO).is_err());
+        assert!(allocate_tenders(&[tender(PaymentMethod::Cash, 0)], Money::from_baht(100)).is_err());
+    }
+}
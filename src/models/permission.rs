@@ -0,0 +1,28 @@
+use sqlx::{MySql, Pool};
+use anyhow::Result;
+
+/// Shop role permission model
+/// Permission codes look like "order.delete" and are granted per shop_role
+pub struct PermissionModel;
+
+impl PermissionModel {
+    /// Get all permission codes granted to a shop role
+    pub async fn get_shop_role_permissions(
+        db: &Pool<MySql>,
+        shop_role_id: i32,
+    ) -> Result<Vec<String>> {
+        let codes: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT permissions.permission_code
+            FROM shop_role_permissions
+            JOIN permissions ON shop_role_permissions.permission_id = permissions.id
+            WHERE shop_role_permissions.shop_role_id = ?
+            "#,
+        )
+        .bind(shop_role_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(codes)
+    }
+}
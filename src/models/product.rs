@@ -1,13 +1,15 @@
 use sqlx::{FromRow, MySql, Pool};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use crate::libs::{Money, VatType};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Product {
     pub id: i32,
     pub shop_id: i32,
     pub product_name: String,
-    pub product_price: f64,
+    pub product_price: Money,
+    pub product_vat_type: VatType,
     pub product_stock: i32,
 }
 
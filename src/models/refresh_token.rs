@@ -0,0 +1,107 @@
+use sqlx::{FromRow, MySql, Pool};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+
+/// Issued refresh token record (stored in the logging database)
+/// Tokens rotated from the same login share a family_id
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RefreshToken {
+    pub jti: String,
+    pub family_id: String,
+    pub user_id: i32,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// Refresh token model with database operations
+pub struct RefreshTokenModel;
+
+impl RefreshTokenModel {
+    /// Record a newly issued refresh token
+    pub async fn create_token(
+        db: &Pool<MySql>,
+        jti: &str,
+        family_id: &str,
+        user_id: i32,
+        expires_at: NaiveDateTime,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (jti, family_id, user_id, expires_at, created_at)
+            VALUES (?, ?, ?, ?, NOW())
+            "#,
+        )
+        .bind(jti)
+        .bind(family_id)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get refresh token record by jti
+    pub async fn get_token(
+        db: &Pool<MySql>,
+        jti: &str,
+    ) -> Result<Option<RefreshToken>> {
+        let token = sqlx::query_as::<_, RefreshToken>(
+            r#"
+            SELECT *
+            FROM refresh_tokens
+            WHERE jti = ?
+            "#,
+        )
+        .bind(jti)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Mark refresh token as used
+    /// Returns false if the token was already used or revoked
+    pub async fn mark_used(
+        db: &Pool<MySql>,
+        jti: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET used_at = NOW()
+            WHERE jti = ?
+                AND used_at IS NULL
+                AND revoked_at IS NULL
+            "#,
+        )
+        .bind(jti)
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Revoke every token in a family (used when reuse is detected)
+    pub async fn revoke_family(
+        db: &Pool<MySql>,
+        family_id: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = NOW()
+            WHERE family_id = ?
+                AND revoked_at IS NULL
+            "#,
+        )
+        .bind(family_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+}
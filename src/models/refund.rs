@@ -0,0 +1,452 @@
+use sqlx::{FromRow, MySql, Pool};
+use serde::{Deserialize, Serialize};
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use super::order::{Order, OrderLine};
+use super::order_status::{OrderStatus, OrderStatusModel};
+use super::payment::PaymentMethod;
+use super::document_sequence::{DocumentSequenceModel, DocumentType};
+use crate::libs::{thailand_today, Money};
+
+/// Refund database model; each refund is one credit note
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Refund {
+    pub id: i32,
+    pub order_id: i32,
+    pub shop_id: i32,
+    pub refund_number: String,
+    pub refund_method: PaymentMethod,
+    pub refund_amount: Money,
+    pub refund_reason: String,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// Refunded quantity of an order line (refund_items row)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RefundLine {
+    pub id: i32,
+    pub refund_id: i32,
+    pub order_item_id: i32,
+    pub ri_quantity: i32,
+    pub ri_amount: Money,
+    pub ri_restock: bool,
+}
+
+/// Credit note header to insert; the number comes from the shop's credit note sequence
+#[derive(Debug, Clone)]
+pub struct NewRefund {
+    pub method: PaymentMethod,
+    pub reason: String,
+    pub created_by: i32,
+}
+
+/// Requested refund of some units of an order line
+#[derive(Debug, Clone)]
+pub struct RefundRequestLine {
+    pub order_item_id: i32,
+    pub quantity: i32,
+    pub restock: bool,
+}
+
+/// Refund line priced against the order, ready to insert
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefundItem {
+    pub order_item_id: i32,
+    pub product_id: i32,
+    pub quantity: i32,
+    pub amount: Money,
+    pub restock: bool,
+}
+
+/// Price refund lines against an order
+/// A line is refunded at what was paid for it (oi_net: after its share of the bill discount,
+/// VAT included). Refunding the last units of a line returns whatever is left of that, so a
+/// line refunded in several parts adds up exactly.
+pub fn allocate_refund(lines: &[OrderLine], requests: &[RefundRequestLine]) -> Result<Vec<RefundItem>> {
+    if requests.is_empty() {
+        bail!("Nothing to refund");
+    }
+
+    let mut items = Vec::with_capacity(requests.len());
+
+    for request in requests {
+        let Some(line) = lines.iter().find(|l| l.id == request.order_item_id) else {
+            bail!("Order item {} not found in this order", request.order_item_id);
+        };
+        if requests.iter().filter(|r| r.order_item_id == request.order_item_id).count() > 1 {
+            bail!("Order item {} is listed more than once", request.order_item_id);
+        }
+
+        let remaining = i64::from(line.oi_quantity) - line.oi_refunded_quantity;
+        if request.quantity < 1 || i64::from(request.quantity) > remaining {
+            bail!("Order item {} has {} unit(s) left to refund", line.id, remaining);
+        }
+
+        let amount = if i64::from(request.quantity) == remaining {
+            line.oi_net - line.oi_refunded_amount
+        } else {
+            line.oi_net.prorate(Decimal::from(request.quantity), Decimal::from(line.oi_quantity))
+        };
+
+        items.push(RefundItem {
+            order_item_id: line.id,
+            product_id: line.product_id,
+            quantity: request.quantity,
+            amount,
+            restock: request.restock,
+        });
+    }
+
+    Ok(items)
+}
+
+/// Whether refunding `items` leaves nothing of the order unrefunded
+pub fn is_full_refund(lines: &[OrderLine], items: &[RefundItem]) -> bool {
+    lines.iter().all(|line| {
+        let refunding: i64 = items
+            .iter()
+            .filter(|i| i.order_item_id == line.id)
+            .map(|i| i64::from(i.quantity))
+            .sum();
+        line.oi_refunded_quantity + refunding >= i64::from(line.oi_quantity)
+    })
+}
+
+/// Price the credit note that voids an order: every unit not yet refunded goes back, and the
+/// money returned is `refundable` (what was received and not yet refunded), shared across the
+/// remaining lines by what is left of their net. A fully paid order gets each line back at
+/// what it cost; a deposit is spread over the lines it was paid against.
+pub fn void_refund_items(lines: &[OrderLine], refundable: Money, restock: bool) -> Result<Vec<RefundItem>> {
+    let remaining: Vec<&OrderLine> = lines
+        .iter()
+        .filter(|line| line.oi_refunded_quantity < i64::from(line.oi_quantity))
+        .collect();
+
+    if remaining.is_empty() {
+        bail!("Nothing is left on this order to void");
+    }
+
+    let weights: Vec<Money> = remaining
+        .iter()
+        .map(|line| line.oi_net - line.oi_refunded_amount)
+        .collect();
+    let amounts = if weights.iter().all(|w| w.is_zero()) {
+        // Nothing to share by (a zero-net order); keep the whole amount on the first line
+        let mut amounts = vec![Money::ZERO; weights.len()];
+        amounts[0] = refundable;
+        amounts
+    } else {
+        refundable.allocate(&weights)
+    };
+
+    Ok(remaining
+        .into_iter()
+        .zip(amounts)
+        .map(|(line, amount)| RefundItem {
+            order_item_id: line.id,
+            product_id: line.product_id,
+            quantity: line.oi_quantity - line.oi_refunded_quantity as i32,
+            amount,
+            restock,
+        })
+        .collect())
+}
+
+/// Refund model with database operations
+pub struct RefundModel;
+
+impl RefundModel {
+    /// Get the refunds (credit notes) of an order, oldest first
+    pub async fn get_order_refunds(
+        db: &Pool<MySql>,
+        order_id: i32,
+    ) -> Result<Vec<Refund>> {
+        let refunds = sqlx::query_as::<_, Refund>(
+            r#"
+            SELECT id, order_id, shop_id, refund_number, refund_method, refund_amount, refund_reason,
+                created_by, created_at
+            FROM refunds
+            WHERE order_id = ?
+            ORDER BY id
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(refunds)
+    }
+
+    /// Get the refunded lines of all refunds of an order
+    pub async fn get_order_refund_lines(
+        db: &Pool<MySql>,
+        order_id: i32,
+    ) -> Result<Vec<RefundLine>> {
+        let lines = sqlx::query_as::<_, RefundLine>(
+            r#"
+            SELECT refund_items.id, refund_items.refund_id, refund_items.order_item_id,
+                refund_items.ri_quantity, refund_items.ri_amount, refund_items.ri_restock
+            FROM refund_items
+            JOIN refunds ON refund_items.refund_id = refunds.id
+            WHERE refunds.order_id = ?
+            ORDER BY refund_items.id
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(lines)
+    }
+
+    /// Insert a refund with its lines, put restocked units back and move the order to
+    /// `new_status` (refunded once nothing is left, cancelled on a void), all in one transaction
+    /// `paid_before` and `refunded_before` are the totals the lines were priced against; returns
+    /// None if the order's status, payments or refunds changed in the meantime, otherwise the
+    /// refund ID
+    pub async fn create_refund(
+        db: &Pool<MySql>,
+        order: &Order,
+        paid_before: Money,
+        refunded_before: Money,
+        refund: &NewRefund,
+        items: &[RefundItem],
+        new_status: Option<OrderStatus>,
+    ) -> Result<Option<i32>> {
+        let mut tx = db.begin().await?;
+
+        // Lock the order so concurrent refunds serialize on it
+        let status: Option<OrderStatus> = sqlx::query_scalar(
+            r#"
+            SELECT order_status
+            FROM orders
+            WHERE id = ? AND shop_id = ? AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+        )
+        .bind(order.id)
+        .bind(order.shop_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let paid: Money = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(payment_amount), 0)
+            FROM payments
+            WHERE order_id = ?
+            "#,
+        )
+        .bind(order.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let refunded: Money = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(refund_amount), 0)
+            FROM refunds
+            WHERE order_id = ?
+            "#,
+        )
+        .bind(order.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if status != Some(order.order_status) || paid != paid_before || refunded != refunded_before {
+            return Ok(None);
+        }
+
+        let refund_number = DocumentSequenceModel::next_number(&mut tx, order.shop_id, DocumentType::CreditNote, thailand_today()).await?;
+        let amount: Money = items.iter().map(|i| i.amount).sum();
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO refunds
+            (order_id, shop_id, refund_number, refund_method, refund_amount, refund_reason, created_by, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, NOW())
+            "#,
+        )
+        .bind(order.id)
+        .bind(order.shop_id)
+        .bind(&refund_number)
+        .bind(refund.method)
+        .bind(amount)
+        .bind(&refund.reason)
+        .bind(refund.created_by)
+        .execute(&mut *tx)
+        .await?;
+
+        let refund_id = result.last_insert_id() as i32;
+
+        for item in items {
+            sqlx::query(
+                r#"
+                INSERT INTO refund_items
+                (refund_id, order_item_id, ri_quantity, ri_amount, ri_restock)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(refund_id)
+            .bind(item.order_item_id)
+            .bind(item.quantity)
+            .bind(item.amount)
+            .bind(item.restock)
+            .execute(&mut *tx)
+            .await?;
+
+            if item.restock {
+                sqlx::query(
+                    r#"
+                    UPDATE products
+                    SET product_stock = product_stock + ?
+                    WHERE id = ? AND shop_id = ?
+                    "#,
+                )
+                .bind(item.quantity)
+                .bind(item.product_id)
+                .bind(order.shop_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        if let Some(new_status) = new_status {
+            sqlx::query(
+                r#"
+                UPDATE orders
+                SET order_status = ?, updated_at = NOW()
+                WHERE id = ?
+                "#,
+            )
+            .bind(new_status)
+            .bind(order.id)
+            .execute(&mut *tx)
+            .await?;
+
+            OrderStatusModel::record_change(
+                &mut tx,
+                order.id,
+                Some(order.order_status),
+                new_status,
+                refund.created_by,
+                Some(&refund.reason),
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(refund_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::VatType;
+
+    fn line(id: i32, quantity: i32, total: Money, net: Money) -> OrderLine {
+        OrderLine {
+            id,
+            order_id: 1,
+            product_id: id * 10,
+            product_name: format!("Product {}", id),
+            oi_quantity: quantity,
+            oi_price: total.prorate(Decimal::ONE, Decimal::from(quantity)),
+            oi_discount: Money::ZERO,
+            oi_total: total,
+            oi_vat_type: VatType::Inclusive,
+            oi_vat: net.prorate(Decimal::from(7), Decimal::from(107)),
+            oi_net: net,
+            oi_refunded_quantity: 0,
+            oi_refunded_amount: Money::ZERO,
+        }
+    }
+
+    fn request(order_item_id: i32, quantity: i32) -> RefundRequestLine {
+        RefundRequestLine {
+            order_item_id,
+            quantity,
+            restock: true,
+        }
+    }
+
+    #[test]
+    fn test_refund_shares_bill_discount() {
+        // 300 + 100 with a 40 baht bill discount: net 360, lines carry 270 and 90
+        let lines = vec![
+            line(1, 3, Money::from_baht(300), Money::from_baht(270)),
+            line(2, 1, Money::from_baht(100), Money::from_baht(90)),
+        ];
+        let net = Money::from_baht(360);
+
+        let items = allocate_refund(&lines, &[request(1, 1)]).unwrap();
+        assert_eq!(items[0].amount, Money::from_baht(90));
+
+        let items = allocate_refund(&lines, &[request(1, 3), request(2, 1)]).unwrap();
+        let total: Money = items.iter().map(|i| i.amount).sum();
+        assert_eq!(total, net);
+        assert!(is_full_refund(&lines, &items));
+    }
+
+    #[test]
+    fn test_partial_refunds_add_up() {
+        let mut lines = vec![line(1, 3, Money::from_baht(100), Money::from_baht(100))];
+
+        let first = allocate_refund(&lines, &[request(1, 1)]).unwrap();
+        assert_eq!(first[0].amount, Money::from_satang(3333));
+        assert!(!is_full_refund(&lines, &first));
+
+        lines[0].oi_refunded_quantity = 1;
+        lines[0].oi_refunded_amount = first[0].amount;
+
+        let rest = allocate_refund(&lines, &[request(1, 2)]).unwrap();
+        assert_eq!(rest[0].amount, Money::from_satang(6667));
+        assert!(is_full_refund(&lines, &rest));
+    }
+
+    #[test]
+    fn test_invalid_refund_lines() {
+        let mut lines = vec![line(1, 2, Money::from_baht(100), Money::from_baht(100))];
+
+        assert!(allocate_refund(&lines, &[]).is_err());
+        assert!(allocate_refund(&lines, &[request(9, 1)]).is_err());
+        assert!(allocate_refund(&lines, &[request(1, 0)]).is_err());
+        assert!(allocate_refund(&lines, &[request(1, 3)]).is_err());
+        assert!(allocate_refund(&lines, &[request(1, 1), request(1, 1)]).is_err());
+
+        lines[0].oi_refunded_quantity = 2;
+        assert!(allocate_refund(&lines, &[request(1, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_void_returns_what_was_received() {
+        let mut lines = vec![
+            line(1, 3, Money::from_baht(300), Money::from_baht(270)),
+            line(2, 1, Money::from_baht(100), Money::from_baht(90)),
+        ];
+
+        // Fully paid: every line comes back at its net
+        let items = void_refund_items(&lines, Money::from_baht(360), true).unwrap();
+        assert_eq!(items.iter().map(|i| i.amount).collect::<Vec<_>>(), vec![Money::from_baht(270), Money::from_baht(90)]);
+        assert_eq!(items[0].quantity, 3);
+        assert!(is_full_refund(&lines, &items));
+
+        // A 100 baht deposit is spread over the lines and adds up exactly
+        let items = void_refund_items(&lines, Money::from_baht(100), false).unwrap();
+        let total: Money = items.iter().map(|i| i.amount).sum();
+        assert_eq!(total, Money::from_baht(100));
+        assert_eq!(items[0].amount, Money::from_satang(7500));
+
+        // Lines already refunded are left out
+        lines[1].oi_refunded_quantity = 1;
+        lines[1].oi_refunded_amount = Money::from_baht(90);
+        let items = void_refund_items(&lines, Money::from_baht(270), true).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].amount, Money::from_baht(270));
+
+        lines[0].oi_refunded_quantity = 3;
+        assert!(void_refund_items(&lines, Money::ZERO, true).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use sqlx::{MySql, Pool};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+
+/// Revoked token model (stored in the logging database)
+/// Rows can be purged once expires_at has passed, since the JWT itself is expired by then
+pub struct RevokedTokenModel;
+
+impl RevokedTokenModel {
+    /// Add a token jti to the revocation list
+    pub async fn revoke_token(
+        db: &Pool<MySql>,
+        jti: &str,
+        user_id: i32,
+        expires_at: NaiveDateTime,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT IGNORE INTO revoked_tokens (jti, user_id, expires_at, created_at)
+            VALUES (?, ?, ?, NOW())
+            "#,
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Check whether a token jti has been revoked
+    pub async fn is_revoked(
+        db: &Pool<MySql>,
+        jti: &str,
+    ) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM revoked_tokens
+            WHERE jti = ?
+            "#,
+        )
+        .bind(jti)
+        .fetch_one(db)
+        .await?;
+
+        Ok(count > 0)
+    }
+}
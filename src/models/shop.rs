@@ -2,12 +2,21 @@ use sqlx::{FromRow, MySql, Pool};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 
+/// Shop database model
+/// shop_tax_id/shop_branch_code are set once the shop is VAT registered and are required to
+/// issue full tax invoices; shop_logo_url and shop_printer_address are used for thermal receipts;
+/// shop_promptpay_id (mobile number, tax ID or e-wallet ID) receives PromptPay QR payments
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Shop {
     pub id: i32,
     pub shop_name: String,
     pub shop_address: Option<String>,
     pub shop_tel: Option<String>,
+    pub shop_tax_id: Option<String>,
+    pub shop_branch_code: Option<String>,
+    pub shop_logo_url: Option<String>,
+    pub shop_printer_address: Option<String>,
+    pub shop_promptpay_id: Option<String>,
 }
 
 pub struct ShopModel;
@@ -25,4 +34,71 @@ impl ShopModel {
         .await?;
         Ok(shop)
     }
+
+    /// Set the shop's VAT registration (tax ID and branch code)
+    pub async fn update_tax_info(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        tax_id: &str,
+        branch_code: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE shops
+            SET shop_tax_id = ?, shop_branch_code = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(tax_id)
+        .bind(branch_code)
+        .bind(shop_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Set the PromptPay ID customers pay to by QR
+    pub async fn update_promptpay_id(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        promptpay_id: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE shops
+            SET shop_promptpay_id = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(promptpay_id)
+        .bind(shop_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Set the receipt logo and the network printer thermal receipts are sent to
+    pub async fn update_receipt_printer(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        logo_url: Option<&str>,
+        printer_address: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE shops
+            SET shop_logo_url = ?, shop_printer_address = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(logo_url)
+        .bind(printer_address)
+        .bind(shop_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,116 @@
+use sqlx::{FromRow, MySql, Pool};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use super::order::Order;
+use super::order_status::OrderStatus;
+use super::document_sequence::{DocumentSequenceModel, DocumentType};
+use crate::libs::thailand_today;
+
+/// Full tax invoice issued for an order (at most one per order)
+/// Seller details are read from the shop when the invoice is printed; the buyer's are kept here
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TaxInvoice {
+    pub id: i32,
+    pub order_id: i32,
+    pub shop_id: i32,
+    pub ti_number: String,
+    pub ti_customer_name: String,
+    pub ti_customer_tax_id: String,
+    pub ti_customer_branch_code: String,
+    pub ti_customer_address: String,
+    pub issued_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// Buyer details for a new tax invoice
+#[derive(Debug, Clone)]
+pub struct NewTaxInvoice {
+    pub customer_name: String,
+    pub customer_tax_id: String,
+    pub customer_branch_code: String,
+    pub customer_address: String,
+    pub issued_by: i32,
+}
+
+/// Tax invoice model with database operations
+pub struct TaxInvoiceModel;
+
+impl TaxInvoiceModel {
+    /// Get the tax invoice of an order, if one was issued
+    pub async fn get_by_order(
+        db: &Pool<MySql>,
+        order_id: i32,
+    ) -> Result<Option<TaxInvoice>> {
+        let invoice = sqlx::query_as::<_, TaxInvoice>(
+            r#"
+            SELECT id, order_id, shop_id, ti_number, ti_customer_name, ti_customer_tax_id,
+                ti_customer_branch_code, ti_customer_address, issued_by, created_at
+            FROM tax_invoices
+            WHERE order_id = ?
+            "#,
+        )
+        .bind(order_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    /// Issue a tax invoice with the next number of the shop's tax invoice sequence
+    /// Returns None if the order already has one or is no longer paid
+    pub async fn create(
+        db: &Pool<MySql>,
+        order: &Order,
+        invoice: &NewTaxInvoice,
+    ) -> Result<Option<i32>> {
+        let mut tx = db.begin().await?;
+
+        // Lock the order so two requests cannot both issue an invoice, and so it cannot be
+        // refunded or cancelled while the invoice is issued
+        let status: Option<OrderStatus> = sqlx::query_scalar(
+            "SELECT order_status FROM orders WHERE id = ? AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(order.id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if status != Some(OrderStatus::Paid) {
+            return Ok(None);
+        }
+
+        let existing: Option<i32> = sqlx::query_scalar("SELECT id FROM tax_invoices WHERE order_id = ?")
+            .bind(order.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        if existing.is_some() {
+            return Ok(None);
+        }
+
+        let number = DocumentSequenceModel::next_number(&mut tx, order.shop_id, DocumentType::TaxInvoice, thailand_today()).await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO tax_invoices
+            (order_id, shop_id, ti_number, ti_customer_name, ti_customer_tax_id, ti_customer_branch_code,
+             ti_customer_address, issued_by, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, NOW())
+            "#,
+        )
+        .bind(order.id)
+        .bind(order.shop_id)
+        .bind(&number)
+        .bind(&invoice.customer_name)
+        .bind(&invoice.customer_tax_id)
+        .bind(&invoice.customer_branch_code)
+        .bind(&invoice.customer_address)
+        .bind(invoice.issued_by)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(result.last_insert_id() as i32))
+    }
+}
@@ -15,6 +15,7 @@ pub struct User {
     pub user_is_active: i8,
     pub user_otp_url: Option<String>,
     pub password_version: i32,
+    pub session_epoch: i32,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
@@ -34,6 +35,69 @@ pub struct UserWithShop {
     pub role_id: i32,
 }
 
+/// Shop membership with role and discount information
+/// Only accepted memberships (us_invite = 2) are loaded
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserShop {
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub shop_mother_id: i32,
+    pub shop_role_id: i32,
+    pub shop_role_name: String,
+    pub role_id: i32,
+    pub sr_discount_type_id: i32,
+    pub sr_discount: f32,
+}
+
+/// Fields needed to validate a live session
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserAuthStatus {
+    pub password_version: i32,
+    pub session_epoch: i32,
+    pub user_is_active: i8,
+}
+
+impl UserAuthStatus {
+    /// Whether a token issued with these versions is still valid; a password change or reset
+    /// bumps password_version and "log out everywhere" bumps session_epoch
+    pub fn accepts_session(&self, password_version: i32, session_epoch: i32) -> bool {
+        self.password_version == password_version && self.session_epoch == session_epoch
+    }
+}
+
+/// Failed login counter and lockout state for an account
+/// Timestamps are UTC
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserLoginLock {
+    pub user_failed_login_count: i32,
+    pub user_last_failed_login_at: Option<chrono::NaiveDateTime>,
+    pub user_locked_until: Option<chrono::NaiveDateTime>,
+}
+
+impl UserLoginLock {
+    /// Lock state after one more failed login at `now`, locking the account for
+    /// `lockout_minutes` once the counter reaches `max_attempts`
+    /// A lock that has expired starts the count again, so the next failure does not relock
+    pub fn after_failure(&self, now: chrono::NaiveDateTime, max_attempts: i32, lockout_minutes: i64) -> UserLoginLock {
+        let expired = self.user_locked_until.is_some_and(|until| until <= now);
+        let count = if expired { 1 } else { self.user_failed_login_count + 1 };
+
+        let locked_until = if count >= max_attempts {
+            Some(now + chrono::Duration::minutes(lockout_minutes))
+        } else if expired {
+            None
+        } else {
+            self.user_locked_until
+        };
+
+        UserLoginLock {
+            user_failed_login_count: count,
+            user_last_failed_login_at: Some(now),
+            user_locked_until: locked_until,
+        }
+    }
+}
+
 /// User model with database operations
 pub struct UserModel;
 
@@ -76,30 +140,6 @@ impl UserModel {
         Ok(user)
     }
 
-    /// Get user for login by username (email) and password hash
-    /// Equivalent to Go's GetUserForLogin function
-    pub async fn get_user_for_login(
-        db: &Pool<MySql>,
-        username: &str,
-        password_hash: &str,
-    ) -> Result<User> {
-        let user = sqlx::query_as::<_, User>(
-            r#"
-            SELECT *
-            FROM users
-            WHERE user_email = ?
-                AND user_password = ?
-                AND user_is_active = 1
-            "#,
-        )
-        .bind(username)
-        .bind(password_hash)
-        .fetch_one(db)
-        .await?;
-
-        Ok(user)
-    }
-
     /// Get user by email
     pub async fn get_user_by_email(
         db: &Pool<MySql>,
@@ -196,6 +236,175 @@ impl UserModel {
         Ok(())
     }
 
+    /// Replace a legacy password hash with a bcrypt hash
+    /// Does not bump password_version, so existing sessions stay valid
+    pub async fn upgrade_password_hash(
+        db: &Pool<MySql>,
+        user_id: i32,
+        new_password_hash: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET user_password = ?, updated_at = NOW()
+            WHERE id = ?
+            "#,
+        )
+        .bind(new_password_hash)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Increment session epoch to invalidate every token issued so far
+    /// Used for "log out all devices"
+    pub async fn increment_session_epoch(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET session_epoch = session_epoch + 1, updated_at = NOW()
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get failed login counter and lockout state
+    pub async fn get_login_lock(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<Option<UserLoginLock>> {
+        let lock = sqlx::query_as::<_, UserLoginLock>(
+            r#"
+            SELECT user_failed_login_count, user_last_failed_login_at, user_locked_until
+            FROM users
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(lock)
+    }
+
+    /// Count a failed login, locking the account once the counter reaches max_attempts
+    /// The row is locked while the counter is updated so concurrent failures are all counted
+    /// Returns the updated lock state
+    pub async fn register_failed_login(
+        db: &Pool<MySql>,
+        user_id: i32,
+        max_attempts: i32,
+        lockout_minutes: i64,
+    ) -> Result<UserLoginLock> {
+        let mut tx = db.begin().await?;
+
+        let lock = sqlx::query_as::<_, UserLoginLock>(
+            r#"
+            SELECT user_failed_login_count, user_last_failed_login_at, user_locked_until
+            FROM users
+            WHERE id = ?
+            FOR UPDATE
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("User {} not found", user_id))?;
+
+        let lock = lock.after_failure(chrono::Utc::now().naive_utc(), max_attempts, lockout_minutes);
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET user_failed_login_count = ?,
+                user_last_failed_login_at = ?,
+                user_locked_until = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(lock.user_failed_login_count)
+        .bind(lock.user_last_failed_login_at)
+        .bind(lock.user_locked_until)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(lock)
+    }
+
+    /// Clear the failed login counter and any lockout (successful login or admin unlock)
+    pub async fn reset_failed_logins(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET user_failed_login_count = 0,
+                user_last_failed_login_at = NULL,
+                user_locked_until = NULL
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the hashed approval PIN (managers approving discounts)
+    pub async fn get_user_pin_hash(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<Option<String>> {
+        let pin: Option<Option<String>> = sqlx::query_scalar(
+            r#"
+            SELECT user_pin
+            FROM users
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(pin.flatten())
+    }
+
+    /// Set the hashed approval PIN
+    pub async fn update_user_pin(
+        db: &Pool<MySql>,
+        user_id: i32,
+        pin_hash: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET user_pin = ?, updated_at = NOW()
+            WHERE id = ?
+            "#,
+        )
+        .bind(pin_hash)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
     /// Deactivate user
     pub async fn deactivate_user(
         db: &Pool<MySql>,
@@ -270,6 +479,149 @@ impl UserModel {
 
         Ok(())
     }
+
+    /// Get all shops the user has accepted membership in
+    pub async fn get_user_shops(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<Vec<UserShop>> {
+        let shops = sqlx::query_as::<_, UserShop>(
+            r#"
+            SELECT
+                user_shops.shop_id,
+                shops.shop_name,
+                shops.shop_mother_id,
+                user_shops.shop_role_id,
+                shop_roles.shop_role_name,
+                shop_roles.role_id,
+                shop_roles.sr_discount_type_id,
+                shop_roles.sr_discount
+            FROM user_shops
+            JOIN shops ON user_shops.shop_id = shops.id
+            JOIN shop_roles ON user_shops.shop_role_id = shop_roles.id
+            WHERE user_shops.user_id = ?
+                AND user_shops.us_invite = 2
+            ORDER BY user_shops.id ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(shops)
+    }
+
+    /// Get a single shop membership for the user
+    pub async fn get_user_shop(
+        db: &Pool<MySql>,
+        user_id: i32,
+        shop_id: i32,
+    ) -> Result<Option<UserShop>> {
+        let shop = sqlx::query_as::<_, UserShop>(
+            r#"
+            SELECT
+                user_shops.shop_id,
+                shops.shop_name,
+                shops.shop_mother_id,
+                user_shops.shop_role_id,
+                shop_roles.shop_role_name,
+                shop_roles.role_id,
+                shop_roles.sr_discount_type_id,
+                shop_roles.sr_discount
+            FROM user_shops
+            JOIN shops ON user_shops.shop_id = shops.id
+            JOIN shop_roles ON user_shops.shop_role_id = shop_roles.id
+            WHERE user_shops.user_id = ?
+                AND user_shops.shop_id = ?
+                AND user_shops.us_invite = 2
+            "#,
+        )
+        .bind(user_id)
+        .bind(shop_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(shop)
+    }
+
+    /// Get user by ID without shop information
+    pub async fn get_user_account(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            SELECT *
+            FROM users
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(user)
+    }
+
+    /// Get password version and active flag for session validation
+    pub async fn get_user_auth_status(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<Option<UserAuthStatus>> {
+        let status = sqlx::query_as::<_, UserAuthStatus>(
+            r#"
+            SELECT password_version, session_epoch, user_is_active
+            FROM users
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(status)
+    }
+
+    /// Get pending OTP URL (2FA enrollment not yet confirmed)
+    pub async fn get_otp_pending_url(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<Option<String>> {
+        let otp_url: Option<Option<String>> = sqlx::query_scalar(
+            r#"
+            SELECT user_otp_pending_url
+            FROM users
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(otp_url.flatten().filter(|url| !url.is_empty()))
+    }
+
+    /// Update pending OTP URL for 2FA enrollment
+    /// The URL is moved to user_otp_url once the first code is confirmed
+    pub async fn update_otp_pending_url(
+        db: &Pool<MySql>,
+        user_id: i32,
+        otp_url: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET user_otp_pending_url = ?, updated_at = NOW()
+            WHERE id = ?
+            "#,
+        )
+        .bind(otp_url)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -288,6 +640,7 @@ mod tests {
             user_is_active: 1,
             user_otp_url: None,
             password_version: 1,
+            session_epoch: 0,
             created_at: None,
             updated_at: None,
         };
@@ -295,4 +648,67 @@ mod tests {
         let json = serde_json::to_string(&user).unwrap();
         assert!(json.contains("test@example.com"));
     }
+
+    #[test]
+    fn test_password_reset_ends_existing_sessions() {
+        let before = UserAuthStatus {
+            password_version: 3,
+            session_epoch: 1,
+            user_is_active: 1,
+        };
+        assert!(before.accepts_session(3, 1));
+
+        // update_password bumps password_version, so tokens issued before the reset stop working
+        let after = UserAuthStatus {
+            password_version: 4,
+            ..before
+        };
+        assert!(!after.accepts_session(3, 1));
+        assert!(after.accepts_session(4, 1));
+        assert!(!after.accepts_session(4, 0));
+    }
+
+    fn at(h: u32, m: u32) -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_failed_logins_lock_once_at_max() {
+        let mut lock = UserLoginLock {
+            user_failed_login_count: 0,
+            user_last_failed_login_at: None,
+            user_locked_until: None,
+        };
+
+        for minute in 0..4 {
+            lock = lock.after_failure(at(10, minute), 5, 15);
+            assert_eq!(lock.user_locked_until, None);
+        }
+
+        lock = lock.after_failure(at(10, 4), 5, 15);
+        assert_eq!(lock.user_failed_login_count, 5);
+        assert_eq!(lock.user_last_failed_login_at, Some(at(10, 4)));
+        assert_eq!(lock.user_locked_until, Some(at(10, 19)));
+    }
+
+    #[test]
+    fn test_expired_lock_restarts_the_count() {
+        let locked = UserLoginLock {
+            user_failed_login_count: 5,
+            user_last_failed_login_at: Some(at(10, 4)),
+            user_locked_until: Some(at(10, 19)),
+        };
+
+        // One wrong password after the lock expires is not another lockout
+        let lock = locked.after_failure(at(10, 30), 5, 15);
+        assert_eq!(lock.user_failed_login_count, 1);
+        assert_eq!(lock.user_locked_until, None);
+
+        // Four more failures are needed to lock again
+        let lock = (31..34).fold(lock, |lock, minute| lock.after_failure(at(10, minute), 5, 15));
+        assert_eq!(lock.user_locked_until, None);
+        let lock = lock.after_failure(at(10, 34), 5, 15);
+        assert_eq!(lock.user_failed_login_count, 5);
+        assert_eq!(lock.user_locked_until, Some(at(10, 49)));
+    }
 }
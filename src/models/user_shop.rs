@@ -0,0 +1,400 @@
+use sqlx::{FromRow, MySql, Pool};
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use crate::middlewares::hash_token;
+
+/// Invitation states stored in user_shops.us_invite
+pub const INVITE_PENDING: i8 = 1;
+pub const INVITE_ACCEPTED: i8 = 2;
+pub const INVITE_DECLINED: i8 = 3;
+pub const INVITE_CANCELLED: i8 = 4;
+
+/// Shop invitation (a user_shops row)
+/// user_id is NULL until the invitee registers; the invite is then matched by verified email,
+/// or claimed with the code sent by SMS for invitations sent to a phone number
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ShopInvite {
+    pub id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub user_id: Option<i32>,
+    pub shop_role_id: i32,
+    pub us_invite: i8,
+    pub us_invite_email: Option<String>,
+    pub us_invite_tel: Option<String>,
+    pub us_invited_by: Option<i32>,
+    pub us_invited_at: Option<NaiveDateTime>,
+}
+
+/// User shop membership/invitation model with database operations
+pub struct UserShopModel;
+
+impl UserShopModel {
+    /// Create a pending invitation
+    pub async fn create_invite(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        user_id: Option<i32>,
+        shop_role_id: i32,
+        email: Option<&str>,
+        tel: Option<&str>,
+        invited_by: i32,
+    ) -> Result<i32> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO user_shops
+            (shop_id, user_id, shop_role_id, us_invite, us_invite_email, us_invite_tel, us_invited_by, us_invited_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, NOW())
+            "#,
+        )
+        .bind(shop_id)
+        .bind(user_id)
+        .bind(shop_role_id)
+        .bind(INVITE_PENDING)
+        .bind(email)
+        .bind(tel)
+        .bind(invited_by)
+        .execute(db)
+        .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    /// Get invitation by ID
+    pub async fn get_invite(
+        db: &Pool<MySql>,
+        invite_id: i32,
+    ) -> Result<Option<ShopInvite>> {
+        let invite = sqlx::query_as::<_, ShopInvite>(
+            r#"
+            SELECT
+                user_shops.id,
+                user_shops.shop_id,
+                shops.shop_name,
+                user_shops.user_id,
+                user_shops.shop_role_id,
+                user_shops.us_invite,
+                user_shops.us_invite_email,
+                user_shops.us_invite_tel,
+                user_shops.us_invited_by,
+                user_shops.us_invited_at
+            FROM user_shops
+            JOIN shops ON user_shops.shop_id = shops.id
+            WHERE user_shops.id = ?
+            "#,
+        )
+        .bind(invite_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(invite)
+    }
+
+    /// Find a pending invite or accepted membership for the same person in a shop
+    pub async fn find_existing(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        user_id: Option<i32>,
+        email: Option<&str>,
+        tel: Option<&str>,
+    ) -> Result<Option<ShopInvite>> {
+        let invite = sqlx::query_as::<_, ShopInvite>(
+            r#"
+            SELECT
+                user_shops.id,
+                user_shops.shop_id,
+                shops.shop_name,
+                user_shops.user_id,
+                user_shops.shop_role_id,
+                user_shops.us_invite,
+                user_shops.us_invite_email,
+                user_shops.us_invite_tel,
+                user_shops.us_invited_by,
+                user_shops.us_invited_at
+            FROM user_shops
+            JOIN shops ON user_shops.shop_id = shops.id
+            WHERE user_shops.shop_id = ?
+                AND user_shops.us_invite IN (?, ?)
+                AND (
+                    user_shops.user_id = ?
+                    OR user_shops.us_invite_email = ?
+                    OR user_shops.us_invite_tel = ?
+                )
+            LIMIT 1
+            "#,
+        )
+        .bind(shop_id)
+        .bind(INVITE_PENDING)
+        .bind(INVITE_ACCEPTED)
+        .bind(user_id)
+        .bind(email)
+        .bind(tel)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(invite)
+    }
+
+    /// Get pending invitations sent by a shop
+    pub async fn get_shop_invites(
+        db: &Pool<MySql>,
+        shop_id: i32,
+    ) -> Result<Vec<ShopInvite>> {
+        let invites = sqlx::query_as::<_, ShopInvite>(
+            r#"
+            SELECT
+                user_shops.id,
+                user_shops.shop_id,
+                shops.shop_name,
+                user_shops.user_id,
+                user_shops.shop_role_id,
+                user_shops.us_invite,
+                user_shops.us_invite_email,
+                user_shops.us_invite_tel,
+                user_shops.us_invited_by,
+                user_shops.us_invited_at
+            FROM user_shops
+            JOIN shops ON user_shops.shop_id = shops.id
+            WHERE user_shops.shop_id = ?
+                AND user_shops.us_invite = ?
+            ORDER BY user_shops.us_invited_at DESC
+            "#,
+        )
+        .bind(shop_id)
+        .bind(INVITE_PENDING)
+        .fetch_all(db)
+        .await?;
+
+        Ok(invites)
+    }
+
+    /// Get pending invitations for a user
+    pub async fn get_user_invites(
+        db: &Pool<MySql>,
+        user_id: i32,
+    ) -> Result<Vec<ShopInvite>> {
+        let invites = sqlx::query_as::<_, ShopInvite>(
+            r#"
+            SELECT
+                user_shops.id,
+                user_shops.shop_id,
+                shops.shop_name,
+                user_shops.user_id,
+                user_shops.shop_role_id,
+                user_shops.us_invite,
+                user_shops.us_invite_email,
+                user_shops.us_invite_tel,
+                user_shops.us_invited_by,
+                user_shops.us_invited_at
+            FROM user_shops
+            JOIN shops ON user_shops.shop_id = shops.id
+            WHERE user_shops.user_id = ?
+                AND user_shops.us_invite = ?
+            ORDER BY user_shops.us_invited_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(INVITE_PENDING)
+        .fetch_all(db)
+        .await?;
+
+        Ok(invites)
+    }
+
+    /// Attach invitations sent to a verified email before the user registered
+    /// Phone numbers are not verified, so invitations sent by SMS are only attached through
+    /// their code (claim_invite_by_code)
+    pub async fn claim_invites(
+        db: &Pool<MySql>,
+        user_id: i32,
+        email: &str,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_shops
+            SET user_id = ?
+            WHERE user_id IS NULL
+                AND us_invite = ?
+                AND us_invite_email = ?
+            "#,
+        )
+        .bind(user_id)
+        .bind(INVITE_PENDING)
+        .bind(email)
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Store the hash of the code sent by SMS with an invitation, replacing any earlier code
+    pub async fn set_invite_code(
+        db: &Pool<MySql>,
+        invite_id: i32,
+        code_hash: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE user_shops
+            SET us_invite_code_hash = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(code_hash)
+        .bind(invite_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Attach a pending invitation to the user who entered its SMS code; the code is single-use
+    /// The invitation stays pending until the user accepts it
+    /// Returns the invitation ID, or None if no unclaimed invitation has this code
+    pub async fn claim_invite_by_code(
+        db: &Pool<MySql>,
+        user_id: i32,
+        code_hash: &str,
+    ) -> Result<Option<i32>> {
+        let mut tx = db.begin().await?;
+
+        let invite_id: Option<i32> = sqlx::query_scalar(
+            r#"
+            SELECT id
+            FROM user_shops
+            WHERE us_invite_code_hash = ? AND us_invite = ? AND user_id IS NULL
+            FOR UPDATE
+            "#,
+        )
+        .bind(code_hash)
+        .bind(INVITE_PENDING)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(invite_id) = invite_id else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE user_shops
+            SET user_id = ?, us_invite_code_hash = NULL
+            WHERE id = ?
+            "#,
+        )
+        .bind(user_id)
+        .bind(invite_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(invite_id))
+    }
+
+    /// Move a pending invitation to a new state
+    /// Returns false if the invitation is no longer pending
+    pub async fn update_invite_status(
+        db: &Pool<MySql>,
+        invite_id: i32,
+        status: i8,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_shops
+            SET us_invite = ?, us_responded_at = NOW()
+            WHERE id = ? AND us_invite = ?
+            "#,
+        )
+        .bind(status)
+        .bind(invite_id)
+        .bind(INVITE_PENDING)
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Accept every pending invitation sent to the user's verified email (first login)
+    /// Invitations claimed with an SMS code wait for the user to accept them
+    pub async fn accept_all_invites(
+        db: &Pool<MySql>,
+        user_id: i32,
+        email: &str,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_shops
+            SET us_invite = ?, us_responded_at = NOW()
+            WHERE user_id = ? AND us_invite = ? AND us_invite_email = ?
+            "#,
+        )
+        .bind(INVITE_ACCEPTED)
+        .bind(user_id)
+        .bind(INVITE_PENDING)
+        .bind(email)
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Refresh the invited timestamp when an invitation is resent
+    pub async fn touch_invite(
+        db: &Pool<MySql>,
+        invite_id: i32,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE user_shops
+            SET us_invited_at = NOW()
+            WHERE id = ?
+            "#,
+        )
+        .bind(invite_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Check that a shop role belongs to the shop
+    pub async fn shop_role_exists(
+        db: &Pool<MySql>,
+        shop_id: i32,
+        shop_role_id: i32,
+    ) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM shop_roles
+            WHERE id = ? AND shop_id = ?
+            "#,
+        )
+        .bind(shop_role_id)
+        .bind(shop_id)
+        .fetch_one(db)
+        .await?;
+
+        Ok(count > 0)
+    }
+}
+
+/// Hash of an invitation code as typed by the invitee; codes are case-insensitive
+pub fn invite_code_hash(code: &str) -> String {
+    hash_token(&code.trim().to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invite_code_hash_ignores_case_and_spaces() {
+        assert_eq!(invite_code_hash(" ab12cd34 "), invite_code_hash("AB12CD34"));
+        assert_ne!(invite_code_hash("AB12CD34"), invite_code_hash("AB12CD35"));
+        // Only the hash is stored, never the code
+        assert_ne!(invite_code_hash("AB12CD34"), "AB12CD34");
+    }
+}
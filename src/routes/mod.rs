@@ -9,6 +9,7 @@ use axum::{
 use crate::configs::AppState;
 use crate::controllers;
 use crate::middlewares;
+use crate::middlewares::RequirePermission;
 
 /// Create main router with all routes
 /// Equivalent to Go's main.go router setup
@@ -18,13 +19,19 @@ pub fn create_router(state: AppState) -> Router {
         .route("/health", get(health_check))
 
         // Auth routes (public)
-        .nest("/auth", auth_routes())
+        .nest("/auth", auth_routes(&state))
 
         // User routes (protected)
-        .nest("/user", user_routes())
+        .nest("/user", user_routes(&state))
 
         // Order routes (protected)
-        .nest("/order", order_routes())
+        .nest("/order", order_routes(&state))
+
+        // Customer routes (protected)
+        .nest("/customer", customer_routes(&state))
+
+        // Shop settings routes (protected)
+        .nest("/shop", shop_routes(&state))
 
         // Add state
         .with_state(state)
@@ -37,35 +44,231 @@ async fn health_check() -> &'static str {
 
 /// Auth routes
 /// Equivalent to Go's SetRouterAuth
-fn auth_routes() -> Router<AppState> {
+fn auth_routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .route("/login", post(controllers::login))
-        .route("/logout", post(controllers::logout))
+        .route(
+            "/logout",
+            post(controllers::logout)
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+        )
+        .route(
+            "/logout-all",
+            post(controllers::logout_all)
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+        )
         .route(
             "/verify",
             get(controllers::verify_token)
-                .layer(middleware::from_fn(middlewares::check_access_token))
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+        )
+        .route(
+            "/refresh",
+            post(controllers::refresh_token)
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_refresh_token))
+        )
+        .route("/register", post(controllers::register))
+        .route("/verify-email", get(controllers::verify_email))
+        .route("/resend-verification", post(controllers::resend_verification))
+        .route("/forgot-password", post(controllers::forgot_password))
+        .route("/reset-password", post(controllers::reset_password))
+        .route(
+            "/change-password",
+            post(controllers::change_password)
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+        )
+        .route(
+            "/switch-shop",
+            post(controllers::switch_shop)
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+        )
+        .route(
+            "/otp/setup",
+            post(controllers::setup_otp)
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+        )
+        .route(
+            "/otp/verify",
+            post(controllers::verify_otp_setup)
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+        )
+        .route(
+            "/otp/disable",
+            post(controllers::disable_otp)
+                .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
         )
 }
 
 /// User routes
 /// Equivalent to Go's SetRouterUser
-fn user_routes() -> Router<AppState> {
+fn user_routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .route("/me", get(controllers::get_current_user))
         .route("/:id", get(controllers::get_user_detail))
         .route("/", put(controllers::update_user))
         .route("/list", get(controllers::get_shop_users))
-        .layer(middleware::from_fn(middlewares::check_access_token))
+        .route("/pin", put(controllers::set_pin))
+        .route(
+            "/:id/unlock",
+            post(controllers::unlock_user)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "user.unlock"), middlewares::require_permission))
+        )
+        .route(
+            "/invite",
+            post(controllers::invite_user)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "user.invite"), middlewares::require_permission))
+        )
+        .route(
+            "/invite/:id",
+            delete(controllers::cancel_invite)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "user.invite"), middlewares::require_permission))
+        )
+        .route(
+            "/invite/:id/resend",
+            post(controllers::resend_invite)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "user.invite"), middlewares::require_permission))
+        )
+        .route(
+            "/invites",
+            get(controllers::get_shop_invites)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "user.invite"), middlewares::require_permission))
+        )
+        .route("/invites/me", get(controllers::get_my_invites))
+        .route("/invites/claim", post(controllers::claim_invite))
+        .route("/invites/:id/accept", post(controllers::accept_invite))
+        .route("/invites/:id/decline", post(controllers::decline_invite))
+        .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
 }
 
 /// Order routes
 /// Equivalent to Go's SetRouterOrders
-fn order_routes() -> Router<AppState> {
+fn order_routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .route("/search", post(controllers::search_orders))
         .route("/:id", get(controllers::get_order_detail))
-        .route("/", post(controllers::create_order))
-        .route("/:id", delete(controllers::delete_order))
-        .layer(middleware::from_fn(middlewares::check_access_token))
+        .route(
+            "/",
+            post(controllers::create_order)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.create"), middlewares::require_permission))
+        )
+        .route(
+            "/:id",
+            delete(controllers::delete_order)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.delete"), middlewares::require_permission))
+        )
+        .route("/:id/history", get(controllers::get_order_status_history))
+        .route("/:id/refunds", get(controllers::get_order_refunds))
+        .route(
+            "/:id/refunds",
+            post(controllers::create_refund)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.refund"), middlewares::require_permission))
+        )
+        .route(
+            "/:id/tax-invoice",
+            post(controllers::issue_tax_invoice)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.tax_invoice"), middlewares::require_permission))
+        )
+        .route("/:id/receipt.pdf", get(controllers::get_order_document_pdf))
+        .route(
+            "/:id/documents",
+            post(controllers::store_order_document)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.document"), middlewares::require_permission))
+        )
+        .route("/:id/receipt.escpos", get(controllers::get_order_receipt_escpos))
+        .route(
+            "/:id/print",
+            post(controllers::print_order_receipt)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.print"), middlewares::require_permission))
+        )
+        .route("/:id/payments", get(controllers::get_order_payments))
+        .route("/:id/promptpay-qr", get(controllers::get_order_promptpay_qr))
+        .route(
+            "/:id/payments",
+            post(controllers::record_payment)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.payment"), middlewares::require_permission))
+        )
+        .route(
+            "/:id/restore",
+            post(controllers::restore_order)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.restore"), middlewares::require_permission))
+        )
+        .route(
+            "/:id/confirm",
+            post(controllers::confirm_order)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.confirm"), middlewares::require_permission))
+        )
+        .route(
+            "/:id/cancel",
+            post(controllers::cancel_order)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.cancel"), middlewares::require_permission))
+        )
+        .route(
+            "/:id/void",
+            post(controllers::void_order)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "order.void"), middlewares::require_permission))
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+}
+
+/// Customer routes
+fn customer_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/search", post(controllers::search_customers))
+        .route("/duplicates", get(controllers::find_duplicate_customers))
+        .route("/merges", get(controllers::get_customer_merges))
+        .route(
+            "/merges/:id/undo",
+            post(controllers::undo_customer_merge)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "customer.merge"), middlewares::require_permission))
+        )
+        .route("/:id", get(controllers::get_customer_detail))
+        .route("/:id/duplicates", get(controllers::get_customer_duplicates))
+        .route(
+            "/:id/merge",
+            post(controllers::merge_customer)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "customer.merge"), middlewares::require_permission))
+        )
+        .route(
+            "/",
+            post(controllers::create_customer)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "customer.create"), middlewares::require_permission))
+        )
+        .route(
+            "/:id",
+            put(controllers::update_customer)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "customer.update"), middlewares::require_permission))
+        )
+        .route(
+            "/:id",
+            delete(controllers::delete_customer)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "customer.delete"), middlewares::require_permission))
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
+}
+
+/// Shop settings routes
+fn shop_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/document-sequences", get(controllers::get_document_sequences))
+        .route(
+            "/document-sequences",
+            put(controllers::update_document_sequence)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "shop.settings"), middlewares::require_permission))
+        )
+        .route(
+            "/tax-info",
+            put(controllers::update_shop_tax_info)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "shop.settings"), middlewares::require_permission))
+        )
+        .route(
+            "/promptpay",
+            put(controllers::update_shop_promptpay)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "shop.settings"), middlewares::require_permission))
+        )
+        .route(
+            "/receipt-printer",
+            put(controllers::update_receipt_printer)
+                .layer(middleware::from_fn_with_state(RequirePermission::new(state, "shop.settings"), middlewares::require_permission))
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), middlewares::check_access_token))
 }
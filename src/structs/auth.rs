@@ -12,6 +12,9 @@ pub struct LoginRequest {
     pub password: String,
 
     pub otp_code: Option<String>,
+
+    /// Shop to log in to; defaults to the user's first shop
+    pub shop_id: Option<i32>,
 }
 
 /// Login response
@@ -21,6 +24,7 @@ pub struct LoginResponse {
     pub email: String,
     pub fname: String,
     pub lname: String,
+    pub shop_id: i32,
     pub access_token: String,
     pub refresh_token: String,
     pub shops: Vec<ShopAccount>,
@@ -35,10 +39,17 @@ pub struct ShopAccount {
     pub role_id: i32,
 }
 
-/// Refresh token request
+/// Logout request
 #[derive(Debug, Deserialize)]
-pub struct RefreshTokenRequest {
-    pub refresh_token: String,
+pub struct LogoutRequest {
+    /// Refresh token to revoke along with the current access token
+    pub refresh_token: Option<String>,
+}
+
+/// Switch shop request
+#[derive(Debug, Deserialize)]
+pub struct SwitchShopRequest {
+    pub shop_id: i32,
 }
 
 /// Token response
@@ -67,6 +78,26 @@ pub struct RegisterRequest {
     pub tel: String,
 }
 
+/// Register response
+/// Identical whether or not the email was already registered
+#[derive(Debug, Serialize)]
+pub struct RegisterResponse {
+    pub email: String,
+}
+
+/// Email verification query (link from verification email)
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+/// Resend verification email request
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResendVerificationRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
 /// Change password request
 #[derive(Debug, Deserialize, Validate)]
 pub struct ChangePasswordRequest {
@@ -104,3 +135,11 @@ pub struct OtpVerifyRequest {
     pub user_id: i32,
     pub otp_code: String,
 }
+
+/// OTP setup response
+#[derive(Debug, Serialize)]
+pub struct OtpSetupResponse {
+    pub otp_url: String,
+    pub secret: String,
+    pub qr_code: String, // Base64-encoded PNG
+}
@@ -72,6 +72,15 @@ impl PaginationRequest {
     }
 }
 
+/// Sort direction for list endpoints
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
 /// Pagination response
 #[derive(Debug, Serialize)]
 pub struct PaginatedResponse<T> {
@@ -1,11 +1,159 @@
 use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::BTreeMap;
+use super::common::SortDirection;
+use super::order::OrderResponse;
+use crate::models::MatchReason;
+use crate::libs::{is_valid_tax_id, normalize_thai_phone};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CustomerResponse {
     pub id: i32,
     pub shop_id: i32,
+    pub customer_code: String,
     pub fname: String,
     pub lname: String,
     pub tel: String,
     pub email: Option<String>,
+    pub national_id: Option<String>,
+    pub birthdate: Option<NaiveDate>,
+    pub address: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
 }
+
+/// Customer with their latest orders
+#[derive(Debug, Serialize)]
+pub struct CustomerDetailResponse {
+    #[serde(flatten)]
+    pub customer: CustomerResponse,
+    /// Number of orders of the customer; `orders` holds the latest of them, the rest are in
+    /// the order search with customer_id
+    pub order_count: i64,
+    pub orders: Vec<OrderResponse>,
+}
+
+/// Customer details as entered at the front desk
+#[derive(Debug, Deserialize, Validate)]
+pub struct CustomerRequest {
+    #[validate(length(min = 1, max = 100, message = "First name is required (max 100 characters)"))]
+    pub fname: String,
+    #[validate(length(max = 100, message = "Last name must be at most 100 characters"))]
+    #[serde(default)]
+    pub lname: String,
+    /// Any common format ("081-234-5678", "+66 81 234 5678"); stored as digits
+    #[validate(custom = "validate_thai_phone")]
+    pub tel: String,
+    #[validate(email(message = "Invalid email"), length(max = 255, message = "Email must be at most 255 characters"))]
+    pub email: Option<String>,
+    /// 13-digit Thai national ID
+    #[validate(custom = "validate_national_id")]
+    pub national_id: Option<String>,
+    pub birthdate: Option<NaiveDate>,
+    #[validate(length(max = 500, message = "Address must be at most 500 characters"))]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCustomerRequest {
+    #[serde(flatten)]
+    #[validate]
+    pub customer: CustomerRequest,
+    /// Create the customer even if another one has the same phone, email or national ID
+    #[serde(default)]
+    pub allow_duplicate: bool,
+}
+
+/// Customer search filters (query string)
+/// q matches name, phone, email or customer code; the other filters must all match
+#[derive(Debug, Deserialize)]
+pub struct CustomerSearchRequest {
+    pub q: Option<String>,
+    pub name: Option<String>,
+    pub tel: Option<String>,
+    pub email: Option<String>,
+    /// Matches by prefix
+    pub customer_code: Option<String>,
+    pub sort_by: Option<CustomerSortField>,
+    pub sort_dir: Option<SortDirection>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Sortable customer columns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomerSortField {
+    #[default]
+    CreatedAt,
+    CustomerCode,
+    Fname,
+    Lname,
+}
+
+impl CustomerSortField {
+    /// Column name used in ORDER BY
+    pub fn column(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::CustomerCode => "customer_code",
+            Self::Fname => "customer_fname",
+            Self::Lname => "customer_lname",
+        }
+    }
+}
+
+/// Two customers that may be the same person
+#[derive(Debug, Serialize)]
+pub struct DuplicateMatchResponse {
+    pub customer: CustomerResponse,
+    pub duplicate: CustomerResponse,
+    pub reasons: Vec<MatchReason>,
+    /// Similarity of the normalized names, 0.0 to 1.0
+    pub name_similarity: f64,
+}
+
+/// Customer to fold into the customer in the path, which is kept
+#[derive(Debug, Deserialize)]
+pub struct MergeCustomerRequest {
+    pub duplicate_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CustomerMergeResponse {
+    pub id: i32,
+    /// Surviving customer
+    pub target_id: i32,
+    /// Merged customer, soft-deleted until the merge is undone
+    pub source_id: i32,
+    /// Number of rows moved to the target per table
+    pub moved: BTreeMap<String, usize>,
+    pub merged_by: i32,
+    /// UTC
+    pub merged_at: NaiveDateTime,
+    /// UTC
+    pub undo_until: NaiveDateTime,
+    /// UTC
+    pub undone_at: Option<NaiveDateTime>,
+}
+
+pub fn validate_thai_phone(tel: &str) -> Result<(), ValidationError> {
+    if normalize_thai_phone(tel).is_some() {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("tel");
+        error.message = Some("Phone must be a Thai mobile (10 digits) or landline (9 digits) number".into());
+        Err(error)
+    }
+}
+
+pub fn validate_national_id(national_id: &str) -> Result<(), ValidationError> {
+    if is_valid_tax_id(national_id) {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("national_id");
+        error.message = Some("National ID must be 13 digits with a valid check digit".into());
+        Err(error)
+    }
+}
+
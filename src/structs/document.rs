@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use crate::libs::{DocumentKind, PaperSize};
+
+/// Printed document options: query string when downloading the PDF, JSON body when storing it
+#[derive(Debug, Default, Deserialize)]
+pub struct PrintDocumentQuery {
+    /// receipt (default), abbreviated_tax_invoice, full_tax_invoice or credit_note
+    pub kind: Option<DocumentKind>,
+    /// thermal80 (default) or a4
+    pub paper: Option<PaperSize>,
+    /// Credit note to print, the latest one by default
+    pub refund_id: Option<i32>,
+}
+
+/// PDF kept in S3
+#[derive(Debug, Serialize)]
+pub struct StoredDocumentResponse {
+    pub number: String,
+    pub url: String,
+}
+
+/// Thermal receipt to print: query string when the print agent fetches the ESC/POS job, JSON
+/// body when sending it to the shop's network printer
+#[derive(Debug, Default, Deserialize)]
+pub struct ThermalPrintRequest {
+    /// receipt (default), abbreviated_tax_invoice, full_tax_invoice or credit_note
+    pub kind: Option<DocumentKind>,
+    /// Credit note to print, the latest one by default
+    pub refund_id: Option<i32>,
+}
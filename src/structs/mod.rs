@@ -4,12 +4,22 @@
 pub mod auth;
 pub mod user;
 pub mod order;
+pub mod payment;
+pub mod refund;
+pub mod tax_invoice;
+pub mod document;
 pub mod customer;
+pub mod shop;
 pub mod common;
 
 // Re-export commonly used structs
 pub use auth::*;
 pub use user::*;
 pub use order::*;
+pub use payment::*;
+pub use refund::*;
+pub use tax_invoice::*;
+pub use document::*;
 pub use customer::*;
+pub use shop::*;
 pub use common::*;
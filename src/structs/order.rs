@@ -1,4 +1,12 @@
 use serde::{Deserialize, Serialize};
+use validator::Validate;
+use chrono::NaiveDate;
+use super::common::SortDirection;
+use super::payment::PaymentSummaryResponse;
+use super::refund::CreditNoteResponse;
+use super::tax_invoice::TaxInvoiceResponse;
+use crate::models::{OrderStatus, PaymentMethod};
+use crate::libs::{Discount, Money, VatType};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrderResponse {
@@ -6,29 +14,149 @@ pub struct OrderResponse {
     pub shop_id: i32,
     pub customer_id: i32,
     pub order_code: String,
-    pub order_total: f64,
-    pub order_discount: f64,
-    pub order_net: f64,
-    pub order_status: i8,
+    pub order_total: Money,
+    pub order_discount: Money,
+    pub order_net: Money,
+    pub order_vat: Money,
+    pub order_status: OrderStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Order with its line items
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderDetailResponse {
+    pub id: i32,
+    pub shop_id: i32,
+    pub customer_id: i32,
+    pub order_code: String,
+    pub order_total: Money,
+    pub order_discount: Money,
+    pub order_net: Money,
+    /// VAT breakdown: order_vatable + order_vat + order_exempt = order_net
+    pub order_vatable: Money,
+    pub order_vat: Money,
+    pub order_exempt: Money,
+    pub order_status: OrderStatus,
+    pub discount_approved_by: Option<i32>,
+    pub items: Vec<OrderItemResponse>,
+    pub payment: PaymentSummaryResponse,
+    pub refunds: Vec<CreditNoteResponse>,
+    pub tax_invoice: Option<TaxInvoiceResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderItemResponse {
+    pub id: i32,
+    pub product_id: i32,
+    pub product_name: String,
+    pub quantity: i32,
+    pub price: Money,
+    pub discount: Money,
+    pub total: Money,
+    pub vat_type: VatType,
+    pub vat: Money,
+    /// Paid for the line after the bill discount, VAT included
+    pub net: Money,
+    pub refunded_quantity: i64,
+    pub refunded_amount: Money,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateOrderRequest {
     pub customer_id: i32,
+    #[validate(length(min = 1, message = "Order must have at least one item"))]
+    #[validate]
     pub items: Vec<OrderItem>,
+    /// Bill-level discount, applied after item discounts
+    pub discount: Option<Discount>,
+    /// Required when the total discount exceeds the staff member's cap
+    pub discount_approval: Option<DiscountApproval>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Manager override: a second user's credentials, with either password or PIN
+#[derive(Debug, Deserialize)]
+pub struct DiscountApproval {
+    pub username: String,
+    pub password: Option<String>,
+    pub pin: Option<String>,
+}
+
+/// Requested line item; the price is always taken from the product
+#[derive(Debug, Deserialize, Serialize, Validate)]
 pub struct OrderItem {
     pub product_id: i32,
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
     pub quantity: i32,
-    pub price: f64,
+    pub discount: Option<Discount>,
 }
 
+/// Order search filters (query string)
+/// date_from/date_to are inclusive dates (YYYY-MM-DD), order_code matches by prefix
 #[derive(Debug, Deserialize)]
 pub struct OrderSearchRequest {
     pub customer_id: Option<i32>,
-    pub status: Option<i8>,
+    pub status: Option<OrderStatus>,
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    pub order_code: Option<String>,
+    pub min_net: Option<Money>,
+    pub max_net: Option<Money>,
+    pub sort_by: Option<OrderSortField>,
+    pub sort_dir: Option<SortDirection>,
+    /// Include soft-deleted orders (requires order.view_deleted)
+    pub include_deleted: Option<bool>,
     pub page: Option<i64>,
     pub limit: Option<i64>,
 }
+
+/// Sortable order columns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSortField {
+    #[default]
+    OrderDate,
+    OrderCode,
+    OrderNet,
+    CreatedAt,
+}
+
+impl OrderSortField {
+    /// Column name used in ORDER BY
+    pub fn column(self) -> &'static str {
+        match self {
+            Self::OrderDate => "order_date",
+            Self::OrderCode => "order_code",
+            Self::OrderNet => "order_net",
+            Self::CreatedAt => "created_at",
+        }
+    }
+}
+
+/// Reason required to cancel or delete an order
+#[derive(Debug, Deserialize, Validate)]
+pub struct OrderReasonRequest {
+    #[validate(length(min = 1, max = 255, message = "Reason is required (max 255 characters)"))]
+    pub reason: String,
+}
+
+/// Void a paid order; everything received goes back on one credit note
+#[derive(Debug, Deserialize, Validate)]
+pub struct VoidOrderRequest {
+    #[validate(length(min = 1, max = 255, message = "Reason is required (max 255 characters)"))]
+    pub reason: String,
+    /// How the money goes back to the customer
+    pub method: PaymentMethod,
+    /// Put the order's products back in stock
+    #[serde(default)]
+    pub restock: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderStatusHistoryResponse {
+    pub from_status: Option<OrderStatus>,
+    pub to_status: OrderStatus,
+    pub reason: Option<String>,
+    pub changed_by: i32,
+    pub changed_at: chrono::NaiveDateTime,
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use crate::models::{OrderStatus, PaymentMethod};
+use crate::libs::Money;
+
+/// Take payment on an order with one or more tenders (split payment)
+/// A deposit is a partial payment taken on a draft or confirmed order
+#[derive(Debug, Deserialize, Validate)]
+pub struct RecordPaymentRequest {
+    #[validate(length(min = 1, max = 10, message = "Payment must have 1 to 10 tenders"))]
+    #[validate]
+    pub tenders: Vec<TenderRequest>,
+    #[serde(default)]
+    pub is_deposit: bool,
+}
+
+/// One tender; for cash, amount is what the customer handed over
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct TenderRequest {
+    pub method: PaymentMethod,
+    pub amount: Money,
+    /// Card approval code, transfer or PromptPay reference
+    #[validate(length(max = 100, message = "Reference must be at most 100 characters"))]
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentResponse {
+    pub id: i32,
+    pub receipt_number: String,
+    pub method: PaymentMethod,
+    pub amount: Money,
+    pub tendered: Money,
+    pub change: Money,
+    pub reference: Option<String>,
+    pub is_deposit: bool,
+    pub created_by: i32,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// Payment totals of an order, included in the order detail
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentSummaryResponse {
+    pub paid: Money,
+    pub deposit: Money,
+    pub outstanding: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderPaymentsResponse {
+    pub order_id: i32,
+    pub order_net: Money,
+    pub order_status: OrderStatus,
+    pub summary: PaymentSummaryResponse,
+    pub payments: Vec<PaymentResponse>,
+}
+
+/// Result of taking a payment; change is the cash to hand back
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordPaymentResponse {
+    pub order_status: OrderStatus,
+    pub receipt_number: String,
+    pub change: Money,
+    pub summary: PaymentSummaryResponse,
+    pub payments: Vec<PaymentResponse>,
+}
+
+/// PromptPay QR for what is left to pay on an order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromptPayQrResponse {
+    pub order_id: i32,
+    pub amount: Money,
+    /// EMVCo payload encoded in the QR code
+    pub payload: String,
+    pub qr_code: String, // Base64-encoded PNG
+}
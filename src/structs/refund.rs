@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use crate::models::{OrderStatus, PaymentMethod};
+use crate::libs::Money;
+
+/// Refund an order; without items, everything not yet refunded is refunded
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateRefundRequest {
+    /// How the money goes back to the customer
+    pub method: PaymentMethod,
+    #[validate(length(min = 1, max = 255, message = "Reason is required (max 255 characters)"))]
+    pub reason: String,
+    #[validate]
+    pub items: Option<Vec<RefundItemRequest>>,
+    /// Put all refunded products back in stock (full refunds only; per item otherwise)
+    #[serde(default)]
+    pub restock: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct RefundItemRequest {
+    pub order_item_id: i32,
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
+    pub quantity: i32,
+    /// Return the refunded units to product stock
+    #[serde(default)]
+    pub restock: bool,
+}
+
+/// Refund as a credit note
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditNoteResponse {
+    pub id: i32,
+    pub credit_note_number: String,
+    pub method: PaymentMethod,
+    pub amount: Money,
+    pub reason: String,
+    pub items: Vec<CreditNoteItemResponse>,
+    pub created_by: i32,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditNoteItemResponse {
+    pub order_item_id: i32,
+    pub quantity: i32,
+    pub amount: Money,
+    pub restock: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRefundResponse {
+    pub order_status: OrderStatus,
+    pub credit_note: CreditNoteResponse,
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+use super::tax_invoice::{validate_branch_code, validate_tax_id};
+use crate::models::{DocumentType, SequenceReset};
+use crate::libs::{parse_printer_address, printer_ports, PromptPayId};
+
+/// Numbering format of one document type
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentSequenceResponse {
+    pub document_type: DocumentType,
+    pub prefix: String,
+    pub reset: SequenceReset,
+    pub padding: i32,
+    /// Number the next document will get, e.g. "INV2569-00043"
+    pub next_number: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateDocumentSequenceRequest {
+    pub document_type: DocumentType,
+    #[validate(length(max = 10, message = "Prefix must be at most 10 characters"))]
+    pub prefix: String,
+    pub reset: SequenceReset,
+    #[validate(range(min = 1, max = 10, message = "Padding must be between 1 and 10"))]
+    pub padding: i32,
+}
+
+/// Shop VAT registration, printed as the seller on tax invoices
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateShopTaxInfoRequest {
+    #[validate(custom = "validate_tax_id")]
+    pub tax_id: String,
+    #[validate(custom = "validate_branch_code")]
+    pub branch_code: String,
+}
+
+/// Shop's PromptPay account
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdatePromptPayRequest {
+    /// Mobile number (10 digits), tax ID (13 digits) or e-wallet ID (15 digits); dashes and
+    /// spaces are ignored
+    #[validate(custom = "validate_promptpay_id")]
+    pub promptpay_id: String,
+}
+
+pub fn validate_promptpay_id(promptpay_id: &str) -> Result<(), ValidationError> {
+    if PromptPayId::parse(promptpay_id).is_some() {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("promptpay_id");
+        error.message = Some("PromptPay ID must be a mobile number, a valid tax ID or a 15-digit e-wallet ID".into());
+        Err(error)
+    }
+}
+
+/// Thermal receipt settings; leave a field out to clear it
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateReceiptPrinterRequest {
+    /// Logo printed at the top of thermal receipts (PNG or JPEG, https, at most 1 MB)
+    #[validate(
+        url(message = "Logo must be a URL"),
+        custom = "validate_logo_url",
+        length(max = 500, message = "Logo URL must be at most 500 characters")
+    )]
+    pub logo_url: Option<String>,
+    /// Network printer on the shop's LAN as "ip" or "ip:port" (raw port 9100 by default)
+    #[validate(custom = "validate_printer_address")]
+    pub printer_address: Option<String>,
+}
+
+pub fn validate_logo_url(url: &str) -> Result<(), ValidationError> {
+    if url.starts_with("https://") {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("logo_url");
+        error.message = Some("Logo URL must use https".into());
+        Err(error)
+    }
+}
+
+pub fn validate_printer_address(address: &str) -> Result<(), ValidationError> {
+    match parse_printer_address(address, &printer_ports()) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let mut error = ValidationError::new("printer_address");
+            error.message = Some(e.to_string().into());
+            Err(error)
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+use crate::libs::{is_valid_branch_code, is_valid_tax_id};
+
+/// Buyer details for a full tax invoice
+#[derive(Debug, Deserialize, Validate)]
+pub struct IssueTaxInvoiceRequest {
+    #[validate(length(min = 1, max = 255, message = "Customer name is required (max 255 characters)"))]
+    pub customer_name: String,
+    #[validate(custom = "validate_tax_id")]
+    pub tax_id: String,
+    /// 5 digits, "00000" for the head office
+    #[validate(custom = "validate_branch_code")]
+    pub branch_code: String,
+    #[validate(length(min = 1, max = 500, message = "Address is required (max 500 characters)"))]
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxInvoiceResponse {
+    pub id: i32,
+    pub tax_invoice_number: String,
+    pub customer_name: String,
+    pub tax_id: String,
+    pub branch_code: String,
+    pub address: String,
+    pub issued_by: i32,
+    pub issued_at: chrono::NaiveDateTime,
+}
+
+pub fn validate_tax_id(tax_id: &str) -> Result<(), ValidationError> {
+    if is_valid_tax_id(tax_id) {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("tax_id");
+        error.message = Some("Tax ID must be 13 digits with a valid check digit".into());
+        Err(error)
+    }
+}
+
+pub fn validate_branch_code(branch_code: &str) -> Result<(), ValidationError> {
+    if is_valid_branch_code(branch_code) {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("branch_code");
+        error.message = Some("Branch code must be 5 digits (00000 for head office)".into());
+        Err(error)
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserResponse {
@@ -17,3 +18,44 @@ pub struct UpdateUserRequest {
     pub lname: String,
     pub tel: String,
 }
+
+/// Invite user to shop request (by email or phone)
+#[derive(Debug, Deserialize, Validate)]
+pub struct InviteUserRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: Option<String>,
+
+    #[validate(length(min = 10, max = 10, message = "Phone number must be 10 digits"))]
+    pub tel: Option<String>,
+
+    pub shop_role_id: i32,
+}
+
+/// Claim an SMS invitation with its code
+#[derive(Debug, Deserialize, Validate)]
+pub struct ClaimInviteRequest {
+    #[validate(length(min = 1, max = 32, message = "Invitation code is required"))]
+    pub code: String,
+}
+
+/// Shop invitation response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShopInviteResponse {
+    pub id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub shop_role_id: i32,
+    pub email: Option<String>,
+    pub tel: Option<String>,
+    pub status: i8,
+    pub invited_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Set the PIN used to approve discounts for other staff
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetPinRequest {
+    #[validate(length(min = 1, message = "Current password is required"))]
+    pub password: String,
+    #[validate(length(min = 4, max = 8, message = "PIN must be 4-8 digits"))]
+    pub pin: String,
+}
@@ -0,0 +1,128 @@
+#!/usr/bin/env python3
+"""Generate ThaiTestBoxes.ttf, the font used by the PDF rendering tests.
+
+Every character is a plain box, so the file is tiny and has no third-party glyphs.
+It covers printable ASCII and the Thai block; Thai vowels and tone marks written
+above or below a consonant have no advance width, as in a real Thai font.
+
+    python3 tests/fixtures/make_test_font.py
+"""
+import os
+import struct
+
+UNITS_PER_EM = 1000
+ASCENT, DESCENT = 800, -200
+ADVANCE = 600
+
+RANGES = [(0x20, 0x7E), (0x0E01, 0x0E3A), (0x0E3F, 0x0E5B)]
+COMBINING = {0x0E31, *range(0x0E34, 0x0E3B), *range(0x0E47, 0x0E4F)}
+
+codepoints = [c for start, end in RANGES for c in range(start, end + 1)]
+num_glyphs = len(codepoints) + 1  # glyph 0 is .notdef
+
+
+def box(x0, y0, x1, y1):
+    """Simple glyph with one rectangular contour"""
+    points = [(x0, y0), (x0, y1), (x1, y1), (x1, y0)]
+    data = struct.pack(">hhhhhH", 1, x0, y0, x1, y1, 3) + struct.pack(">H", 0)
+    data += bytes([0x01] * 4)
+    px = py = 0
+    xs = ys = b""
+    for x, y in points:
+        xs += struct.pack(">h", x - px)
+        ys += struct.pack(">h", y - py)
+        px, py = x, y
+    data += xs + ys
+    return data + b"\0" * (-len(data) % 4)
+
+
+glyphs, metrics = [box(50, 0, 550, 700)], [(ADVANCE, 50)]
+for c in codepoints:
+    if c == 0x20:
+        glyphs.append(b"")
+        metrics.append((ADVANCE, 0))
+    elif c in COMBINING:
+        glyphs.append(box(-300, 750, -100, 800) if c < 0x0E38 or c > 0x0E3A else box(-300, -150, -100, -100))
+        metrics.append((0, -300))
+    else:
+        glyphs.append(box(50, 0, 550, 700))
+        metrics.append((ADVANCE, 50))
+
+glyf = b"".join(glyphs)
+offsets = [0]
+for g in glyphs:
+    offsets.append(offsets[-1] + len(g))
+loca = struct.pack(">%dI" % len(offsets), *offsets)
+hmtx = b"".join(struct.pack(">Hh", a, l) for a, l in metrics)
+
+# cmap format 4, one segment per range mapped to consecutive glyphs
+segments, glyph = [], 1
+for start, end in RANGES:
+    segments.append((start, end, (glyph - start) % 0x10000))
+    glyph += end - start + 1
+segments.append((0xFFFF, 0xFFFF, 1))
+seg_x2 = len(segments) * 2
+search = 2 * 2 ** (len(segments).bit_length() - 1)
+sub = struct.pack(">HHHHHH", 4, 16 + 4 * seg_x2, 0, seg_x2, search, (search // 2).bit_length() - 1)
+sub = sub[:12] + struct.pack(">H", seg_x2 - search)
+sub += struct.pack(">%dH" % len(segments), *[s[1] for s in segments]) + struct.pack(">H", 0)
+sub += struct.pack(">%dH" % len(segments), *[s[0] for s in segments])
+sub += struct.pack(">%dH" % len(segments), *[s[2] for s in segments])
+sub += struct.pack(">%dH" % len(segments), *[0] * len(segments))
+cmap = struct.pack(">HHHHI", 0, 1, 3, 1, 12) + sub
+
+head = struct.pack(
+    ">IIIIHHqqhhhhHHhhh",
+    0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, UNITS_PER_EM, 0, 0,
+    -300, -200, 550, 800, 0, 8, 2, 1, 0,
+)
+hhea = struct.pack(
+    ">IhhhHhhhhhh4hhH",
+    0x00010000, ASCENT, DESCENT, 0, ADVANCE, -300, 0, 550, 1, 0, 0, 0, 0, 0, 0, 0, num_glyphs,
+)
+maxp = struct.pack(">IHHHHHHHHHHHHHH", 0x00010000, num_glyphs, 4, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0)
+os2 = struct.pack(
+    ">HhHHHhhhhhhhhhhh10sIIII4sHHHhhhHHIIhhHHH",
+    4, ADVANCE, 400, 5, 0, 650, 700, 0, 140, 650, 700, 0, 480, 50, 250, 0, b"\0" * 10,
+    1 | 1 << 24, 0, 0, 0, b"NONE", 0x40, 0x20, 0x0E5B, ASCENT, DESCENT, 0, ASCENT, -DESCENT,
+    1 | 1 << 16, 0, 500, 700, 0, 0x20, 2,
+)
+post = struct.pack(">IIhhIIIII", 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0)
+
+names = {0: "Generated for tests, no third-party glyphs", 1: "Thai Test Boxes", 2: "Regular",
+         4: "Thai Test Boxes Regular", 6: "ThaiTestBoxes-Regular"}
+records, strings = b"", b""
+for name_id, text in names.items():
+    encoded = text.encode("utf-16-be")
+    records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+    strings += encoded
+name = struct.pack(">HHH", 0, len(names), 6 + len(records)) + records + strings
+
+tables = {"OS/2": os2, "cmap": cmap, "glyf": glyf, "head": head, "hhea": hhea,
+          "hmtx": hmtx, "loca": loca, "maxp": maxp, "name": name, "post": post}
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+num_tables = len(tables)
+entry = 2 ** (num_tables.bit_length() - 1)
+font = struct.pack(">IHHHH", 0x00010000, num_tables, entry * 16, entry.bit_length() - 1, num_tables * 16 - entry * 16)
+offset = 12 + 16 * num_tables
+directory, body = b"", b""
+for tag in sorted(tables):
+    data = tables[tag]
+    directory += struct.pack(">4sIII", tag.encode(), checksum(data), offset + len(body), len(data))
+    body += data + b"\0" * (-len(data) % 4)
+font += directory + body
+
+adjustment = (0xB1B0AFBA - checksum(font)) & 0xFFFFFFFF
+head_offset = offset + sum(len(tables[t]) + (-len(tables[t]) % 4) for t in sorted(tables) if t < "head")
+font = font[:head_offset + 8] + struct.pack(">I", adjustment) + font[head_offset + 12:]
+
+path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "ThaiTestBoxes.ttf")
+with open(path, "wb") as f:
+    f.write(font)
+print("wrote %s (%d bytes, %d glyphs)" % (path, len(font), num_glyphs))